@@ -0,0 +1,30 @@
+(() => {
+  console.log("load session-watch.js");
+  if (window.__refinedLineSessionWatchInstalled) return;
+  window.__refinedLineSessionWatchInstalled = true;
+
+  let lastUrl = null;
+
+  const report = () => {
+    const url = window.location.href;
+    if (url === lastUrl) return;
+    lastUrl = url;
+    const invoke = window.__TAURI__?.core?.invoke;
+    if (!invoke) return;
+    invoke("report_session_route", { url }).catch(() => {});
+  };
+
+  ["pushState", "replaceState"].forEach((name) => {
+    const original = history[name];
+    if (typeof original !== "function") return;
+    history[name] = function (...args) {
+      const result = original.apply(this, args);
+      report();
+      return result;
+    };
+  });
+
+  window.addEventListener("hashchange", report);
+  window.addEventListener("popstate", report);
+  report();
+})();
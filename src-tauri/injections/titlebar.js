@@ -111,6 +111,13 @@
     .item:hover { background: #fafafa; }
     .item:active { background: #fafafa; }
 
+    .item:disabled {
+      cursor: default;
+      opacity: 0.5;
+    }
+
+    .item:disabled:hover { background: transparent; }
+
     .check {
       width: 16px;
       height: 16px;
@@ -254,6 +261,16 @@
     autostart: "menu.autostart",
     startMinimized: "menu.start_minimized",
     resetProfile: "menu.reset_profile",
+    clearCache: "menu.clear_cache",
+    openDataFolder: "menu.open_data_folder",
+    createProfile: "menu.create_profile",
+    exportData: "menu.export_data",
+    importData: "menu.import_data",
+    backupProfile: "menu.backup_profile",
+    restoreProfile: "menu.restore_profile",
+    exportCookies: "menu.export_cookies",
+    importCookies: "menu.import_cookies",
+    exportCookieTimeline: "menu.export_cookie_timeline",
     logError: "menu.log.error",
     logWarn: "menu.log.warn",
     logInfo: "menu.log.info",
@@ -261,6 +278,21 @@
     logVerbose: "menu.log.verbose"
   };
 
+  const MANAGED_HINT = "組織によって管理されています";
+
+  const LOCKED_SETTING_MENU_IDS = {
+    contentProtection: [MENU_IDS.contentProtection],
+    autoStart: [MENU_IDS.autostart],
+    startMinimized: [MENU_IDS.startMinimized],
+    logLevel: [
+      MENU_IDS.logError,
+      MENU_IDS.logWarn,
+      MENU_IDS.logInfo,
+      MENU_IDS.logDebug,
+      MENU_IDS.logVerbose
+    ]
+  };
+
   const menuItemElements = new Map();
 
   const getTauriInvoke = () => window.__TAURI__?.core?.invoke;
@@ -306,6 +338,51 @@
     type: "action"
   };
 
+  const openDataFolderItem = {
+    id: MENU_IDS.openDataFolder,
+    label: "データフォルダを開く",
+    type: "action"
+  };
+
+  const clearCacheItem = {
+    id: MENU_IDS.clearCache,
+    label: "キャッシュを削除",
+    type: "action"
+  };
+
+  const dataTransferItems = [
+    {
+      id: MENU_IDS.exportData,
+      label: "設定をエクスポート…",
+      type: "action"
+    },
+    {
+      id: MENU_IDS.importData,
+      label: "設定をインポート…",
+      type: "action"
+    },
+    {
+      id: MENU_IDS.backupProfile,
+      label: "プロファイルをバックアップ",
+      type: "action"
+    },
+    {
+      id: MENU_IDS.restoreProfile,
+      label: "プロファイルを復元…",
+      type: "action"
+    },
+    {
+      id: MENU_IDS.exportCookies,
+      label: "ログイン情報をエクスポート…",
+      type: "action"
+    },
+    {
+      id: MENU_IDS.importCookies,
+      label: "ログイン情報をインポート…",
+      type: "action"
+    }
+  ];
+
   const logMenuItems = [
     {
       id: MENU_IDS.logError,
@@ -339,10 +416,56 @@
     }
   ];
 
+  const cookieTimelineItem = {
+    id: MENU_IDS.exportCookieTimeline,
+    label: "Cookie の変更履歴をエクスポート…",
+    type: "action"
+  };
+
+  const PROFILE_SWITCH_PREFIX = "profile.switch.";
+  const PROFILE_OPEN_PREFIX = "profile.open.";
+  let profileMenuItems = [];
+  let activeProfileId = null;
+  let menuIsDev = false;
+
+  const buildProfileMenuItems = (profileList) => {
+    const open = new Set(profileList?.open || []);
+    const profiles = profileList?.profiles || [];
+    const items = profiles.map((profile) => ({
+      id: `${PROFILE_SWITCH_PREFIX}${profile.id}`,
+      label: `プロファイル: ${profile.name}`,
+      type: "radio"
+    }));
+    profiles
+      .filter((profile) => !open.has(profile.id))
+      .forEach((profile) => {
+        items.push({
+          id: `${PROFILE_OPEN_PREFIX}${profile.id}`,
+          label: `${profile.name} を別ウィンドウで開く`,
+          type: "action"
+        });
+      });
+    items.push({
+      id: MENU_IDS.createProfile,
+      label: "新しいプロファイル…",
+      type: "action"
+    });
+    return items;
+  };
+
   const buildMenuModel = (isDev) => {
-    const items = [...baseMenuItems, { type: "separator" }, resetProfileItem];
+    const items = [
+      ...baseMenuItems,
+      { type: "separator" },
+      ...profileMenuItems,
+      { type: "separator" },
+      ...dataTransferItems,
+      openDataFolderItem,
+      clearCacheItem,
+      resetProfileItem
+    ];
     if (isDev) {
-      items.push({ type: "separator" }, ...logMenuItems);
+      items.push({ type: "separator" }, ...logMenuItems, cookieTimelineItem);
     }
     items.push({ type: "separator" });
     items.push({
@@ -417,6 +540,128 @@
           }
           return;
         }
+        if (item.id.startsWith(PROFILE_OPEN_PREFIX)) {
+          const invoke = getTauriInvoke();
+          if (!invoke) return;
+          try {
+            await invoke("menu_action", { id: item.id });
+          } catch (error) {
+            console.warn("[menu] open profile failed", error);
+          }
+          return;
+        }
+        if (item.id === MENU_IDS.createProfile) {
+          const invoke = getTauriInvoke();
+          if (!invoke) return;
+          const name = window.prompt("新しいプロファイルの名前");
+          if (!name || !name.trim()) return;
+          try {
+            await invoke("create_profile", { name });
+          } catch (error) {
+            console.warn("[menu] create profile failed", error);
+          }
+          await loadProfileMenu();
+          return;
+        }
+        if (item.id === MENU_IDS.openDataFolder) {
+          const invoke = getTauriInvoke();
+          if (!invoke) return;
+          try {
+            await invoke("open_data_folder");
+          } catch (error) {
+            console.warn("[menu] open data folder failed", error);
+          }
+          return;
+        }
+        if (item.id === MENU_IDS.clearCache) {
+          const invoke = getTauriInvoke();
+          if (!invoke) return;
+          try {
+            await invoke("clear_cache");
+          } catch (error) {
+            console.warn("[menu] clear cache failed", error);
+          }
+          return;
+        }
+        if (item.id === MENU_IDS.exportData) {
+          const invoke = getTauriInvoke();
+          if (!invoke) return;
+          try {
+            await invoke("export_app_data");
+          } catch (error) {
+            console.warn("[menu] export failed", error);
+          }
+          return;
+        }
+        if (item.id === MENU_IDS.importData) {
+          const invoke = getTauriInvoke();
+          if (!invoke) return;
+          try {
+            await invoke("import_app_data");
+          } catch (error) {
+            console.warn("[menu] import failed", error);
+          }
+          await refreshMenuState();
+          return;
+        }
+        if (item.id === MENU_IDS.backupProfile) {
+          const invoke = getTauriInvoke();
+          if (!invoke) return;
+          try {
+            await invoke("backup_profile");
+          } catch (error) {
+            console.warn("[menu] profile backup failed", error);
+          }
+          return;
+        }
+        if (item.id === MENU_IDS.restoreProfile) {
+          const invoke = getTauriInvoke();
+          if (!invoke) return;
+          try {
+            await invoke("restore_profile_backup");
+          } catch (error) {
+            console.warn("[menu] profile restore failed", error);
+          }
+          return;
+        }
+        if (item.id === MENU_IDS.exportCookies) {
+          const invoke = getTauriInvoke();
+          if (!invoke) return;
+          const passphrase = window.prompt("ファイルを暗号化するパスフレーズ");
+          if (!passphrase) return;
+          if (window.prompt("確認のためもう一度入力してください") !== passphrase) {
+            window.alert("パスフレーズが一致しません。");
+            return;
+          }
+          try {
+            await invoke("export_cookies", { passphrase });
+          } catch (error) {
+            console.warn("[menu] cookie export failed", error);
+          }
+          return;
+        }
+        if (item.id === MENU_IDS.importCookies) {
+          const invoke = getTauriInvoke();
+          if (!invoke) return;
+          const passphrase = window.prompt("エクスポート時のパスフレーズ");
+          if (!passphrase) return;
+          try {
+            await invoke("import_cookies", { passphrase });
+          } catch (error) {
+            console.warn("[menu] cookie import failed", error);
+          }
+          return;
+        }
+        if (item.id === MENU_IDS.exportCookieTimeline) {
+          const invoke = getTauriInvoke();
+          if (!invoke) return;
+          try {
+            await invoke("export_cookie_timeline");
+          } catch (error) {
+            console.warn("[menu] cookie timeline export failed", error);
+          }
+          return;
+        }
         if (item.id === "window.close") {
           const currentWindow = getCurrentWindow();
           if (currentWindow) {
@@ -469,20 +714,75 @@
     });
   };
 
+  const setMenuItemLocked = (id, locked) => {
+    const button = menuItemElements.get(id);
+    if (!button) return;
+    button.disabled = locked;
+    button.title = locked ? MANAGED_HINT : "";
+    const label = button.querySelector(".label");
+    if (label && button.dataset.baseLabel === undefined) {
+      button.dataset.baseLabel = label.textContent;
+    }
+    if (label) {
+      label.textContent = locked
+        ? `${button.dataset.baseLabel}（${MANAGED_HINT}）`
+        : button.dataset.baseLabel;
+    }
+  };
+
+  const applyLockedSettings = (lockedKeys) => {
+    const locked = new Set(lockedKeys || []);
+    Object.entries(LOCKED_SETTING_MENU_IDS).forEach(([key, ids]) => {
+      ids.forEach((id) => {
+        setMenuItemLocked(id, locked.has(key));
+      });
+    });
+  };
+
+  const applySettingsToMenu = (settings) => {
+    setMenuItemChecked(MENU_IDS.autostart, !!settings?.autoStart);
+    setMenuItemChecked(MENU_IDS.startMinimized, !!settings?.startMinimized);
+    setLogLevelChecked(settings?.logLevel || "info");
+  };
+
+  const applyActiveProfile = () => {
+    profileMenuItems
+      .filter((item) => item.id.startsWith(PROFILE_SWITCH_PREFIX))
+      .forEach((item) => {
+        setMenuItemChecked(item.id, item.id === `${PROFILE_SWITCH_PREFIX}${activeProfileId}`);
+      });
+  };
+
+  const loadProfileMenu = async () => {
+    const invoke = getTauriInvoke();
+    if (!invoke) return;
+
+    try {
+      const profileList = await invoke("list_profiles");
+      activeProfileId = profileList?.active || null;
+      profileMenuItems = buildProfileMenuItems(profileList);
+      renderMenu(buildMenuModel(menuIsDev));
+      await refreshMenuState();
+    } catch (error) {
+      console.warn("[menu] profiles failed", error);
+    }
+  };
+
   const refreshMenuState = async () => {
     const invoke = getTauriInvoke();
     if (!invoke) return;
+    applyActiveProfile();
 
     try {
-      const [settings, protectedState] = await Promise.all([
+      const [settings, protectedState, lockedKeys] = await Promise.all([
         invoke("get_settings"),
-        invoke("get_content_protection")
+        invoke("get_content_protection"),
+        invoke("get_locked_settings")
       ]);
 
       setMenuItemChecked(MENU_IDS.contentProtection, !!protectedState);
-      setMenuItemChecked(MENU_IDS.autostart, !!settings?.autoStart);
-      setMenuItemChecked(MENU_IDS.startMinimized, !!settings?.startMinimized);
-      setLogLevelChecked(settings?.logLevel || "info");
+      applySettingsToMenu(settings);
+      applyLockedSettings(lockedKeys);
     } catch (error) {
       console.warn("[menu] refresh failed", error);
     }
@@ -492,6 +792,9 @@
     event.stopPropagation();
     const isOpen = dropdown.getAttribute("data-open") === "true";
     setMenuOpen(!isOpen);
+    if (!isOpen) {
+      loadProfileMenu();
+    }
   });
 
   const isEventInsideHost = (event) => {
@@ -523,11 +826,19 @@
     listen("content-protection-changed", (event) => {
       setMenuItemChecked(MENU_IDS.contentProtection, !!event.payload);
     });
+    listen("settings-changed", (event) => {
+      setMenuItemChecked(
+        MENU_IDS.contentProtection,
+        !!event.payload?.contentProtection
+      );
+      applySettingsToMenu(event.payload);
+    });
   };
 
   const syncMenuForDev = async () => {
     const isDev = await getIsDev();
     if (!isDev) return;
+    menuIsDev = true;
     renderMenu(buildMenuModel(true));
     await refreshMenuState();
   };
@@ -661,6 +972,7 @@
   refreshMenuState();
   maybeListenContentProtection();
   syncMenuForDev();
+  loadProfileMenu();
   refreshMaximizeState();
   bindWindowStateSync();
 
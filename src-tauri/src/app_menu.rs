@@ -1,7 +1,9 @@
-use crate::content_protection::{is_content_protected, set_content_protection_from_app};
-use crate::logger::{apply_log_level, LogLevel};
-use crate::settings::{load_settings, save_settings};
-use crate::tray::set_tray_enabled;
+use crate::content_protection::is_content_protected;
+use crate::logger::LogLevel;
+use crate::policy::{current_policy, locked_error, setting_label, MANAGED_HINT};
+use crate::profiles::handle_profile_menu_action;
+use crate::settings::AppSettings;
+use crate::settings_service::{current_settings, modify_settings};
 use log::{info, warn};
 use tauri::menu::{CheckMenuItem, MenuEvent, MenuId, PredefinedMenuItem, Submenu};
 use tauri::{is_dev, Manager, Wry};
@@ -29,82 +31,90 @@ pub(crate) struct MenuState {
 
 pub(crate) fn build_menu(
   app_handle: &tauri::AppHandle,
-  settings: &crate::settings::AppSettings,
+  settings: &AppSettings,
 ) -> tauri::Result<MenuState> {
   let autostart_enabled = app_handle
     .autolaunch()
     .is_enabled()
     .unwrap_or(settings.auto_start);
-  let effective_log_level = crate::logger::resolve_log_level(&settings.log_level);
+  let effective_log_level = settings.log_level;
+  let policy = current_policy(app_handle);
+  let content_protection_locked = policy.is_locked("contentProtection");
+  let autostart_locked = policy.is_locked("autoStart");
+  let start_minimized_locked = policy.is_locked("startMinimized");
+  let log_level_locked = policy.is_locked("logLevel");
 
   let content_protection = CheckMenuItem::with_id(
     app_handle,
     MenuId::new(MENU_CONTENT_PROTECTION_ID),
-    "画面を保護",
-    true,
+    menu_label(
+      setting_label("contentProtection"),
+      content_protection_locked,
+    ),
+    !content_protection_locked,
     settings.content_protection,
     Some("Alt+H"),
   )?;
   let autostart = CheckMenuItem::with_id(
     app_handle,
     MenuId::new(MENU_AUTOSTART_ID),
-    "Windows 起動時に自動起動",
-    true,
+    menu_label(setting_label("autoStart"), autostart_locked),
+    !autostart_locked,
     autostart_enabled,
     None::<&str>,
   )?;
   let start_minimized = CheckMenuItem::with_id(
     app_handle,
     MenuId::new(MENU_START_MINIMIZED_ID),
-    "起動時に最小化",
-    true,
+    menu_label(setting_label("startMinimized"), start_minimized_locked),
+    !start_minimized_locked,
     settings.start_minimized,
     None::<&str>,
   )?;
   let log_error = CheckMenuItem::with_id(
     app_handle,
     MenuId::new(MENU_LOG_ERROR_ID),
-    "Error",
-    true,
+    menu_label("Error", log_level_locked),
+    !log_level_locked,
     effective_log_level == LogLevel::Error,
     None::<&str>,
   )?;
   let log_warn = CheckMenuItem::with_id(
     app_handle,
     MenuId::new(MENU_LOG_WARN_ID),
-    "Warn",
-    true,
+    menu_label("Warn", log_level_locked),
+    !log_level_locked,
     effective_log_level == LogLevel::Warn,
     None::<&str>,
   )?;
   let log_info = CheckMenuItem::with_id(
     app_handle,
     MenuId::new(MENU_LOG_INFO_ID),
-    "Info",
-    true,
+    menu_label("Info", log_level_locked),
+    !log_level_locked,
     effective_log_level == LogLevel::Info,
     None::<&str>,
   )?;
   let log_debug = CheckMenuItem::with_id(
     app_handle,
     MenuId::new(MENU_LOG_DEBUG_ID),
-    "Debug",
-    true,
+    menu_label("Debug", log_level_locked),
+    !log_level_locked,
     effective_log_level == LogLevel::Debug,
     None::<&str>,
   )?;
   let log_verbose = CheckMenuItem::with_id(
     app_handle,
     MenuId::new(MENU_LOG_VERBOSE_ID),
-    "Verbose",
-    true,
+    menu_label("Verbose", log_level_locked),
+    !log_level_locked,
     effective_log_level == LogLevel::Verbose,
     None::<&str>,
   )?;
 
   let log_menu = Submenu::with_items(
     app_handle,
-    "ログレベル",
+    setting_label("logLevel"),
     true,
     &[&log_error, &log_warn, &log_info, &log_debug, &log_verbose],
   )?;
@@ -142,71 +152,71 @@ pub(crate) fn build_menu(
   })
 }
 
+fn menu_label(label: &str, locked: bool) -> String {
+  if locked {
+    format!("{label}（{MANAGED_HINT}）")
+  } else {
+    label.to_string()
+  }
+}
+
+fn locked_setting_for_menu(id: &str) -> Option<&'static str> {
+  match id {
+    MENU_CONTENT_PROTECTION_ID => Some("contentProtection"),
+    MENU_AUTOSTART_ID => Some("autoStart"),
+    MENU_START_MINIMIZED_ID => Some("startMinimized"),
+    MENU_LOG_ERROR_ID | MENU_LOG_WARN_ID | MENU_LOG_INFO_ID | MENU_LOG_DEBUG_ID
+    | MENU_LOG_VERBOSE_ID => Some("logLevel"),
+    _ => None,
+  }
+}
+
 pub(crate) fn handle_menu_event(app_handle: &tauri::AppHandle, event: MenuEvent) {
-  handle_menu_action_id(app_handle, event.id().as_ref());
+  if let Err(error) = handle_menu_action_id(app_handle, event.id().as_ref()) {
+    warn!("[menu] {error}");
+  }
 }
 
 #[tauri::command]
 pub(crate) fn menu_action(app_handle: tauri::AppHandle, id: String) -> Result<(), String> {
-  handle_menu_action_id(&app_handle, id.as_str());
-  Ok(())
+  handle_menu_action_id(&app_handle, id.as_str())
 }
 
-pub(crate) fn handle_menu_action_id(app_handle: &tauri::AppHandle, id: &str) {
+pub(crate) fn handle_menu_action_id(app_handle: &tauri::AppHandle, id: &str) -> Result<(), String> {
   warn!("[menu] action id={id}");
+  if let Some(result) = handle_profile_menu_action(app_handle, id) {
+    return result;
+  }
+  if let Some(key) = locked_setting_for_menu(id) {
+    if current_policy(app_handle).is_locked(key) {
+      return Err(locked_error(key));
+    }
+  }
   match id {
     id if id == MENU_CONTENT_PROTECTION_ID => {
       let target = !is_content_protected(app_handle);
-      if let Ok(enabled) = set_content_protection_from_app(app_handle, target) {
-        set_menu_checked(app_handle, MENU_CONTENT_PROTECTION_ID, enabled);
-      }
+      update_settings_from_menu(app_handle, move |settings| {
+        settings.content_protection = target;
+      });
     }
     id if id == MENU_AUTOSTART_ID => {
       let app_handle = app_handle.clone();
       std::thread::spawn(move || {
-        let fallback_enabled = load_settings(&app_handle)
-          .map(|settings| settings.auto_start)
-          .unwrap_or(false);
+        let fallback_enabled = current_settings(&app_handle).auto_start;
         info!("[autostart] menu clicked");
         let current = app_handle
           .autolaunch()
           .is_enabled()
           .unwrap_or(fallback_enabled);
-        let target = !current;
-        info!("[autostart] toggle current={current} target={target}");
-
-        let result = if target {
-          app_handle.autolaunch().enable()
-        } else {
-          app_handle.autolaunch().disable()
-        };
-        if let Err(error) = result {
-          warn!("[autostart] update failed: {error:#}");
-        }
-        let enabled = app_handle.autolaunch().is_enabled().unwrap_or(current);
-
-        if let Ok(mut settings) = load_settings(&app_handle) {
-          settings.auto_start = enabled;
-          let _ = save_settings(&app_handle, &settings);
-        }
-
-        let menu_handle = app_handle.clone();
-        let _ = app_handle.run_on_main_thread(move || {
-          set_menu_checked(&menu_handle, MENU_AUTOSTART_ID, enabled);
+        update_settings_from_menu(&app_handle, move |settings| {
+          settings.auto_start = !current;
         });
       });
     }
     id if id == MENU_START_MINIMIZED_ID => {
-      let current = load_settings(app_handle)
-        .map(|settings| settings.start_minimized)
-        .unwrap_or(false);
-      let target = !current;
-      let tray_enabled = set_tray_enabled(app_handle, target);
-      if let Ok(mut settings) = load_settings(app_handle) {
-        settings.start_minimized = tray_enabled;
-        let _ = save_settings(app_handle, &settings);
-      }
-      set_menu_checked(app_handle, MENU_START_MINIMIZED_ID, tray_enabled);
+      update_settings_from_menu(app_handle, |settings| {
+        settings.start_minimized = !settings.start_minimized;
+      });
     }
     id if id == MENU_LOG_ERROR_ID => {
       update_log_level(app_handle, LogLevel::Error);
@@ -225,6 +235,18 @@ pub(crate) fn handle_menu_action_id(app_handle: &tauri::AppHandle, id: &str) {
     }
     _ => {}
   }
+  Ok(())
+}
+
+fn update_settings_from_menu(
+  app_handle: &tauri::AppHandle,
+  change: impl FnOnce(&mut AppSettings) + Send + 'static,
+) {
+  if let Err(error) = modify_settings(app_handle, change) {
+    warn!("[menu] settings update failed: {error:#}");
+    // The clicked item already flipped its own check; put it back.
+    sync_menu_checks(app_handle, &current_settings(app_handle));
+  }
 }
 
 pub(crate) fn set_menu_checked(app_handle: &tauri::AppHandle, id: &str, checked: bool) {
@@ -261,18 +283,26 @@ pub(crate) fn set_menu_checked(app_handle: &tauri::AppHandle, id: &str, checked:
 }
 
 fn update_log_level(app_handle: &tauri::AppHandle, level: LogLevel) {
-  apply_log_level(level);
-  if let Ok(mut settings) = load_settings(app_handle) {
-    settings.log_level = level.as_str().to_string();
-    let _ = save_settings(app_handle, &settings);
-  }
+  update_settings_from_menu(app_handle, move |settings| settings.log_level = level);
+}
+
+/// Mirrors `settings` onto every check item so the menu never drifts from what was applied.
+pub(crate) fn sync_menu_checks(app_handle: &tauri::AppHandle, settings: &AppSettings) {
+  let level = settings.log_level;
+  set_menu_checked(
+    app_handle,
+    MENU_CONTENT_PROTECTION_ID,
+    settings.content_protection,
+  );
+  set_menu_checked(app_handle, MENU_AUTOSTART_ID, settings.auto_start);
+  set_menu_checked(
+    app_handle,
+    MENU_START_MINIMIZED_ID,
+    settings.start_minimized,
+  );
   set_menu_checked(app_handle, MENU_LOG_ERROR_ID, level == LogLevel::Error);
   set_menu_checked(app_handle, MENU_LOG_WARN_ID, level == LogLevel::Warn);
   set_menu_checked(app_handle, MENU_LOG_INFO_ID, level == LogLevel::Info);
   set_menu_checked(app_handle, MENU_LOG_DEBUG_ID, level == LogLevel::Debug);
   set_menu_checked(app_handle, MENU_LOG_VERBOSE_ID, level == LogLevel::Verbose);
 }
-
-pub(crate) fn menu_content_protection_id() -> &'static str {
-  MENU_CONTENT_PROTECTION_ID
-}
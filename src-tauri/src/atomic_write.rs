@@ -0,0 +1,33 @@
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Writes `contents` to a sibling temp file and renames it over `path`, so a crash
+/// mid-write leaves either the old or the new file but never a truncated one.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  let temp_path = sibling_path(path, "tmp")?;
+  {
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+  }
+  if let Err(error) = fs::rename(&temp_path, path) {
+    let _ = fs::remove_file(&temp_path);
+    return Err(error.into());
+  }
+  Ok(())
+}
+
+pub(crate) fn sibling_path(path: &Path, suffix: &str) -> Result<PathBuf> {
+  let file_name = path
+    .file_name()
+    .ok_or_else(|| anyhow!("invalid file path: {}", path.display()))?;
+  let mut name = file_name.to_os_string();
+  name.push(".");
+  name.push(suffix);
+  Ok(path.with_file_name(name))
+}
@@ -0,0 +1,126 @@
+use super::BrowserBackend;
+use crate::cookie_jar::CookieRecord;
+use anyhow::{anyhow, Result};
+use std::cell::RefCell;
+use std::path::Path;
+use url::Url;
+
+/// In-memory `BrowserBackend`. Every call is recorded as `op:argument` in `calls`; a call whose
+/// record starts with an entry of `failures` fails instead.
+#[derive(Default)]
+pub(crate) struct FakeBackend {
+  pub(crate) calls: RefCell<Vec<String>>,
+  pub(crate) cookies: RefCell<Vec<CookieRecord>>,
+  pub(crate) failures: RefCell<Vec<String>>,
+  enabled: RefCell<Vec<String>>,
+}
+
+impl FakeBackend {
+  pub(crate) fn with_cookies(cookies: Vec<CookieRecord>) -> Self {
+    Self {
+      cookies: RefCell::new(cookies),
+      ..Self::default()
+    }
+  }
+
+  pub(crate) fn fail_on(&self, call: &str) {
+    self.failures.borrow_mut().push(call.to_string());
+  }
+
+  pub(crate) fn calls(&self) -> Vec<String> {
+    self.calls.borrow().clone()
+  }
+
+  pub(crate) fn is_enabled(&self, id: &str) -> bool {
+    self.enabled.borrow().iter().any(|enabled| enabled == id)
+  }
+
+  fn record(&self, call: String) -> Result<()> {
+    let failed = self
+      .failures
+      .borrow()
+      .iter()
+      .any(|failure| call.starts_with(failure.as_str()));
+    self.calls.borrow_mut().push(call.clone());
+    if failed {
+      return Err(anyhow!("fake failure: {call}"));
+    }
+    Ok(())
+  }
+}
+
+/// Extension ids are derived from the directory name, like `id-<name>`.
+pub(crate) fn fake_extension_id(dir: &Path) -> String {
+  let name = dir
+    .file_name()
+    .map(|name| name.to_string_lossy().to_string())
+    .unwrap_or_default();
+  format!("id-{name}")
+}
+
+// RFC 6265 domain matching, enough for the hosts the app cares about.
+fn domain_matches(cookie_domain: &str, host: &str) -> bool {
+  let domain = cookie_domain.trim_start_matches('.');
+  host == domain || host.ends_with(&format!(".{domain}"))
+}
+
+impl BrowserBackend for FakeBackend {
+  fn add_extension(&self, dir: &Path) -> Result<String> {
+    self.record(format!("add:{}", dir.display()))?;
+    Ok(fake_extension_id(dir))
+  }
+
+  fn enable_extension(&self, id: &str) -> Result<()> {
+    self.record(format!("enable:{id}"))?;
+    let mut enabled = self.enabled.borrow_mut();
+    if !enabled.iter().any(|enabled| enabled == id) {
+      enabled.push(id.to_string());
+    }
+    Ok(())
+  }
+
+  fn list_cookies(&self, uri: Option<&str>) -> Result<Vec<CookieRecord>> {
+    self.record(format!("list:{}", uri.unwrap_or("all")))?;
+    let host = uri
+      .map(|uri| {
+        Url::parse(uri)
+          .ok()
+          .and_then(|url| url.host_str().map(str::to_string))
+          .ok_or_else(|| anyhow!("invalid uri {uri}"))
+      })
+      .transpose()?;
+    Ok(
+      self
+        .cookies
+        .borrow()
+        .iter()
+        .filter(|cookie| {
+          host
+            .as_deref()
+            .is_none_or(|host| domain_matches(&cookie.domain, host))
+        })
+        .cloned()
+        .collect(),
+    )
+  }
+
+  fn set_cookie(&self, cookie: &CookieRecord) -> Result<()> {
+    self.record(format!("set:{}", cookie.name))?;
+    let mut cookies = self.cookies.borrow_mut();
+    cookies.retain(|existing| {
+      !(existing.name == cookie.name
+        && existing.domain == cookie.domain
+        && existing.path == cookie.path)
+    });
+    cookies.push(cookie.clone());
+    Ok(())
+  }
+
+  fn navigate(&self, url: &str) -> Result<()> {
+    self.record(format!("navigate:{url}"))
+  }
+
+  fn reload(&self) -> Result<()> {
+    self.record("reload".to_string())
+  }
+}
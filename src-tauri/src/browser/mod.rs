@@ -0,0 +1,26 @@
+#[cfg(test)]
+pub(crate) mod fake;
+#[cfg(target_os = "windows")]
+mod webview2;
+
+use crate::cookie_jar::CookieRecord;
+use anyhow::Result;
+use std::path::Path;
+
+#[cfg(target_os = "windows")]
+pub(crate) use webview2::WebView2Backend;
+
+/// The browser operations the extension and cookie logic relies on. WebView2 implements it on
+/// Windows; tests run the same logic against an in-memory fake on any OS.
+pub(crate) trait BrowserBackend {
+  /// Installs the unpacked extension in `dir` and returns the id the browser assigned to it.
+  fn add_extension(&self, dir: &Path) -> Result<String>;
+  /// Enables the installed extension `id`; a no-op when it already is.
+  fn enable_extension(&self, id: &str) -> Result<()>;
+  /// Cookies sent to `uri`, or every cookie of the profile when `uri` is `None`.
+  fn list_cookies(&self, uri: Option<&str>) -> Result<Vec<CookieRecord>>;
+  /// Adds `cookie`, replacing the one with the same name, domain and path.
+  fn set_cookie(&self, cookie: &CookieRecord) -> Result<()>;
+  fn navigate(&self, url: &str) -> Result<()>;
+  fn reload(&self) -> Result<()>;
+}
@@ -0,0 +1,232 @@
+use super::BrowserBackend;
+use crate::cookie_jar::{CookieRecord, CookieSameSite};
+use anyhow::{anyhow, Result};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::mpsc;
+use tauri::webview::PlatformWebview;
+use webview2_com::Microsoft::Web::WebView2::Win32::{
+  ICoreWebView2, ICoreWebView2BrowserExtension, ICoreWebView2CookieManager, ICoreWebView2Profile7,
+  ICoreWebView2_13, ICoreWebView2_2, COREWEBVIEW2_COOKIE_SAME_SITE_KIND,
+  COREWEBVIEW2_COOKIE_SAME_SITE_KIND_LAX, COREWEBVIEW2_COOKIE_SAME_SITE_KIND_NONE,
+  COREWEBVIEW2_COOKIE_SAME_SITE_KIND_STRICT,
+};
+use webview2_com::{
+  take_pwstr, wait_with_pump, BrowserExtensionEnableCompletedHandler, GetCookiesCompletedHandler,
+  ProfileAddBrowserExtensionCompletedHandler,
+};
+use windows::core::{Interface, BOOL, HSTRING, PCWSTR, PWSTR};
+
+/// `BrowserBackend` over the `ICoreWebView2` of a Tauri webview. COM callbacks are awaited with
+/// `wait_with_pump`, so every call must happen on the webview's thread.
+pub(crate) struct WebView2Backend {
+  core: ICoreWebView2,
+  // Installed extensions by id; WebView2 enables through the extension object, not the id.
+  extensions: RefCell<HashMap<String, ICoreWebView2BrowserExtension>>,
+}
+
+impl WebView2Backend {
+  pub(crate) fn new(webview: &PlatformWebview) -> Result<Self> {
+    let controller = webview.controller();
+    let core = unsafe { controller.CoreWebView2()? };
+    Ok(Self {
+      core,
+      extensions: RefCell::new(HashMap::new()),
+    })
+  }
+
+  fn cookie_manager(&self) -> Result<ICoreWebView2CookieManager> {
+    let webview = self.core.cast::<ICoreWebView2_2>()?;
+    Ok(unsafe { webview.CookieManager()? })
+  }
+}
+
+impl BrowserBackend for WebView2Backend {
+  fn add_extension(&self, dir: &Path) -> Result<String> {
+    let profile = unsafe {
+      self
+        .core
+        .cast::<ICoreWebView2_13>()?
+        .Profile()?
+        .cast::<ICoreWebView2Profile7>()?
+    };
+    let path = dir.canonicalize()?;
+    let path_hs = HSTRING::from(path.as_path());
+    let (tx, rx) = mpsc::channel();
+    let handler =
+      ProfileAddBrowserExtensionCompletedHandler::create(Box::new(move |result, extension| {
+        let _ = tx.send((result, extension));
+        Ok(())
+      }));
+
+    unsafe {
+      profile.AddBrowserExtension(&path_hs, &handler)?;
+    }
+
+    let (result, extension) =
+      wait_with_pump(rx).map_err(|error| anyhow!("extension install callback error: {error:?}"))?;
+
+    if let Err(error) = result {
+      return Err(anyhow!("add extension failed: {error:?}"));
+    }
+
+    let extension = extension.ok_or_else(|| anyhow!("add extension returned no extension"))?;
+    let mut id_ptr = PWSTR::null();
+    unsafe {
+      extension.Id(&mut id_ptr)?;
+    }
+    let id = take_pwstr(id_ptr);
+    self.extensions.borrow_mut().insert(id.clone(), extension);
+    Ok(id)
+  }
+
+  fn enable_extension(&self, id: &str) -> Result<()> {
+    let extension = self
+      .extensions
+      .borrow()
+      .get(id)
+      .cloned()
+      .ok_or_else(|| anyhow!("extension {id} is not installed"))?;
+    let mut enabled = BOOL(0);
+    unsafe {
+      extension.IsEnabled(&mut enabled)?;
+    }
+    if enabled == true {
+      return Ok(());
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let handler = BrowserExtensionEnableCompletedHandler::create(Box::new(move |result| {
+      let _ = tx.send(result);
+      Ok(())
+    }));
+
+    unsafe {
+      extension.Enable(true, &handler)?;
+    }
+
+    let result =
+      wait_with_pump(rx).map_err(|error| anyhow!("extension enable callback error: {error:?}"))?;
+    if let Err(error) = result {
+      return Err(anyhow!("enable extension failed: {error:?}"));
+    }
+
+    Ok(())
+  }
+
+  fn list_cookies(&self, uri: Option<&str>) -> Result<Vec<CookieRecord>> {
+    let cookie_manager = self.cookie_manager()?;
+    let (tx, rx) = mpsc::channel();
+    unsafe {
+      let handler = GetCookiesCompletedHandler::create(Box::new(move |result, cookies| {
+        result?;
+        let mut out: Vec<CookieRecord> = Vec::new();
+        if let Some(cookies) = cookies {
+          let mut count = 0;
+          cookies.Count(&mut count)?;
+          for idx in 0..count {
+            let cookie = cookies.GetValueAtIndex(idx)?;
+            let mut name_ptr = PWSTR::null();
+            cookie.Name(&mut name_ptr)?;
+            let name = take_pwstr(name_ptr);
+
+            let mut value_ptr = PWSTR::null();
+            cookie.Value(&mut value_ptr)?;
+            let value = take_pwstr(value_ptr);
+
+            let mut domain_ptr = PWSTR::null();
+            cookie.Domain(&mut domain_ptr)?;
+            let domain = take_pwstr(domain_ptr);
+
+            let mut path_ptr = PWSTR::null();
+            cookie.Path(&mut path_ptr)?;
+            let path = take_pwstr(path_ptr);
+
+            let mut is_session = BOOL(0);
+            cookie.IsSession(&mut is_session)?;
+
+            let mut is_http_only = BOOL(0);
+            cookie.IsHttpOnly(&mut is_http_only)?;
+
+            let mut is_secure = BOOL(0);
+            cookie.IsSecure(&mut is_secure)?;
+
+            let mut same_site = COREWEBVIEW2_COOKIE_SAME_SITE_KIND(0);
+            cookie.SameSite(&mut same_site)?;
+
+            let mut expires = 0f64;
+            cookie.Expires(&mut expires)?;
+
+            out.push(CookieRecord {
+              name,
+              value,
+              domain,
+              path,
+              http_only: is_http_only.as_bool(),
+              secure: is_secure.as_bool(),
+              same_site: match same_site {
+                COREWEBVIEW2_COOKIE_SAME_SITE_KIND_LAX => CookieSameSite::Lax,
+                COREWEBVIEW2_COOKIE_SAME_SITE_KIND_STRICT => CookieSameSite::Strict,
+                _ => CookieSameSite::None,
+              },
+              expires: (!is_session.as_bool()).then_some(expires),
+            });
+          }
+        }
+        let _ = tx.send(out);
+        Ok(())
+      }));
+
+      match uri {
+        Some(uri) => {
+          let uri_hs = HSTRING::from(uri);
+          cookie_manager.GetCookies(&uri_hs, &handler)?;
+        }
+        None => {
+          cookie_manager.GetCookies(PCWSTR::null(), &handler)?;
+        }
+      }
+    }
+    wait_with_pump(rx).map_err(Into::into)
+  }
+
+  fn set_cookie(&self, cookie: &CookieRecord) -> Result<()> {
+    let cookie_manager = self.cookie_manager()?;
+    let name_hs = HSTRING::from(cookie.name.as_str());
+    let value_hs = HSTRING::from(cookie.value.as_str());
+    let domain_hs = HSTRING::from(cookie.domain.as_str());
+    let path_hs = HSTRING::from(cookie.path.as_str());
+    let same_site = match cookie.same_site {
+      CookieSameSite::None => COREWEBVIEW2_COOKIE_SAME_SITE_KIND_NONE,
+      CookieSameSite::Lax => COREWEBVIEW2_COOKIE_SAME_SITE_KIND_LAX,
+      CookieSameSite::Strict => COREWEBVIEW2_COOKIE_SAME_SITE_KIND_STRICT,
+    };
+    unsafe {
+      let new_cookie = cookie_manager.CreateCookie(&name_hs, &value_hs, &domain_hs, &path_hs)?;
+      new_cookie.SetIsHttpOnly(cookie.http_only)?;
+      new_cookie.SetIsSecure(cookie.secure)?;
+      new_cookie.SetSameSite(same_site)?;
+      if let Some(expires) = cookie.expires {
+        new_cookie.SetExpires(expires)?;
+      }
+      cookie_manager.AddOrUpdateCookie(&new_cookie)?;
+    }
+    Ok(())
+  }
+
+  fn navigate(&self, url: &str) -> Result<()> {
+    let target = HSTRING::from(url);
+    unsafe {
+      self.core.Navigate(&target)?;
+    }
+    Ok(())
+  }
+
+  fn reload(&self) -> Result<()> {
+    unsafe {
+      self.core.Reload()?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,318 @@
+use crate::maintenance::{enqueue_maintenance, MaintenanceOp};
+use crate::paths::profile_dir;
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri_plugin_dialog::DialogExt;
+
+/// Profile data that WebView2 rebuilds on its own, so deleting it never logs the user out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum CacheCategory {
+  HttpCache,
+  CodeCache,
+  ServiceWorkerCache,
+  GpuCache,
+  CrashDumps,
+}
+
+impl CacheCategory {
+  pub(crate) const ALL: [CacheCategory; 5] = [
+    CacheCategory::HttpCache,
+    CacheCategory::CodeCache,
+    CacheCategory::ServiceWorkerCache,
+    CacheCategory::GpuCache,
+    CacheCategory::CrashDumps,
+  ];
+
+  /// Paths of the category's directories relative to the WebView2 user data folder, so a
+  /// directory of the same name inside site or extension storage is never touched.
+  fn patterns(self) -> &'static [&'static [&'static str]] {
+    match self {
+      CacheCategory::HttpCache => &[&["EBWebView", "Default", "Cache"]],
+      CacheCategory::CodeCache => &[&["EBWebView", "Default", "Code Cache"]],
+      CacheCategory::ServiceWorkerCache => {
+        &[&["EBWebView", "Default", "Service Worker", "CacheStorage"]]
+      }
+      CacheCategory::GpuCache => &[
+        &["EBWebView", "Default", "GPUCache"],
+        &["EBWebView", "GrShaderCache"],
+        &["EBWebView", "ShaderCache"],
+      ],
+      CacheCategory::CrashDumps => &[&["EBWebView", "Crashpad"]],
+    }
+  }
+
+  fn matches(self, relative: &Path) -> bool {
+    self
+      .patterns()
+      .iter()
+      .any(|components| relative.iter().eq(components.iter().map(OsStr::new)))
+  }
+
+  // Only directories on the way to a pattern are worth descending into.
+  fn leads_to(self, relative: &Path) -> bool {
+    self.patterns().iter().any(|components| {
+      let depth = relative.iter().count();
+      depth < components.len()
+        && relative
+          .iter()
+          .eq(components[..depth].iter().map(OsStr::new))
+    })
+  }
+}
+
+/// Whether `relative`, a directory below the profile, belongs to a cache category, which
+/// WebView2 rebuilds on its own.
+pub(crate) fn is_rebuildable_cache(relative: &Path) -> bool {
+  CacheCategory::ALL
+    .iter()
+    .any(|category| category.matches(relative))
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CleanupReport {
+  pub(crate) reclaimed_bytes: u64,
+  /// Categories that could not be fully removed, usually because the webview holds them open.
+  pub(crate) incomplete: Vec<CacheCategory>,
+  /// Set when the incomplete categories were queued for the next startup.
+  pub(crate) deferred: bool,
+}
+
+/// Deletes the directories of `categories` below `profile` and reports the bytes freed.
+pub(crate) fn clear_cache_categories(
+  profile: &Path,
+  categories: &[CacheCategory],
+) -> Result<CleanupReport> {
+  let mut report = CleanupReport::default();
+  for category in categories {
+    let mut complete = true;
+    for dir in find_category_dirs(profile, *category)? {
+      let before = dir_size(&dir);
+      let result = fs::remove_dir_all(&dir);
+      let after = if dir.exists() { dir_size(&dir) } else { 0 };
+      report.reclaimed_bytes += before.saturating_sub(after);
+      if let Err(error) = result {
+        debug!("[cache] {} not fully removed: {error}", dir.display());
+        complete = false;
+      }
+    }
+    if !complete {
+      report.incomplete.push(*category);
+    }
+  }
+  info!(
+    "[cache] cleared {categories:?} reclaimed={} incomplete={:?}",
+    report.reclaimed_bytes, report.incomplete
+  );
+  Ok(report)
+}
+
+/// Startup-path variant used by the maintenance queue; any leftover is an error so it is retried.
+pub(crate) fn clear_cache_for_maintenance(
+  app: &tauri::AppHandle,
+  categories: &[CacheCategory],
+) -> Result<u64> {
+  let report = clear_cache_categories(&profile_dir(app)?, categories)?;
+  if !report.incomplete.is_empty() {
+    return Err(anyhow!(
+      "locked after reclaiming {} bytes: {:?}",
+      report.reclaimed_bytes,
+      report.incomplete
+    ));
+  }
+  Ok(report.reclaimed_bytes)
+}
+
+pub(crate) fn format_bytes(bytes: u64) -> String {
+  const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+  let mut value = bytes as f64;
+  let mut unit = 0;
+  while value >= 1024.0 && unit < UNITS.len() - 1 {
+    value /= 1024.0;
+    unit += 1;
+  }
+  if unit == 0 {
+    format!("{bytes} B")
+  } else {
+    format!("{value:.1} {}", UNITS[unit])
+  }
+}
+
+pub(crate) fn category_size(profile: &Path, category: CacheCategory) -> u64 {
+  find_category_dirs(profile, category)
+    .map(|dirs| dirs.iter().map(|dir| dir_size(dir)).sum())
+    .unwrap_or(0)
+}
+
+pub(crate) fn dir_size(path: &Path) -> u64 {
+  let Ok(entries) = fs::read_dir(path) else {
+    return 0;
+  };
+  entries
+    .flatten()
+    .map(|entry| match entry.file_type() {
+      Ok(file_type) if file_type.is_dir() => dir_size(&entry.path()),
+      Ok(_) => entry.metadata().map(|meta| meta.len()).unwrap_or(0),
+      Err(_) => 0,
+    })
+    .sum()
+}
+
+fn find_category_dirs(profile: &Path, category: CacheCategory) -> Result<Vec<PathBuf>> {
+  let mut found = Vec::new();
+  if profile.is_dir() {
+    collect_matching_dirs(profile, profile, category, &mut found)?;
+  }
+  Ok(found)
+}
+
+fn collect_matching_dirs(
+  profile: &Path,
+  dir: &Path,
+  category: CacheCategory,
+  found: &mut Vec<PathBuf>,
+) -> Result<()> {
+  for entry in fs::read_dir(dir)? {
+    let entry = entry?;
+    if !entry.file_type()?.is_dir() {
+      continue;
+    }
+    let path = entry.path();
+    let relative = path.strip_prefix(profile).unwrap_or(&path);
+    if category.matches(relative) {
+      found.push(path);
+    } else if category.leads_to(relative) {
+      collect_matching_dirs(profile, &path, category, found)?;
+    }
+  }
+  Ok(())
+}
+
+#[tauri::command]
+pub(crate) async fn clear_cache(
+  app_handle: tauri::AppHandle,
+  categories: Option<Vec<CacheCategory>>,
+) -> Result<CleanupReport, String> {
+  let categories = categories.unwrap_or_else(|| CacheCategory::ALL.to_vec());
+  let handle = app_handle.clone();
+  let report = tauri::async_runtime::spawn_blocking(move || {
+    let profile = profile_dir(&handle)?;
+    let mut report = clear_cache_categories(&profile, &categories)?;
+    if !report.incomplete.is_empty() {
+      let op = MaintenanceOp::ClearCache {
+        categories: report.incomplete.clone(),
+      };
+      match enqueue_maintenance(&handle, op) {
+        Ok(_) => report.deferred = true,
+        Err(error) => warn!("[cache] defer failed: {error:#}"),
+      }
+    }
+    Ok::<_, anyhow::Error>(report)
+  })
+  .await
+  .map_err(|error| error.to_string())?
+  .map_err(|error| error.to_string())?;
+
+  let mut message = format!("{} を解放しました。", format_bytes(report.reclaimed_bytes));
+  if report.deferred {
+    message.push_str("\n使用中のファイルは次回起動時に削除します。");
+  }
+  app_handle
+    .dialog()
+    .message(message)
+    .title("キャッシュの削除")
+    .show(|_| {});
+  Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // A throwaway profile laid out like a WebView2 user data folder.
+  fn profile(name: &str) -> PathBuf {
+    let root =
+      std::env::temp_dir().join(format!("refined-line-cache-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for (dir, size) in [
+      ("EBWebView/Default/Cache", 100),
+      ("EBWebView/Default/Code Cache/js", 20),
+      ("EBWebView/Default/Service Worker/CacheStorage", 5),
+      ("EBWebView/Default/GPUCache", 3),
+      ("EBWebView/ShaderCache", 2),
+      ("EBWebView/Crashpad/reports", 7),
+      // Same names inside storage that must survive.
+      ("EBWebView/Default/Local Extension Settings/abc/Cache", 11),
+      ("EBWebView/Default/IndexedDB/Cache", 13),
+      ("Cache", 17),
+    ] {
+      let dir = root.join(dir);
+      fs::create_dir_all(&dir).unwrap();
+      fs::write(dir.join("data"), vec![0u8; size]).unwrap();
+    }
+    root
+  }
+
+  #[test]
+  fn categories_only_match_the_webview2_layout() {
+    let root = profile("find");
+    let found = find_category_dirs(&root, CacheCategory::HttpCache).unwrap();
+    assert_eq!(found, vec![root.join("EBWebView/Default/Cache")]);
+    let mut gpu = find_category_dirs(&root, CacheCategory::GpuCache).unwrap();
+    gpu.sort();
+    assert_eq!(
+      gpu,
+      vec![
+        root.join("EBWebView/Default/GPUCache"),
+        root.join("EBWebView/ShaderCache"),
+      ]
+    );
+    assert!(is_rebuildable_cache(Path::new("EBWebView/Crashpad")));
+    assert!(!is_rebuildable_cache(Path::new(
+      "EBWebView/Default/IndexedDB/Cache"
+    )));
+    assert!(!is_rebuildable_cache(Path::new("Cache")));
+    let _ = fs::remove_dir_all(&root);
+  }
+
+  #[test]
+  fn clearing_reports_reclaimed_bytes_and_keeps_storage() {
+    let root = profile("clear");
+    let report =
+      clear_cache_categories(&root, &[CacheCategory::HttpCache, CacheCategory::CodeCache]).unwrap();
+    assert_eq!(report.reclaimed_bytes, 120);
+    assert!(report.incomplete.is_empty());
+    assert!(!root.join("EBWebView/Default/Cache").exists());
+    assert!(!root.join("EBWebView/Default/Code Cache").exists());
+    assert!(root.join("EBWebView/Default/IndexedDB/Cache/data").exists());
+    assert!(root
+      .join("EBWebView/Default/Local Extension Settings/abc/Cache/data")
+      .exists());
+    assert!(root.join("Cache/data").exists());
+    assert_eq!(category_size(&root, CacheCategory::CrashDumps), 7);
+    let _ = fs::remove_dir_all(&root);
+  }
+
+  #[test]
+  fn missing_profile_has_nothing_to_clear() {
+    let root = std::env::temp_dir().join("refined-line-cache-missing");
+    let report = clear_cache_categories(&root, &CacheCategory::ALL).unwrap();
+    assert_eq!(report.reclaimed_bytes, 0);
+    assert!(report.incomplete.is_empty());
+  }
+
+  #[test]
+  fn bytes_are_formatted_in_binary_units() {
+    assert_eq!(format_bytes(0), "0 B");
+    assert_eq!(format_bytes(1023), "1023 B");
+    assert_eq!(format_bytes(1024), "1.0 KB");
+    assert_eq!(format_bytes(1536), "1.5 KB");
+    assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MB");
+    assert_eq!(format_bytes(3 * 1024 * 1024 * 1024 * 1024), "3072.0 GB");
+  }
+}
@@ -0,0 +1,182 @@
+use crate::diagnostics::write_diagnostics;
+use crate::logger::LogLevel;
+use crate::paths::AppPaths;
+use clap::Parser;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tauri::PackageInfo;
+
+static CLI_ARGS: OnceLock<CliArgs> = OnceLock::new();
+
+/// Flags of the desktop binary. `--minimized` and `--log-level` become the CLI config layer next
+/// to `--set`; the rest steer paths and startup.
+#[derive(Debug, Default, Parser)]
+#[command(name = "refined-line", disable_version_flag = true)]
+pub(crate) struct CliArgs {
+  /// Start minimized to the tray
+  #[arg(long)]
+  pub(crate) minimized: bool,
+  /// Run as this profile, by id or name, for this launch only
+  #[arg(long, value_name = "ID")]
+  pub(crate) profile: Option<String>,
+  /// Log level for this launch: error, warn, info, debug or verbose
+  #[arg(long, value_name = "LEVEL", value_parser = parse_log_level)]
+  pub(crate) log_level: Option<LogLevel>,
+  /// Use the installed extensions without any network access; implies --no-update
+  #[arg(long)]
+  pub(crate) offline: bool,
+  /// Skip the app and extension update checks
+  #[arg(long)]
+  pub(crate) no_update: bool,
+  /// Reset the profile data before opening LINE
+  #[arg(long)]
+  pub(crate) reset_profile: bool,
+  /// Install a local .crx before opening LINE
+  #[arg(long, value_name = "PATH")]
+  pub(crate) import_crx: Option<PathBuf>,
+  /// Keep all app data under this directory
+  #[arg(long, value_name = "PATH")]
+  pub(crate) data_dir: Option<PathBuf>,
+  /// Portable mode; data goes to DIR, relative to the exe, or `data` next to it
+  #[arg(
+    long,
+    value_name = "DIR",
+    num_args = 0..=1,
+    require_equals = true,
+    default_missing_value = ""
+  )]
+  pub(crate) portable: Option<String>,
+  /// Override a setting for this launch, e.g. --set logLevel=debug
+  #[arg(long = "set", value_name = "KEY=VALUE")]
+  pub(crate) overrides: Vec<String>,
+  /// Print the version and exit
+  #[arg(short = 'V', long)]
+  pub(crate) version: bool,
+  /// Write a diagnostics zip and exit
+  #[arg(long, value_name = "OUT.zip")]
+  pub(crate) diagnostics: Option<PathBuf>,
+  /// refined-line:// or line.me/R/ links to open
+  #[arg(value_name = "URL")]
+  pub(crate) links: Vec<String>,
+}
+
+impl CliArgs {
+  /// Neither the app updater nor the extensions may check for updates.
+  pub(crate) fn skips_updates(&self) -> bool {
+    self.no_update || self.offline
+  }
+}
+
+fn parse_log_level(raw: &str) -> Result<LogLevel, String> {
+  LogLevel::from_str(raw).ok_or_else(|| format!("unknown log level {raw}"))
+}
+
+/// Parses the process arguments once, before anything reads them. `--help` and usage errors
+/// print and exit here.
+pub(crate) fn parse_cli() -> &'static CliArgs {
+  let args = CliArgs::try_parse().unwrap_or_else(|error| {
+    attach_parent_console();
+    error.exit()
+  });
+  CLI_ARGS.get_or_init(|| args)
+}
+
+/// The flags of this process; none are set before `parse_cli` runs.
+pub(crate) fn cli_args() -> &'static CliArgs {
+  CLI_ARGS.get_or_init(CliArgs::default)
+}
+
+/// Runs the flags that finish without opening a window. Returns the exit code when one ran.
+pub(crate) fn run_without_window(cli: &CliArgs, package: &PackageInfo) -> Option<i32> {
+  if cli.version {
+    attach_parent_console();
+    println!("{} {}", package.name, package.version);
+    return Some(0);
+  }
+  let out = cli.diagnostics.as_deref()?;
+  attach_parent_console();
+  match AppPaths::resolve(&package.name).and_then(|paths| write_diagnostics(&paths, package, out)) {
+    Ok(()) => {
+      println!("diagnostics written to {}", out.display());
+      Some(0)
+    }
+    Err(error) => {
+      eprintln!("diagnostics failed: {error:#}");
+      Some(1)
+    }
+  }
+}
+
+// Release builds use the windows subsystem, so output only shows up in the console that
+// started the process after attaching to it.
+#[cfg(target_os = "windows")]
+fn attach_parent_console() {
+  use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+  unsafe {
+    let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+  }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn attach_parent_console() {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(args: &[&str]) -> CliArgs {
+    CliArgs::try_parse_from(std::iter::once("refined-line").chain(args.iter().copied())).unwrap()
+  }
+
+  #[test]
+  fn no_arguments_set_nothing() {
+    let cli = parse(&[]);
+    assert!(!cli.minimized && !cli.version && !cli.skips_updates());
+    assert!(cli.profile.is_none() && cli.portable.is_none() && cli.overrides.is_empty());
+  }
+
+  #[test]
+  fn parses_values_in_both_forms() {
+    let cli = parse(&[
+      "--profile",
+      "work",
+      "--log-level=trace",
+      "--import-crx",
+      "line.crx",
+      "--data-dir=D:\\line",
+      "--set",
+      "a=1",
+      "--set=b=x",
+    ]);
+    assert_eq!(cli.profile.as_deref(), Some("work"));
+    assert_eq!(cli.log_level, Some(LogLevel::Verbose));
+    assert_eq!(cli.import_crx, Some(PathBuf::from("line.crx")));
+    assert_eq!(cli.data_dir, Some(PathBuf::from("D:\\line")));
+    assert_eq!(cli.overrides, ["a=1", "b=x"]);
+  }
+
+  #[test]
+  fn links_are_positional() {
+    let cli = parse(&["--minimized", "refined-line://show"]);
+    assert!(cli.minimized);
+    assert_eq!(cli.links, ["refined-line://show"]);
+  }
+
+  #[test]
+  fn portable_takes_an_optional_dir() {
+    assert_eq!(parse(&["--portable"]).portable.as_deref(), Some(""));
+    assert_eq!(parse(&["--portable=usb"]).portable.as_deref(), Some("usb"));
+  }
+
+  #[test]
+  fn offline_skips_updates() {
+    assert!(parse(&["--offline"]).skips_updates());
+    assert!(parse(&["--no-update"]).skips_updates());
+  }
+
+  #[test]
+  fn rejects_unknown_flags_and_levels() {
+    assert!(CliArgs::try_parse_from(["refined-line", "--nope"]).is_err());
+    assert!(CliArgs::try_parse_from(["refined-line", "--log-level", "loud"]).is_err());
+  }
+}
@@ -1,12 +1,14 @@
-use crate::paths::profile_reset_marker;
-use crate::settings::{load_settings, save_settings, AppSettings};
+use crate::config::{resolve_config, EffectiveConfig};
+use crate::maintenance::{enqueue_maintenance, MaintenanceOp};
+use crate::settings::AppSettings;
+use crate::settings_service::{current_settings, replace_settings};
 use log::info;
 use tauri::Window;
 use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
 
 #[tauri::command]
-pub(crate) fn get_settings(app_handle: tauri::AppHandle) -> Result<AppSettings, String> {
-  load_settings(&app_handle).map_err(|error| error.to_string())
+pub(crate) fn get_settings(app_handle: tauri::AppHandle) -> AppSettings {
+  current_settings(&app_handle)
 }
 
 #[tauri::command]
@@ -14,8 +16,14 @@ pub(crate) fn update_settings(
   app_handle: tauri::AppHandle,
   settings: AppSettings,
 ) -> Result<AppSettings, String> {
-  save_settings(&app_handle, &settings).map_err(|error| error.to_string())?;
-  Ok(settings)
+  replace_settings(&app_handle, settings).map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+pub(crate) fn get_effective_config(
+  app_handle: tauri::AppHandle,
+) -> Result<EffectiveConfig, String> {
+  resolve_config(&app_handle).map_err(|error| error.to_string())
 }
 
 #[tauri::command]
@@ -30,11 +38,8 @@ pub(crate) fn get_is_maximized(window: Window) -> Result<bool, String> {
 
 #[tauri::command]
 pub(crate) fn reset_profile(app_handle: tauri::AppHandle) -> Result<(), String> {
-  let marker = profile_reset_marker(&app_handle).map_err(|error| error.to_string())?;
-  if let Some(parent) = marker.parent() {
-    std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
-  }
-  std::fs::write(&marker, "reset").map_err(|error| error.to_string())?;
+  enqueue_maintenance(&app_handle, MaintenanceOp::ResetProfile)
+    .map_err(|error| error.to_string())?;
   info!("[webview] reset profile requested");
   app_handle.exit(0);
   Ok(())
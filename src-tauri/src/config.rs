@@ -1,24 +1,555 @@
+use crate::cli::{cli_args, CliArgs};
+use crate::paths::{app_paths, machine_config_dir, user_config_path};
+use crate::policy::{load_policy, policy_path};
+use crate::settings::{migrate_settings_document, settings_from_value, AppSettings};
 use anyhow::{anyhow, Result};
+use log::warn;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
 use tauri::path::BaseDirectory;
 use tauri::Manager;
 
+const USER_EXTENSIONS_DIR: &str = "user";
+const ENV_PREFIX: &str = "REFINED_LINE_";
+// Kept for scripts written before the layered resolver existed.
+const LEGACY_LOG_ENV: &str = "REFINED_LINE_LOG";
+const SCHEMA_VERSION_KEY: &str = "schemaVersion";
+
+/// Configuration sources, lowest precedence first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ConfigLayer {
+  Bundled,
+  Machine,
+  User,
+  /// Settings of the named profile this process runs as, saved on top of the shared ones.
+  Profile,
+  Env,
+  Cli,
+  Policy,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LayerReport {
+  layer: ConfigLayer,
+  path: Option<String>,
+  applied: bool,
+  error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct ConfigValue {
+  value: Value,
+  source: ConfigLayer,
+}
+
+/// Merged view of every layer; each top-level key remembers which layer set it last.
+#[derive(Default, Serialize)]
+pub(crate) struct EffectiveConfig {
+  values: BTreeMap<String, ConfigValue>,
+  layers: Vec<LayerReport>,
+  /// The merged document as of the user's own files, before anything that only shapes this run.
+  #[serde(skip)]
+  saved: Map<String, Value>,
+}
+
 #[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub(crate) struct AppConfig {
-  #[serde(rename = "lineExtensionId")]
-  pub(crate) line_extension_id: String,
-  #[serde(rename = "lineEntryPath")]
-  pub(crate) line_entry_path: String,
-  #[serde(rename = "update2BaseUrl")]
+  pub(crate) extensions: Vec<ManagedExtension>,
+  /// When set, replaces the update source of every managed extension.
+  #[serde(default)]
+  pub(crate) update2_base_url: Option<String>,
+  #[serde(default)]
+  pub(crate) profile_backup: ProfileBackupConfig,
+  #[serde(default)]
+  pub(crate) storage_monitor: StorageMonitorConfig,
+  #[serde(default)]
+  pub(crate) cookie_persistence: CookiePersistenceConfig,
+  #[serde(default)]
+  pub(crate) deep_links: DeepLinkConfig,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct ProfileBackupConfig {
+  /// Minimum age of the newest backup before startup takes another; 0 disables the schedule.
+  pub(crate) interval_hours: u64,
+  /// Number of backups kept; older ones are deleted after each new backup.
+  pub(crate) retention: usize,
+}
+
+impl Default for ProfileBackupConfig {
+  fn default() -> Self {
+    Self {
+      interval_hours: 0,
+      retention: 3,
+    }
+  }
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct StorageMonitorConfig {
+  /// How often the storage report is recomputed while running; 0 disables monitoring.
+  pub(crate) interval_hours: u64,
+  /// Total size above which the user is offered a cleanup.
+  pub(crate) threshold_mb: u64,
+}
+
+impl Default for StorageMonitorConfig {
+  fn default() -> Self {
+    Self {
+      interval_hours: 0,
+      threshold_mb: 2048,
+    }
+  }
+}
+
+/// Session cookies of these hosts are rewritten with an expiry so the LINE login survives restarts.
+/// Only the config files set it: `cookiePersistence` in the user's `<root>/config.json`, the
+/// machine config or policy. It is not part of `AppSettings`, so `settings.json` cannot change it,
+/// and it has no UI.
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct CookiePersistenceConfig {
+  pub(crate) domains: Vec<String>,
+  /// Also persist session cookies of every other host in the profile.
+  pub(crate) all_domains: bool,
+  /// Delays after a window opens, in seconds, at which cookies are persisted.
+  pub(crate) schedule_seconds: Vec<u64>,
+  /// Re-run interval after the schedule; 0 disables periodic persistence.
+  pub(crate) interval_minutes: u64,
+  pub(crate) expiry_days: u64,
+  /// Replay the last good cookie snapshot and reload when a logout is detected. Off by
+  /// default: a logout the user chose looks the same and would be undone.
+  pub(crate) restore_on_logout: bool,
+  /// Substrings of the extension URL that identify its login view.
+  pub(crate) login_view_patterns: Vec<String>,
+}
+
+impl Default for CookiePersistenceConfig {
+  fn default() -> Self {
+    Self {
+      domains: vec![
+        "access.line.me".to_string(),
+        "line.me".to_string(),
+        "api.line.me".to_string(),
+      ],
+      all_domains: true,
+      schedule_seconds: vec![10, 30],
+      interval_minutes: 60,
+      expiry_days: 365,
+      restore_on_logout: false,
+      login_view_patterns: vec!["/login".to_string()],
+    }
+  }
+}
+
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct DeepLinkConfig {
+  /// Open `line.me/R/` links in a window of the app instead of the default browser.
+  pub(crate) intercept_line_links: bool,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ManagedExtension {
+  /// Local slot name; also the directory under `extensions/`.
+  pub(crate) id: String,
+  pub(crate) extension_id: String,
   pub(crate) update2_base_url: String,
+  #[serde(default)]
+  pub(crate) entry_path: Option<String>,
+  #[serde(default)]
+  pub(crate) primary: bool,
+  #[serde(default)]
+  pub(crate) patches: Vec<PatchRule>,
+}
+
+#[derive(Clone, Debug, serde::Deserialize)]
+pub(crate) struct PatchRule {
+  pub(crate) file: String,
+  pub(crate) find: String,
+  pub(crate) replace: String,
+}
+
+impl AppConfig {
+  fn validate(&self) -> Result<()> {
+    let primary_count = self
+      .extensions
+      .iter()
+      .filter(|extension| extension.primary)
+      .count();
+    if primary_count != 1 {
+      return Err(anyhow!(
+        "config must have exactly one primary extension (found {primary_count})"
+      ));
+    }
+
+    let mut seen = HashSet::new();
+    for extension in &self.extensions {
+      let valid_id = !extension.id.is_empty()
+        && extension
+          .id
+          .chars()
+          .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+      if !valid_id || extension.id == USER_EXTENSIONS_DIR {
+        return Err(anyhow!("invalid extension id: {:?}", extension.id));
+      }
+      if !seen.insert(extension.id.as_str()) {
+        return Err(anyhow!("duplicate extension id: {}", extension.id));
+      }
+      if extension.primary && extension.entry_path.is_none() {
+        return Err(anyhow!(
+          "primary extension {} has no entryPath",
+          extension.id
+        ));
+      }
+    }
+    Ok(())
+  }
+}
+
+impl EffectiveConfig {
+  fn apply(&mut self, layer: ConfigLayer, path: Option<&Path>, values: Result<Map<String, Value>>) {
+    let path = path.map(|path| path.display().to_string());
+    match values {
+      Ok(values) => {
+        for (key, value) in values {
+          if key == SCHEMA_VERSION_KEY {
+            continue;
+          }
+          self.values.insert(
+            key,
+            ConfigValue {
+              value,
+              source: layer,
+            },
+          );
+        }
+        self.layers.push(LayerReport {
+          layer,
+          path,
+          applied: true,
+          error: None,
+        });
+      }
+      Err(error) => {
+        warn!("[config] {layer:?} layer skipped: {error:#}");
+        self.layers.push(LayerReport {
+          layer,
+          path,
+          applied: false,
+          error: Some(format!("{error:#}")),
+        });
+      }
+    }
+  }
+
+  fn to_document(&self) -> Value {
+    Value::Object(
+      self
+        .values
+        .iter()
+        .map(|(key, entry)| (key.clone(), entry.value.clone()))
+        .collect(),
+    )
+  }
+
+  pub(crate) fn app_config(&self) -> Result<AppConfig> {
+    let mut config: AppConfig = serde_json::from_value(self.to_document())?;
+    if let Some(base_url) = config.update2_base_url.clone() {
+      for extension in config.extensions.iter_mut() {
+        extension.update2_base_url = base_url.clone();
+      }
+    }
+    config.validate()?;
+    Ok(config)
+  }
+
+  /// Settings from the layers up to the user's own files; what the settings service persists.
+  pub(crate) fn saved_settings(&self) -> Result<AppSettings> {
+    settings_from_value(Value::Object(self.saved.clone()))
+  }
+
+  /// Settings values the env or CLI layers set for this run. Values that do not read as the
+  /// setting they name are dropped.
+  pub(crate) fn runtime_overrides(&self) -> Result<Map<String, Value>> {
+    let Value::Object(defaults) = serde_json::to_value(AppSettings::default())? else {
+      return Err(anyhow!("settings root is not an object"));
+    };
+    let mut overrides = Map::new();
+    for (key, entry) in &self.values {
+      let runtime = matches!(entry.source, ConfigLayer::Env | ConfigLayer::Cli);
+      if !runtime || !defaults.contains_key(key) {
+        continue;
+      }
+      let mut candidate = self.saved.clone();
+      candidate.insert(key.clone(), entry.value.clone());
+      // Read back through the settings type so legacy spellings such as `warning` are normalized.
+      match settings_from_value(Value::Object(candidate))
+        .and_then(|settings| Ok(serde_json::to_value(settings)?))
+      {
+        Ok(Value::Object(mut settings)) => {
+          if let Some(value) = settings.remove(key) {
+            overrides.insert(key.clone(), value);
+          }
+        }
+        Ok(_) => {}
+        Err(error) => warn!("[config] ignoring {key} override: {error:#}"),
+      }
+    }
+    Ok(overrides)
+  }
+
+  fn mark_saved(&mut self) {
+    if let Value::Object(document) = self.to_document() {
+      self.saved = document;
+    }
+  }
 }
 
 pub(crate) fn load_config(app: &tauri::AppHandle) -> Result<AppConfig> {
-  let config_path = app
+  resolve_config(app)?.app_config()
+}
+
+pub(crate) fn resolve_config(app: &tauri::AppHandle) -> Result<EffectiveConfig> {
+  let mut config = EffectiveConfig::default();
+
+  let bundled_path = app
     .path()
     .resolve("config.json", BaseDirectory::Resource)
     .map_err(|error| anyhow!("config path error: {error}"))?;
-  let raw = fs::read_to_string(&config_path)?;
-  let config: AppConfig = serde_json::from_str(&raw)?;
+  let mut bundled = read_object(&bundled_path)?.unwrap_or_default();
+  if let Value::Object(defaults) = serde_json::to_value(AppSettings::default())? {
+    for (key, value) in defaults {
+      bundled.entry(key).or_insert(value);
+    }
+  }
+  let known_keys: Vec<String> = bundled.keys().cloned().collect();
+  config.apply(ConfigLayer::Bundled, Some(&bundled_path), Ok(bundled));
+
+  let machine_path = machine_config_dir(app).map(|dir| dir.join("config.json"));
+  apply_file_layer(&mut config, ConfigLayer::Machine, machine_path);
+
+  let user_path = user_config_path(app);
+  apply_file_layer(&mut config, ConfigLayer::User, user_path);
+  let paths = app_paths(app)?;
+  apply_file_layer_with(
+    &mut config,
+    ConfigLayer::User,
+    Ok(paths.shared_settings_file()),
+    settings_layer,
+  );
+  if paths.settings_file() != paths.shared_settings_file() {
+    apply_file_layer_with(
+      &mut config,
+      ConfigLayer::Profile,
+      Ok(paths.settings_file()),
+      settings_layer,
+    );
+  }
+  config.mark_saved();
+
+  config.apply(ConfigLayer::Env, None, Ok(env_overrides(&known_keys)));
+  config.apply(ConfigLayer::Cli, None, Ok(cli_overrides(cli_args())));
+
+  let policy = load_policy(app);
+  if !policy.is_empty() {
+    let path = policy_path(app).ok();
+    config.apply(ConfigLayer::Policy, path.as_deref(), policy.to_values());
+  }
+
   Ok(config)
 }
+
+fn apply_file_layer(config: &mut EffectiveConfig, layer: ConfigLayer, path: Result<PathBuf>) {
+  apply_file_layer_with(config, layer, path, Ok);
+}
+
+fn apply_file_layer_with(
+  config: &mut EffectiveConfig,
+  layer: ConfigLayer,
+  path: Result<PathBuf>,
+  read_values: impl FnOnce(Map<String, Value>) -> Result<Map<String, Value>>,
+) {
+  match path {
+    Ok(path) => match read_object(&path) {
+      Ok(Some(values)) => config.apply(layer, Some(&path), read_values(values)),
+      Ok(None) => config.layers.push(LayerReport {
+        layer,
+        path: Some(path.display().to_string()),
+        applied: false,
+        error: None,
+      }),
+      Err(error) => config.apply(layer, Some(&path), Err(error)),
+    },
+    Err(error) => config.apply(layer, None, Err(error)),
+  }
+}
+
+// Only the settings keys the file actually contains, so a value from a lower layer survives a
+// settings file that never set it. Other keys in the file are not settings and are ignored.
+fn settings_layer(values: Map<String, Value>) -> Result<Map<String, Value>> {
+  let Value::Object(defaults) = serde_json::to_value(AppSettings::default())? else {
+    return Err(anyhow!("settings root is not an object"));
+  };
+  let mut document = migrate_settings_document(Value::Object(values))?;
+  document.retain(|key, _| defaults.contains_key(key));
+  Ok(document)
+}
+
+fn read_object(path: &Path) -> Result<Option<Map<String, Value>>> {
+  let raw = match fs::read_to_string(path) {
+    Ok(raw) => raw,
+    Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+    Err(error) => return Err(error.into()),
+  };
+  match serde_json::from_str(&raw)? {
+    Value::Object(values) => Ok(Some(values)),
+    _ => Err(anyhow!("{} is not a JSON object", path.display())),
+  }
+}
+
+fn env_overrides(known_keys: &[String]) -> Map<String, Value> {
+  let mut values = Map::new();
+  if let Ok(raw) = std::env::var(LEGACY_LOG_ENV) {
+    values.insert("logLevel".to_string(), parse_override_value(&raw));
+  }
+  for key in known_keys {
+    if let Ok(raw) = std::env::var(env_var_name(key)) {
+      values.insert(key.clone(), parse_override_value(&raw));
+    }
+  }
+  values
+}
+
+// `--set key=value` in order, then the dedicated flags, which win over a `--set` of the same key.
+fn cli_overrides(args: &CliArgs) -> Map<String, Value> {
+  let mut values = Map::new();
+  for assignment in &args.overrides {
+    match assignment.split_once('=') {
+      Some((key, raw)) if !key.is_empty() => {
+        values.insert(key.to_string(), parse_override_value(raw));
+      }
+      _ => warn!("[config] ignoring malformed --set {assignment}"),
+    }
+  }
+  if args.minimized {
+    values.insert("startMinimized".to_string(), Value::Bool(true));
+  }
+  if let Some(level) = args.log_level {
+    values.insert(
+      "logLevel".to_string(),
+      Value::String(level.as_str().to_string()),
+    );
+  }
+  values
+}
+
+/// `logLevel` -> `REFINED_LINE_LOG_LEVEL`
+fn env_var_name(key: &str) -> String {
+  let mut name = String::from(ENV_PREFIX);
+  for c in key.chars() {
+    if c.is_ascii_uppercase() {
+      name.push('_');
+    }
+    name.push(c.to_ascii_uppercase());
+  }
+  name
+}
+
+// Values are JSON when they parse as JSON, so `true` and `["a"]` work; anything else is a string.
+fn parse_override_value(raw: &str) -> Value {
+  serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn object(value: Value) -> Map<String, Value> {
+    match value {
+      Value::Object(values) => values,
+      _ => unreachable!(),
+    }
+  }
+
+  #[test]
+  fn machine_settings_survive_a_user_file_without_them() {
+    let mut config = EffectiveConfig::default();
+    let defaults = object(serde_json::to_value(AppSettings::default()).unwrap());
+    config.apply(ConfigLayer::Bundled, None, Ok(defaults));
+    config.apply(
+      ConfigLayer::Machine,
+      None,
+      Ok(object(json!({ "startMinimized": true }))),
+    );
+    let user = object(json!({ "schemaVersion": 1, "autoStart": true }));
+    config.apply(ConfigLayer::User, None, settings_layer(user));
+    config.mark_saved();
+
+    let settings = config.saved_settings().unwrap();
+    assert!(settings.start_minimized);
+    assert!(settings.auto_start);
+    assert_eq!(config.values["startMinimized"].source, ConfigLayer::Machine);
+    assert_eq!(config.values["autoStart"].source, ConfigLayer::User);
+  }
+
+  #[test]
+  fn runtime_overrides_stay_out_of_saved_settings() {
+    let mut config = EffectiveConfig::default();
+    let defaults = object(serde_json::to_value(AppSettings::default()).unwrap());
+    config.apply(ConfigLayer::Bundled, None, Ok(defaults));
+    let user = object(json!({ "schemaVersion": 1, "contentProtection": true }));
+    config.apply(ConfigLayer::User, None, settings_layer(user));
+    config.mark_saved();
+    config.apply(
+      ConfigLayer::Env,
+      None,
+      Ok(object(json!({
+        "autoStart": "sometimes",
+        "contentProtection": false,
+        "logLevel": "warning",
+      }))),
+    );
+    config.apply(
+      ConfigLayer::Cli,
+      None,
+      Ok(object(json!({ "startMinimized": true, "extensions": [] }))),
+    );
+
+    let saved = config.saved_settings().unwrap();
+    assert!(saved.content_protection);
+    assert!(!saved.start_minimized);
+    let overrides = config.runtime_overrides().unwrap();
+    assert_eq!(
+      overrides,
+      object(json!({
+        "contentProtection": false,
+        "logLevel": "warn",
+        "startMinimized": true,
+      }))
+    );
+  }
+
+  #[test]
+  fn settings_layer_migrates_and_drops_foreign_keys() {
+    let layer = settings_layer(object(json!({
+      "logLevel": "warning",
+      "extensions": [],
+    })))
+    .unwrap();
+    assert_eq!(layer.get("logLevel"), Some(&json!("warn")));
+    assert!(!layer.contains_key("extensions"));
+    assert!(!layer.contains_key("autoStart"));
+  }
+}
@@ -1,4 +1,4 @@
-use crate::settings::update_content_protection;
+use crate::settings_service::modify_settings;
 use log::{debug, info, warn};
 use std::collections::HashMap;
 use std::sync::{
@@ -30,14 +30,6 @@ pub(crate) fn is_content_protected<R: Runtime>(app_handle: &tauri::AppHandle<R>)
     .load(Ordering::Relaxed)
 }
 
-pub(crate) fn set_content_protection_from_app(
-  app_handle: &tauri::AppHandle,
-  enabled: bool,
-) -> Result<bool, String> {
-  let state = app_handle.state::<WindowState>();
-  set_content_protection_state(app_handle, &state, enabled)
-}
-
 fn apply_content_protection<R: Runtime>(
   app_handle: &tauri::AppHandle<R>,
   protected: bool,
@@ -57,7 +49,7 @@ pub(crate) fn toggle_content_protection(
   state: State<WindowState>,
 ) -> Result<bool, String> {
   let enabled = !state.protected.load(Ordering::Relaxed);
-  set_content_protection_state(&app_handle, &state, enabled)
+  set_content_protection_setting(&app_handle, enabled)
 }
 
 #[tauri::command]
@@ -68,30 +60,31 @@ pub(crate) fn get_content_protection(state: State<WindowState>) -> Result<bool,
 #[tauri::command]
 pub(crate) fn set_content_protection(
   app_handle: tauri::AppHandle,
-  state: State<WindowState>,
   enabled: bool,
 ) -> Result<bool, String> {
-  set_content_protection_state(&app_handle, &state, enabled)
+  set_content_protection_setting(&app_handle, enabled)
 }
 
-fn set_content_protection_state(
+fn set_content_protection_setting(
   app_handle: &tauri::AppHandle,
-  state: &State<WindowState>,
   enabled: bool,
 ) -> Result<bool, String> {
-  state.protected.store(enabled, Ordering::Relaxed);
+  modify_settings(app_handle, move |settings| {
+    settings.content_protection = enabled
+  })
+  .map(|settings| settings.content_protection)
+  .map_err(|error| error.to_string())
+}
+
+/// Updates the shared state and every open window; persistence is up to the settings service.
+pub(crate) fn apply_content_protection_state(app_handle: &tauri::AppHandle, enabled: bool) {
+  app_handle
+    .state::<WindowState>()
+    .protected
+    .store(enabled, Ordering::Relaxed);
   let count = apply_content_protection(app_handle, enabled);
-  if let Err(error) = update_content_protection(app_handle, enabled) {
-    warn!("[content-protected] save failed: {error:#}");
-  }
-  crate::app_menu::set_menu_checked(
-    app_handle,
-    crate::app_menu::menu_content_protection_id(),
-    enabled,
-  );
   let _ = app_handle.emit("content-protection-changed", enabled);
   info!("[content-protected] set {enabled} windows={count}");
-  Ok(enabled)
 }
 
 pub(crate) fn set_content_protected<R: Runtime>(
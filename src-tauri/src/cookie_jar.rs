@@ -0,0 +1,473 @@
+use crate::atomic_write::write_atomic;
+use crate::profiles::is_profile_window;
+use anyhow::{anyhow, Result};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri_plugin_dialog::DialogExt;
+
+const MAGIC: &[u8; 4] = b"RLCJ";
+const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4 + SALT_LEN + NONCE_LEN;
+// The iteration count is stored in the header so it can be raised without breaking old files.
+const PBKDF2_ITERATIONS: u32 = 600_000;
+// Files may ask for more rounds than we write today, but not enough to hang the app.
+const MAX_PBKDF2_ITERATIONS: u32 = PBKDF2_ITERATIONS * 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum CookieSameSite {
+  None,
+  Lax,
+  Strict,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CookieRecord {
+  pub(crate) name: String,
+  pub(crate) value: String,
+  pub(crate) domain: String,
+  pub(crate) path: String,
+  pub(crate) http_only: bool,
+  pub(crate) secure: bool,
+  pub(crate) same_site: CookieSameSite,
+  /// Seconds since the Unix epoch; `None` for session cookies.
+  pub(crate) expires: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CookieJar {
+  pub(crate) exported_at: u64,
+  pub(crate) cookies: Vec<CookieRecord>,
+}
+
+impl CookieJar {
+  pub(crate) fn new(cookies: Vec<CookieRecord>) -> Self {
+    let exported_at = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_secs();
+    Self {
+      exported_at,
+      cookies,
+    }
+  }
+}
+
+/// Serializes `jar` and encrypts it with a key derived from `passphrase`.
+///
+/// Layout: magic, format version, PBKDF2 iterations (LE), salt, nonce, ciphertext. The header is
+/// authenticated as associated data.
+pub(crate) fn seal_cookie_jar(jar: &CookieJar, passphrase: &str) -> Result<Vec<u8>> {
+  seal_with_iterations(jar, passphrase, PBKDF2_ITERATIONS)
+}
+
+/// Opens a sealed jar. The header's iteration count must lie between today's count and ten
+/// times it, so a crafted file can neither weaken the key nor stall the import.
+pub(crate) fn open_cookie_jar(data: &[u8], passphrase: &str) -> Result<CookieJar> {
+  open_with_iterations(data, passphrase, PBKDF2_ITERATIONS..=MAX_PBKDF2_ITERATIONS)
+}
+
+fn open_with_iterations(
+  data: &[u8],
+  passphrase: &str,
+  allowed: RangeInclusive<u32>,
+) -> Result<CookieJar> {
+  if data.len() < HEADER_LEN || &data[..MAGIC.len()] != MAGIC {
+    return Err(anyhow!("not a cookie jar file"));
+  }
+  let version = data[MAGIC.len()];
+  if version != FORMAT_VERSION {
+    return Err(anyhow!("unsupported cookie jar version {version}"));
+  }
+  let mut offset = MAGIC.len() + 1;
+  let iterations = u32::from_le_bytes(data[offset..offset + 4].try_into()?);
+  if !allowed.contains(&iterations) {
+    return Err(anyhow!("unsupported PBKDF2 iteration count {iterations}"));
+  }
+  offset += 4;
+  let salt = &data[offset..offset + SALT_LEN];
+  offset += SALT_LEN;
+  let nonce = Nonce::from_slice(&data[offset..offset + NONCE_LEN]);
+
+  let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, salt, iterations));
+  let plaintext = cipher
+    .decrypt(
+      nonce,
+      Payload {
+        msg: &data[HEADER_LEN..],
+        aad: &data[..HEADER_LEN],
+      },
+    )
+    .map_err(|_| anyhow!("wrong passphrase or corrupted cookie jar"))?;
+  Ok(serde_json::from_slice(&plaintext)?)
+}
+
+pub(crate) fn write_cookie_jar(path: &Path, jar: &CookieJar, passphrase: &str) -> Result<()> {
+  write_atomic(path, &seal_cookie_jar(jar, passphrase)?)
+}
+
+pub(crate) fn read_cookie_jar(path: &Path, passphrase: &str) -> Result<CookieJar> {
+  open_cookie_jar(&fs::read(path)?, passphrase)
+}
+
+fn seal_with_iterations(jar: &CookieJar, passphrase: &str, iterations: u32) -> Result<Vec<u8>> {
+  let mut salt = [0u8; SALT_LEN];
+  OsRng.fill_bytes(&mut salt);
+  let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+  let mut out = Vec::with_capacity(HEADER_LEN);
+  out.extend_from_slice(MAGIC);
+  out.push(FORMAT_VERSION);
+  out.extend_from_slice(&iterations.to_le_bytes());
+  out.extend_from_slice(&salt);
+  out.extend_from_slice(&nonce);
+
+  let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt, iterations));
+  let plaintext = serde_json::to_vec(jar)?;
+  let ciphertext = cipher
+    .encrypt(
+      &nonce,
+      Payload {
+        msg: &plaintext,
+        aad: &out,
+      },
+    )
+    .map_err(|_| anyhow!("cookie jar encryption failed"))?;
+  out.extend_from_slice(&ciphertext);
+  Ok(out)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Key {
+  let mut key = [0u8; 32];
+  pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+  key.into()
+}
+
+// Popups run in their own webview; the cookies belong to the profile window that owns them.
+fn cookie_window_label(window: &tauri::WebviewWindow) -> String {
+  if is_profile_window(window.label()) {
+    window.label().to_string()
+  } else {
+    "main".to_string()
+  }
+}
+
+#[cfg(target_os = "windows")]
+async fn with_cookie_webview<T, F>(app: &tauri::AppHandle, label: &str, f: F) -> Result<T>
+where
+  T: Send + 'static,
+  F: FnOnce(&crate::browser::WebView2Backend) -> Result<T> + Send + 'static,
+{
+  use tauri::Manager;
+
+  let window = app
+    .get_webview_window(label)
+    .ok_or_else(|| anyhow!("window {label} not found"))?;
+  let (tx, mut rx) = tauri::async_runtime::channel(1);
+  window.with_webview(move |webview| {
+    let result = crate::browser::WebView2Backend::new(&webview).and_then(|backend| f(&backend));
+    let _ = tx.try_send(result);
+  })?;
+  rx.recv()
+    .await
+    .ok_or_else(|| anyhow!("webview closed before cookies were read"))?
+}
+
+#[cfg(target_os = "windows")]
+async fn export_window_cookies(
+  app: &tauri::AppHandle,
+  label: &str,
+  path: &Path,
+  passphrase: &str,
+) -> Result<usize> {
+  let cookies = with_cookie_webview(app, label, |backend| {
+    crate::extensions::read_cookie_records(backend)
+  })
+  .await?;
+  let count = cookies.len();
+  // Key derivation takes hundreds of milliseconds; keep it off the async executor.
+  let path = path.to_path_buf();
+  let passphrase = passphrase.to_string();
+  tauri::async_runtime::spawn_blocking(move || {
+    write_cookie_jar(&path, &CookieJar::new(cookies), &passphrase)
+  })
+  .await??;
+  Ok(count)
+}
+
+#[cfg(not(target_os = "windows"))]
+async fn export_window_cookies(
+  _app: &tauri::AppHandle,
+  _label: &str,
+  _path: &Path,
+  _passphrase: &str,
+) -> Result<usize> {
+  Err(anyhow!("cookie export is only supported on Windows"))
+}
+
+#[cfg(target_os = "windows")]
+async fn import_window_cookies(
+  app: &tauri::AppHandle,
+  label: &str,
+  path: &Path,
+  passphrase: &str,
+) -> Result<usize> {
+  let path = path.to_path_buf();
+  let passphrase = passphrase.to_string();
+  let jar =
+    tauri::async_runtime::spawn_blocking(move || read_cookie_jar(&path, &passphrase)).await??;
+  with_cookie_webview(app, label, move |backend| {
+    crate::extensions::replay_cookie_records(backend, &jar.cookies)
+  })
+  .await
+}
+
+#[cfg(not(target_os = "windows"))]
+async fn import_window_cookies(
+  _app: &tauri::AppHandle,
+  _label: &str,
+  _path: &Path,
+  _passphrase: &str,
+) -> Result<usize> {
+  Err(anyhow!("cookie import is only supported on Windows"))
+}
+
+fn show_cookie_message(app: &tauri::AppHandle, message: String) {
+  app
+    .dialog()
+    .message(message)
+    .title("ログイン情報")
+    .show(|_| {});
+}
+
+#[tauri::command]
+pub(crate) async fn export_cookies(
+  app_handle: tauri::AppHandle,
+  window: tauri::WebviewWindow,
+  passphrase: String,
+) -> Result<Option<String>, String> {
+  if passphrase.is_empty() {
+    return Err("passphrase is empty".to_string());
+  }
+  let (tx, mut rx) = tauri::async_runtime::channel(1);
+  app_handle
+    .dialog()
+    .file()
+    .add_filter("refined-line ログイン情報", &["rlcookies"])
+    .set_file_name("refined-line.rlcookies")
+    .save_file(move |path| {
+      let _ = tx.try_send(path);
+    });
+  let Some(path) = rx.recv().await.flatten() else {
+    return Ok(None);
+  };
+  let path = path.into_path().map_err(|error| error.to_string())?;
+  let label = cookie_window_label(&window);
+  match export_window_cookies(&app_handle, &label, &path, &passphrase).await {
+    Ok(count) => {
+      info!("[cookie] exported {count} cookies from {label}");
+      show_cookie_message(
+        &app_handle,
+        format!("{count} 件の Cookie をエクスポートしました。"),
+      );
+      Ok(Some(path.display().to_string()))
+    }
+    Err(error) => {
+      warn!("[cookie] export failed: {error:#}");
+      show_cookie_message(
+        &app_handle,
+        format!("エクスポートに失敗しました。\n{error:#}"),
+      );
+      Err(error.to_string())
+    }
+  }
+}
+
+#[tauri::command]
+pub(crate) async fn import_cookies(
+  app_handle: tauri::AppHandle,
+  window: tauri::WebviewWindow,
+  passphrase: String,
+) -> Result<Option<usize>, String> {
+  if passphrase.is_empty() {
+    return Err("passphrase is empty".to_string());
+  }
+  let (tx, mut rx) = tauri::async_runtime::channel(1);
+  app_handle
+    .dialog()
+    .file()
+    .add_filter("refined-line ログイン情報", &["rlcookies"])
+    .pick_file(move |path| {
+      let _ = tx.try_send(path);
+    });
+  let Some(path) = rx.recv().await.flatten() else {
+    return Ok(None);
+  };
+  let path = path.into_path().map_err(|error| error.to_string())?;
+  let label = cookie_window_label(&window);
+  match import_window_cookies(&app_handle, &label, &path, &passphrase).await {
+    Ok(count) => {
+      info!("[cookie] imported {count} cookies into {label}");
+      show_cookie_message(
+        &app_handle,
+        format!(
+          "{count} 件の Cookie をインポートしました。\nページを再読み込みすると反映されます。"
+        ),
+      );
+      Ok(Some(count))
+    }
+    Err(error) => {
+      warn!("[cookie] import failed: {error:#}");
+      show_cookie_message(
+        &app_handle,
+        format!("インポートに失敗しました。パスフレーズを確認してください。\n{error:#}"),
+      );
+      Err(error.to_string())
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Keeps the tests fast; the format reads the count from the header anyway.
+  const TEST_ITERATIONS: u32 = 1_000;
+
+  fn open(data: &[u8], passphrase: &str) -> Result<CookieJar> {
+    open_with_iterations(data, passphrase, TEST_ITERATIONS..=TEST_ITERATIONS)
+  }
+
+  fn sample_jar() -> CookieJar {
+    CookieJar {
+      exported_at: 1_700_000_000,
+      cookies: vec![
+        CookieRecord {
+          name: "ses".to_string(),
+          value: "a=b;c".to_string(),
+          domain: ".line.me".to_string(),
+          path: "/".to_string(),
+          http_only: true,
+          secure: true,
+          same_site: CookieSameSite::None,
+          expires: None,
+        },
+        CookieRecord {
+          name: "lang".to_string(),
+          value: "ja".to_string(),
+          domain: "access.line.me".to_string(),
+          path: "/oauth2".to_string(),
+          http_only: false,
+          secure: false,
+          same_site: CookieSameSite::Strict,
+          expires: Some(1_800_000_000.5),
+        },
+      ],
+    }
+  }
+
+  #[test]
+  fn round_trips_every_field() {
+    let jar = sample_jar();
+    let sealed = seal_with_iterations(&jar, "correct horse", TEST_ITERATIONS).unwrap();
+    assert_eq!(open(&sealed, "correct horse").unwrap(), jar);
+  }
+
+  #[test]
+  fn round_trips_empty_jar() {
+    let jar = CookieJar {
+      exported_at: 0,
+      cookies: Vec::new(),
+    };
+    let sealed = seal_with_iterations(&jar, "p", TEST_ITERATIONS).unwrap();
+    assert_eq!(open(&sealed, "p").unwrap(), jar);
+  }
+
+  #[test]
+  fn output_does_not_contain_plaintext() {
+    let sealed = seal_with_iterations(&sample_jar(), "p", TEST_ITERATIONS).unwrap();
+    let needle = b"line.me";
+    assert!(!sealed.windows(needle.len()).any(|window| window == needle));
+  }
+
+  #[test]
+  fn each_seal_uses_fresh_salt_and_nonce() {
+    let jar = sample_jar();
+    let first = seal_with_iterations(&jar, "p", TEST_ITERATIONS).unwrap();
+    let second = seal_with_iterations(&jar, "p", TEST_ITERATIONS).unwrap();
+    assert_ne!(first[..HEADER_LEN], second[..HEADER_LEN]);
+    assert_ne!(first, second);
+  }
+
+  #[test]
+  fn wrong_passphrase_is_rejected() {
+    let sealed = seal_with_iterations(&sample_jar(), "right", TEST_ITERATIONS).unwrap();
+    assert!(open(&sealed, "wrong").is_err());
+  }
+
+  #[test]
+  fn tampering_is_detected() {
+    let sealed = seal_with_iterations(&sample_jar(), "p", TEST_ITERATIONS).unwrap();
+
+    let mut body = sealed.clone();
+    let last = body.len() - 1;
+    body[last] ^= 1;
+    assert!(open(&body, "p").is_err());
+
+    // The header is authenticated too, so lowering the iteration count fails.
+    let mut header = sealed;
+    header[MAGIC.len() + 1] ^= 1;
+    assert!(open_with_iterations(&header, "p", 1..=TEST_ITERATIONS).is_err());
+  }
+
+  #[test]
+  fn foreign_and_truncated_files_are_rejected() {
+    assert!(open(b"", "p").is_err());
+    assert!(open(b"PK\x03\x04 not a jar at all, just a zip", "p").is_err());
+    let sealed = seal_with_iterations(&sample_jar(), "p", TEST_ITERATIONS).unwrap();
+    assert!(open(&sealed[..HEADER_LEN - 1], "p").is_err());
+    assert!(open(&sealed[..HEADER_LEN], "p").is_err());
+  }
+
+  #[test]
+  fn iteration_count_out_of_bounds_is_rejected() {
+    let sealed = seal_with_iterations(&sample_jar(), "p", TEST_ITERATIONS).unwrap();
+    let error = open_cookie_jar(&sealed, "p").unwrap_err();
+    assert!(error.to_string().contains("iteration"));
+
+    let mut inflated = sealed;
+    let offset = MAGIC.len() + 1;
+    inflated[offset..offset + 4].copy_from_slice(&(MAX_PBKDF2_ITERATIONS + 1).to_le_bytes());
+    let error = open_cookie_jar(&inflated, "p").unwrap_err();
+    assert!(error.to_string().contains("iteration"));
+  }
+
+  #[test]
+  fn newer_format_version_is_rejected() {
+    let mut sealed = seal_with_iterations(&sample_jar(), "p", TEST_ITERATIONS).unwrap();
+    sealed[MAGIC.len()] = FORMAT_VERSION + 1;
+    let error = open(&sealed, "p").unwrap_err();
+    assert!(error.to_string().contains("version"));
+  }
+
+  #[test]
+  fn same_site_serializes_in_camel_case() {
+    let raw = serde_json::to_string(&sample_jar()).unwrap();
+    assert!(raw.contains(r#""sameSite":"none""#));
+    assert!(raw.contains(r#""sameSite":"strict""#));
+    assert!(raw.contains(r#""httpOnly":true"#));
+    assert!(raw.contains(r#""expires":null"#));
+  }
+}
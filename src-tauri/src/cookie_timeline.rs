@@ -0,0 +1,453 @@
+use crate::atomic_write::write_atomic;
+use crate::cookie_jar::{CookieRecord, CookieSameSite};
+use crate::paths::app_paths;
+use crate::profiles::{is_profile_window, profile_of_window};
+use anyhow::Result;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::Manager;
+use tauri_plugin_dialog::DialogExt;
+
+/// Oldest snapshots are dropped beyond this; identical snapshots are never stored twice.
+const MAX_SNAPSHOTS: usize = 100;
+
+/// What the timeline keeps of a cookie. The value is only kept as a salted hash, so a changed
+/// value is visible without the value itself ever being written.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CookieMeta {
+  name: String,
+  domain: String,
+  path: String,
+  http_only: bool,
+  secure: bool,
+  same_site: CookieSameSite,
+  expires: Option<f64>,
+  value_hash: String,
+}
+
+impl CookieMeta {
+  fn new(record: &CookieRecord, salt: &str) -> Self {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(record.value.as_bytes());
+    let digest = hasher.finalize();
+    Self {
+      name: record.name.clone(),
+      domain: record.domain.clone(),
+      path: record.path.clone(),
+      http_only: record.http_only,
+      secure: record.secure,
+      same_site: record.same_site,
+      expires: record.expires,
+      value_hash: hex(&digest[..8]),
+    }
+  }
+
+  fn same_cookie(&self, other: &CookieMeta) -> bool {
+    self.name == other.name && self.domain == other.domain && self.path == other.path
+  }
+
+  fn changed_fields(&self, other: &CookieMeta) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if self.value_hash != other.value_hash {
+      fields.push("value");
+    }
+    if self.expires != other.expires {
+      fields.push("expires");
+    }
+    if self.http_only != other.http_only {
+      fields.push("httpOnly");
+    }
+    if self.secure != other.secure {
+      fields.push("secure");
+    }
+    if self.same_site != other.same_site {
+      fields.push("sameSite");
+    }
+    fields
+  }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TimelineSnapshot {
+  at: u64,
+  tag: String,
+  cookies: Vec<CookieMeta>,
+}
+
+/// Stored per profile. The salt never leaves this file, so exported hashes cannot be matched
+/// against guessed values.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TimelineFile {
+  salt: String,
+  snapshots: Vec<TimelineSnapshot>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CookieChange {
+  before: CookieMeta,
+  after: CookieMeta,
+  fields: Vec<&'static str>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TimelineEntry {
+  at: u64,
+  tag: String,
+  count: usize,
+  session: usize,
+  /// Set on the oldest snapshot still kept. Whatever came before it was trimmed, so it has
+  /// nothing to be compared with and its lists stay empty.
+  baseline: bool,
+  added: Vec<CookieMeta>,
+  removed: Vec<CookieMeta>,
+  changed: Vec<CookieChange>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CookieTimeline {
+  profile_id: String,
+  entries: Vec<TimelineEntry>,
+}
+
+#[derive(Default)]
+pub(crate) struct CookieTimelineState {
+  // Serializes read-modify-write of the timeline files.
+  lock: Mutex<()>,
+}
+
+pub(crate) fn init_cookie_timeline_state(app: &tauri::AppHandle) {
+  app.manage(CookieTimelineState::default());
+}
+
+/// Appends the cookie set of `label` to its profile's timeline unless nothing changed.
+pub(crate) fn record_cookie_timeline(
+  app: &tauri::AppHandle,
+  label: &str,
+  tag: &str,
+  records: &[CookieRecord],
+) -> Result<()> {
+  let state = app.try_state::<CookieTimelineState>();
+  let _guard = state
+    .as_ref()
+    .map(|state| state.lock.lock().unwrap_or_else(|error| error.into_inner()));
+  let path = timeline_path(app, label)?;
+  let mut file = read_timeline(&path);
+  if !push_snapshot(&mut file, tag, records, unix_now()) {
+    return Ok(());
+  }
+  debug!(
+    "[cookie] {label} {tag} timeline snapshot count={}",
+    records.len()
+  );
+  write_atomic(&path, &serde_json::to_vec(&file)?)
+}
+
+/// Appends a snapshot and trims the oldest ones; returns false when it matched the last one.
+fn push_snapshot(file: &mut TimelineFile, tag: &str, records: &[CookieRecord], at: u64) -> bool {
+  if file.salt.is_empty() {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    file.salt = hex(&salt);
+  }
+  let mut cookies: Vec<CookieMeta> = records
+    .iter()
+    .map(|record| CookieMeta::new(record, &file.salt))
+    .collect();
+  cookies.sort_by(|a, b| (&a.domain, &a.path, &a.name).cmp(&(&b.domain, &b.path, &b.name)));
+  if file
+    .snapshots
+    .last()
+    .is_some_and(|last| last.cookies == cookies)
+  {
+    return false;
+  }
+  file.snapshots.push(TimelineSnapshot {
+    at,
+    tag: tag.to_string(),
+    cookies,
+  });
+  let excess = file.snapshots.len().saturating_sub(MAX_SNAPSHOTS);
+  file.snapshots.drain(..excess);
+  true
+}
+
+pub(crate) fn build_cookie_timeline(app: &tauri::AppHandle, label: &str) -> Result<CookieTimeline> {
+  let file = read_timeline(&timeline_path(app, label)?);
+  Ok(CookieTimeline {
+    profile_id: profile_of_window(app, label),
+    entries: timeline_entries(&file.snapshots),
+  })
+}
+
+fn timeline_entries(snapshots: &[TimelineSnapshot]) -> Vec<TimelineEntry> {
+  let mut entries = Vec::with_capacity(snapshots.len());
+  let mut previous: Option<&[CookieMeta]> = None;
+  for snapshot in snapshots {
+    entries.push(match previous {
+      Some(previous) => diff_snapshots(previous, snapshot),
+      None => TimelineEntry {
+        baseline: true,
+        ..diff_snapshots(&snapshot.cookies, snapshot)
+      },
+    });
+    previous = Some(&snapshot.cookies);
+  }
+  entries
+}
+
+fn diff_snapshots(previous: &[CookieMeta], snapshot: &TimelineSnapshot) -> TimelineEntry {
+  let mut added = Vec::new();
+  let mut changed = Vec::new();
+  for cookie in &snapshot.cookies {
+    match previous.iter().find(|before| before.same_cookie(cookie)) {
+      None => added.push(cookie.clone()),
+      Some(before) => {
+        let fields = before.changed_fields(cookie);
+        if !fields.is_empty() {
+          changed.push(CookieChange {
+            before: before.clone(),
+            after: cookie.clone(),
+            fields,
+          });
+        }
+      }
+    }
+  }
+  let removed = previous
+    .iter()
+    .filter(|before| {
+      !snapshot
+        .cookies
+        .iter()
+        .any(|cookie| cookie.same_cookie(before))
+    })
+    .cloned()
+    .collect();
+  TimelineEntry {
+    at: snapshot.at,
+    tag: snapshot.tag.clone(),
+    count: snapshot.cookies.len(),
+    session: snapshot
+      .cookies
+      .iter()
+      .filter(|cookie| cookie.expires.is_none())
+      .count(),
+    baseline: false,
+    added,
+    removed,
+    changed,
+  }
+}
+
+fn timeline_path(app: &tauri::AppHandle, label: &str) -> Result<PathBuf> {
+  Ok(app_paths(app)?.cookie_timeline(&profile_of_window(app, label)))
+}
+
+fn read_timeline(path: &Path) -> TimelineFile {
+  let Ok(raw) = fs::read(path) else {
+    return TimelineFile::default();
+  };
+  serde_json::from_slice(&raw).unwrap_or_else(|error| {
+    warn!("[cookie] timeline unreadable, starting over: {error}");
+    TimelineFile::default()
+  })
+}
+
+fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn unix_now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_secs()
+}
+
+fn timeline_window_label(window: &tauri::WebviewWindow) -> &str {
+  if is_profile_window(window.label()) {
+    window.label()
+  } else {
+    "main"
+  }
+}
+
+#[tauri::command]
+pub(crate) async fn get_cookie_timeline(
+  app_handle: tauri::AppHandle,
+  window: tauri::WebviewWindow,
+) -> Result<CookieTimeline, String> {
+  let label = timeline_window_label(&window).to_string();
+  tauri::async_runtime::spawn_blocking(move || build_cookie_timeline(&app_handle, &label))
+    .await
+    .map_err(|error| error.to_string())?
+    .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+pub(crate) async fn export_cookie_timeline(
+  app_handle: tauri::AppHandle,
+  window: tauri::WebviewWindow,
+) -> Result<Option<String>, String> {
+  let (tx, mut rx) = tauri::async_runtime::channel(1);
+  app_handle
+    .dialog()
+    .file()
+    .add_filter("JSON", &["json"])
+    .set_file_name("refined-line-cookie-timeline.json")
+    .save_file(move |path| {
+      let _ = tx.try_send(path);
+    });
+  let Some(path) = rx.recv().await.flatten() else {
+    return Ok(None);
+  };
+  let path = path.into_path().map_err(|error| error.to_string())?;
+  let timeline = build_cookie_timeline(&app_handle, timeline_window_label(&window))
+    .map_err(|error| error.to_string())?;
+  let raw = serde_json::to_vec_pretty(&timeline).map_err(|error| error.to_string())?;
+  fs::write(&path, raw).map_err(|error| error.to_string())?;
+  Ok(Some(path.display().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn record(name: &str, value: &str) -> CookieRecord {
+    CookieRecord {
+      name: name.to_string(),
+      value: value.to_string(),
+      domain: ".line.me".to_string(),
+      path: "/".to_string(),
+      http_only: true,
+      secure: true,
+      same_site: CookieSameSite::Lax,
+      expires: None,
+    }
+  }
+
+  fn meta(name: &str, value: &str) -> CookieMeta {
+    CookieMeta::new(&record(name, value), "salt")
+  }
+
+  fn snapshot(cookies: Vec<CookieMeta>) -> TimelineSnapshot {
+    TimelineSnapshot {
+      at: 1,
+      tag: "test".to_string(),
+      cookies,
+    }
+  }
+
+  #[test]
+  fn changed_fields_lists_each_difference() {
+    let before = meta("ses", "a");
+    assert!(before.changed_fields(&before.clone()).is_empty());
+
+    let mut after = meta("ses", "b");
+    after.expires = Some(1_800_000_000.0);
+    after.secure = false;
+    after.same_site = CookieSameSite::Strict;
+    assert_eq!(
+      before.changed_fields(&after),
+      ["value", "expires", "secure", "sameSite"]
+    );
+  }
+
+  #[test]
+  fn value_hash_depends_on_the_salt() {
+    let cookie = record("ses", "a");
+    assert_ne!(
+      CookieMeta::new(&cookie, "one").value_hash,
+      CookieMeta::new(&cookie, "two").value_hash
+    );
+  }
+
+  #[test]
+  fn diff_reports_added_removed_and_changed() {
+    let previous = [meta("kept", "1"), meta("gone", "1"), meta("edited", "1")];
+    let entry = diff_snapshots(
+      &previous,
+      &snapshot(vec![
+        meta("kept", "1"),
+        meta("edited", "2"),
+        meta("new", "1"),
+      ]),
+    );
+    assert_eq!(entry.count, 3);
+    assert_eq!(entry.session, 3);
+    assert_eq!(entry.added, [meta("new", "1")]);
+    assert_eq!(entry.removed, [meta("gone", "1")]);
+    assert_eq!(entry.changed.len(), 1);
+    assert_eq!(entry.changed[0].before, meta("edited", "1"));
+    assert_eq!(entry.changed[0].after, meta("edited", "2"));
+    assert_eq!(entry.changed[0].fields, ["value"]);
+  }
+
+  #[test]
+  fn diff_against_nothing_is_all_added() {
+    let entry = diff_snapshots(&[], &snapshot(vec![meta("a", "1"), meta("b", "1")]));
+    assert_eq!(entry.added.len(), 2);
+    assert!(entry.removed.is_empty() && entry.changed.is_empty());
+  }
+
+  #[test]
+  fn oldest_kept_snapshot_is_a_baseline() {
+    let mut file = TimelineFile::default();
+    for at in 0..MAX_SNAPSHOTS as u64 + 1 {
+      push_snapshot(&mut file, "tick", &[record("n", &at.to_string())], at);
+    }
+    let entries = timeline_entries(&file.snapshots);
+    assert!(entries[0].baseline);
+    assert!(entries[0].added.is_empty() && entries[0].removed.is_empty());
+    assert!(entries[0].changed.is_empty());
+    assert_eq!(entries[0].count, 1);
+    assert!(!entries[1].baseline);
+    assert_eq!(entries[1].changed.len(), 1);
+  }
+
+  #[test]
+  fn identical_snapshot_is_not_stored() {
+    let mut file = TimelineFile::default();
+    let records = [record("b", "1"), record("a", "1")];
+    assert!(push_snapshot(&mut file, "start", &records, 1));
+    assert!(!file.salt.is_empty());
+
+    // Order does not matter; the snapshot is sorted before comparing.
+    let reordered = [record("a", "1"), record("b", "1")];
+    assert!(!push_snapshot(&mut file, "again", &reordered, 2));
+    assert_eq!(file.snapshots.len(), 1);
+
+    assert!(push_snapshot(&mut file, "changed", &[record("a", "2")], 3));
+    assert_eq!(file.snapshots.len(), 2);
+  }
+
+  #[test]
+  fn oldest_snapshots_are_trimmed() {
+    let mut file = TimelineFile::default();
+    for at in 0..MAX_SNAPSHOTS as u64 + 5 {
+      assert!(push_snapshot(
+        &mut file,
+        "tick",
+        &[record("n", &at.to_string())],
+        at
+      ));
+    }
+    assert_eq!(file.snapshots.len(), MAX_SNAPSHOTS);
+    assert_eq!(file.snapshots[0].at, 5);
+    assert_eq!(file.snapshots.last().unwrap().at, MAX_SNAPSHOTS as u64 + 4);
+  }
+}
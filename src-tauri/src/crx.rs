@@ -5,7 +5,7 @@ use prost::Message;
 use serde_json::Value;
 use sha2::{Digest, Sha256};
 use std::fs;
-use std::io::{Cursor, Read, Write};
+use std::io::{Cursor, Read, Seek, Write};
 use std::path::Path;
 use std::time::Duration;
 use url::Url;
@@ -185,7 +185,7 @@ fn format_extension_id(raw_id: &[u8]) -> String {
     .collect()
 }
 
-fn extension_id_from_public_key(public_key: &[u8]) -> String {
+pub(crate) fn extension_id_from_public_key(public_key: &[u8]) -> String {
   let digest = Sha256::digest(public_key);
   format_extension_id(&digest[..16])
 }
@@ -199,9 +199,14 @@ pub(crate) fn ensure_clean_dir(path: &Path) -> Result<()> {
 }
 
 pub(crate) fn extract_zip(zip_bytes: &[u8], dest: &Path) -> Result<()> {
-  let reader = Cursor::new(zip_bytes);
-  let mut archive = ZipArchive::new(reader)?;
+  extract_archive(ZipArchive::new(Cursor::new(zip_bytes))?, dest)
+}
+
+pub(crate) fn extract_zip_file(zip_path: &Path, dest: &Path) -> Result<()> {
+  extract_archive(ZipArchive::new(fs::File::open(zip_path)?)?, dest)
+}
 
+fn extract_archive<R: Read + Seek>(mut archive: ZipArchive<R>, dest: &Path) -> Result<()> {
   for i in 0..archive.len() {
     let mut file = archive.by_index(i)?;
     let Some(path) = file.enclosed_name() else {
@@ -0,0 +1,254 @@
+use crate::cli::CliArgs;
+use crate::config::load_config;
+use crate::content_protection::is_content_protected;
+use crate::paths::{portable_root, profile_dir};
+use crate::policy::{current_policy, locked_error};
+use crate::profiles::{find_profile_id, open_profile_window};
+use crate::settings_service::modify_settings;
+use crate::windowing::{open_popup_window, show_main_window};
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use percent_encoding::percent_decode_str;
+use tauri::Manager;
+use tauri_plugin_deep_link::DeepLinkExt;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tauri_plugin_opener::OpenerExt;
+use url::Url;
+
+/// Registered for the app in `tauri.conf.json`; the installer writes it to the registry.
+pub(crate) const DEEP_LINK_SCHEME: &str = "refined-line";
+const LINE_HOSTS: [&str; 2] = ["line.me", "www.line.me"];
+const LINE_APP_LINK_PREFIX: &str = "/R/";
+
+/// What a link asks the app to do. Links reach the app as command-line arguments, from the
+/// first launch or, through the single-instance plugin, from a later one.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum DeepLink {
+  /// `refined-line://show`
+  Show,
+  /// `refined-line://protection/toggle`, `/on` or `/off`; `None` toggles. Turning it off asks first.
+  Protection(Option<bool>),
+  /// `refined-line://profile/<id or name>`
+  OpenProfile(String),
+  /// A `line.me/R/` link, as the argument or as `refined-line://open?url=...`.
+  Line(Url),
+}
+
+pub(crate) fn parse_deep_link(raw: &str) -> Option<DeepLink> {
+  let url = Url::parse(raw).ok()?;
+  if is_line_app_link(&url) {
+    return Some(DeepLink::Line(url));
+  }
+  if url.scheme() != DEEP_LINK_SCHEME {
+    return None;
+  }
+  let mut segments = url
+    .path_segments()
+    .into_iter()
+    .flatten()
+    .filter(|segment| !segment.is_empty());
+  match url.host_str()?.to_ascii_lowercase().as_str() {
+    "show" => Some(DeepLink::Show),
+    "protection" => match segments.next().unwrap_or("toggle") {
+      "toggle" => Some(DeepLink::Protection(None)),
+      "on" => Some(DeepLink::Protection(Some(true))),
+      "off" => Some(DeepLink::Protection(Some(false))),
+      _ => None,
+    },
+    "profile" => {
+      let profile = percent_decode_str(segments.next()?).decode_utf8().ok()?;
+      Some(DeepLink::OpenProfile(profile.into_owned()))
+    }
+    "open" => url
+      .query_pairs()
+      .find(|(key, _)| key == "url")
+      .and_then(|(_, target)| Url::parse(&target).ok())
+      .filter(is_line_app_link)
+      .map(DeepLink::Line),
+    _ => None,
+  }
+}
+
+/// `https://line.me/R/...`, the links LINE uses to add friends, share text and open chats.
+pub(crate) fn is_line_app_link(url: &Url) -> bool {
+  matches!(url.scheme(), "http" | "https")
+    && url
+      .host_str()
+      .is_some_and(|host| LINE_HOSTS.contains(&host))
+    && url.path().starts_with(LINE_APP_LINK_PREFIX)
+}
+
+/// Registers the scheme for the running exe when no installer did: dev builds and portable
+/// copies. An installed app keeps the installer's registration.
+pub(crate) fn register_deep_link_scheme(app: &tauri::AppHandle) {
+  if !cfg!(debug_assertions) && portable_root().is_none() {
+    return;
+  }
+  match app.deep_link().register_all() {
+    Ok(()) => info!("[deep-link] registered {DEEP_LINK_SCHEME}://"),
+    Err(error) => warn!("[deep-link] register failed: {error}"),
+  }
+}
+
+/// Runs every link among the arguments of a launch.
+pub(crate) fn handle_deep_link_args(app: &tauri::AppHandle, args: &CliArgs) {
+  for raw in &args.links {
+    let Some(link) = parse_deep_link(raw) else {
+      warn!("[deep-link] ignoring {raw}");
+      continue;
+    };
+    if let Err(error) = handle_deep_link(app, link) {
+      warn!("[deep-link] {raw} failed: {error:#}");
+    }
+  }
+}
+
+pub(crate) fn handle_deep_link(app: &tauri::AppHandle, link: DeepLink) -> Result<()> {
+  info!("[deep-link] {link:?}");
+  match link {
+    DeepLink::Show => show_main_window(app),
+    DeepLink::Protection(enabled) => {
+      let target = enabled.unwrap_or_else(|| !is_content_protected(app));
+      if target {
+        // Refused while policy locks `contentProtection`.
+        modify_settings(app, |settings| settings.content_protection = true)?;
+      } else if current_policy(app).is_locked("contentProtection") {
+        return Err(anyhow!(locked_error("contentProtection")));
+      } else if is_content_protected(app) {
+        confirm_protection_off(app);
+      }
+    }
+    DeepLink::OpenProfile(profile) => {
+      let id = find_profile_id(app, &profile)?;
+      open_profile_window(app, &id)?;
+    }
+    DeepLink::Line(url) => open_line_link(app, url)?,
+  }
+  Ok(())
+}
+
+// Any program can launch a link, so turning protection off needs the user's consent in the app.
+fn confirm_protection_off(app: &tauri::AppHandle) {
+  show_main_window(app);
+  let mut dialog = app
+    .dialog()
+    .message("リンクから「画面を保護」をオフにするよう求められました。オフにしますか？")
+    .title("画面を保護")
+    .kind(MessageDialogKind::Warning)
+    .buttons(MessageDialogButtons::OkCancelCustom(
+      "オフにする".into(),
+      "キャンセル".into(),
+    ));
+  if let Some(window) = app.get_webview_window("main") {
+    dialog = dialog.parent(&window);
+  }
+  let app = app.clone();
+  dialog.show(move |confirmed| {
+    if !confirmed {
+      info!("[deep-link] protection off declined");
+      return;
+    }
+    if let Err(error) = modify_settings(&app, |settings| settings.content_protection = false) {
+      warn!("[deep-link] protection off failed: {error:#}");
+    }
+  });
+}
+
+/// Opens an external link from a window of the main profile. With
+/// `deepLinks.interceptLineLinks`, `line.me/R/` links stay in the app; everything else goes to
+/// the default browser.
+pub(crate) fn open_external(app: &tauri::AppHandle, url: &Url) {
+  if is_line_app_link(url) && intercepts_line_links(app) {
+    debug!("[deep-link] intercepted {url}");
+    let app = app.clone();
+    let url = url.clone();
+    // Navigation callbacks run inside WebView2 events, where building a window can deadlock.
+    std::thread::spawn(move || {
+      if let Err(error) = open_line_link(&app, url) {
+        warn!("[deep-link] in-app open failed: {error:#}");
+      }
+    });
+    return;
+  }
+  let _ = app.opener().open_url(url.as_str(), None::<&str>);
+}
+
+fn intercepts_line_links(app: &tauri::AppHandle) -> bool {
+  load_config(app)
+    .map(|config| config.deep_links.intercept_line_links)
+    .unwrap_or(false)
+}
+
+// The LINE extension has no route for these links, so they open in a window of the main profile,
+// where line.me sees the same login. Links passed to the app always open here; the config only
+// decides about links clicked inside it.
+fn open_line_link(app: &tauri::AppHandle, url: Url) -> Result<()> {
+  show_main_window(app);
+  open_popup_window(app, profile_dir(app)?, url, |builder| builder)?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn line(raw: &str) -> DeepLink {
+    DeepLink::Line(Url::parse(raw).unwrap())
+  }
+
+  #[test]
+  fn parses_app_actions() {
+    let cases = [
+      ("refined-line://show", DeepLink::Show),
+      ("refined-line://SHOW/", DeepLink::Show),
+      ("refined-line://protection", DeepLink::Protection(None)),
+      (
+        "refined-line://protection/on",
+        DeepLink::Protection(Some(true)),
+      ),
+      (
+        "refined-line://protection/off",
+        DeepLink::Protection(Some(false)),
+      ),
+      (
+        "refined-line://profile/work",
+        DeepLink::OpenProfile("work".to_string()),
+      ),
+      (
+        "refined-line://profile/%E4%BB%95%E4%BA%8B",
+        DeepLink::OpenProfile("仕事".to_string()),
+      ),
+    ];
+    for (raw, expected) in cases {
+      assert_eq!(parse_deep_link(raw), Some(expected), "{raw}");
+    }
+  }
+
+  #[test]
+  fn parses_line_links_directly_and_wrapped() {
+    assert_eq!(
+      parse_deep_link("https://line.me/R/ti/p/@example"),
+      Some(line("https://line.me/R/ti/p/@example"))
+    );
+    assert_eq!(
+      parse_deep_link("refined-line://open?url=https%3A%2F%2Fline.me%2FR%2Fmsg%2Ftext%2F%3Fhi"),
+      Some(line("https://line.me/R/msg/text/?hi"))
+    );
+  }
+
+  #[test]
+  fn rejects_unknown_links() {
+    for raw in [
+      "refined-line://unknown",
+      "refined-line://protection/maybe",
+      "refined-line://profile",
+      "refined-line://open?url=https%3A%2F%2Fexample.com%2F",
+      "https://line.me/en/",
+      "https://example.com/R/ti/p/@example",
+      "other://show",
+      "--minimized",
+    ] {
+      assert_eq!(parse_deep_link(raw), None, "{raw}");
+    }
+  }
+}
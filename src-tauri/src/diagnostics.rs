@@ -0,0 +1,88 @@
+use crate::paths::AppPaths;
+use anyhow::Result;
+use serde_json::json;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::PackageInfo;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const SUMMARY_ENTRY: &str = "diagnostics.json";
+const LOGS_PREFIX: &str = "logs/";
+// Logs are never rotated away, so only the newest files are worth attaching.
+const MAX_LOG_FILES: usize = 5;
+
+/// Writes what a bug report needs into a zip: versions, paths, the settings and config files and
+/// the newest logs. Runs without an `AppHandle`, before any window exists, and never includes
+/// profile data or cookies.
+pub(crate) fn write_diagnostics(paths: &AppPaths, package: &PackageInfo, out: &Path) -> Result<()> {
+  let file = fs::File::create(out)?;
+  let mut zip = ZipWriter::new(file);
+  let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+  let summary = json!({
+    "app": package.name,
+    "version": package.version.to_string(),
+    "os": std::env::consts::OS,
+    "arch": std::env::consts::ARCH,
+    "createdAt": SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_secs(),
+    "paths": paths.info(),
+  });
+  zip.start_file(SUMMARY_ENTRY, options)?;
+  zip.write_all(serde_json::to_string_pretty(&summary)?.as_bytes())?;
+
+  let files = [
+    ("settings.json", paths.settings_file()),
+    ("user-config.json", paths.user_config()),
+    ("profiles.json", paths.profiles_file()),
+    ("maintenance.json", paths.maintenance_queue()),
+    (
+      "session-stats.json",
+      paths.session_stats(paths.profile_id()),
+    ),
+  ];
+  for (name, path) in files {
+    if path.is_file() {
+      zip.start_file(name, options)?;
+      zip.write_all(&fs::read(&path)?)?;
+    }
+  }
+
+  for path in newest_logs(&paths.logs()) {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+      continue;
+    };
+    zip.start_file(format!("{LOGS_PREFIX}{name}"), options)?;
+    zip.write_all(&fs::read(&path)?)?;
+  }
+
+  zip.finish()?;
+  Ok(())
+}
+
+fn newest_logs(dir: &Path) -> Vec<PathBuf> {
+  let Ok(entries) = fs::read_dir(dir) else {
+    return Vec::new();
+  };
+  let mut logs: Vec<(SystemTime, PathBuf)> = entries
+    .filter_map(|entry| {
+      let entry = entry.ok()?;
+      let metadata = entry.metadata().ok()?;
+      if !metadata.is_file() {
+        return None;
+      }
+      Some((metadata.modified().unwrap_or(UNIX_EPOCH), entry.path()))
+    })
+    .collect();
+  logs.sort_by(|a, b| b.0.cmp(&a.0));
+  logs
+    .into_iter()
+    .take(MAX_LOG_FILES)
+    .map(|(_, path)| path)
+    .collect()
+}
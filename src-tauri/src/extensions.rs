@@ -1,11 +1,15 @@
-use crate::config::load_config;
+use crate::browser::BrowserBackend;
+use crate::cli::cli_args;
+use crate::config::{load_config, CookiePersistenceConfig, ManagedExtension, PatchRule};
+use crate::cookie_jar::CookieRecord;
 use crate::crx::{
-  build_update_url, check_update, download_crx, ensure_clean_dir, extract_zip, inject_manifest_key,
-  parse_crx3, UpdateCheck,
+  build_update_url, check_update, download_crx, ensure_clean_dir, extension_id_from_public_key,
+  extract_zip, inject_manifest_key, parse_crx3, UpdateCheck,
 };
-use crate::paths::app_data_root;
+use crate::paths::{extensions_root, previous_extensions_dir, user_extensions_dir};
 use anyhow::{anyhow, Result};
 use log::{debug, info, warn};
+use serde::Serialize;
 use serde_json::Value;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -13,91 +17,218 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use url::Url;
 
 #[cfg(target_os = "windows")]
-use crate::windowing::{attach_new_window_handler, attach_permission_handler};
+use crate::browser::WebView2Backend;
 #[cfg(target_os = "windows")]
-use std::sync::mpsc;
+use crate::cookie_timeline::record_cookie_timeline;
 #[cfg(target_os = "windows")]
-use tauri::webview::PlatformWebview;
+use crate::session_guard::observe_session;
 #[cfg(target_os = "windows")]
-use webview2_com::Microsoft::Web::WebView2::Win32::{
-  ICoreWebView2, ICoreWebView2BrowserExtension, ICoreWebView2Profile7, ICoreWebView2_13,
-  ICoreWebView2_2, COREWEBVIEW2_COOKIE_SAME_SITE_KIND,
-};
-#[cfg(target_os = "windows")]
-use webview2_com::{
-  take_pwstr, wait_with_pump, BrowserExtensionEnableCompletedHandler, GetCookiesCompletedHandler,
-  ProfileAddBrowserExtensionCompletedHandler,
-};
+use crate::windowing::{attach_new_window_handler, attach_permission_handler};
 #[cfg(target_os = "windows")]
-use windows::core::BOOL;
+use tauri::webview::PlatformWebview;
 #[cfg(target_os = "windows")]
-use windows::core::{Interface, HSTRING, PCWSTR, PWSTR};
+use tauri::Manager;
+
+pub(crate) struct PreparedExtension {
+  pub(crate) id: String,
+  pub(crate) dir: PathBuf,
+  pub(crate) entry_path: Option<String>,
+  pub(crate) primary: bool,
+}
 
 pub(crate) struct ExtensionSetup {
-  pub(crate) line_dir: PathBuf,
+  pub(crate) extensions: Vec<PreparedExtension>,
   pub(crate) user_dir: PathBuf,
   pub(crate) updated: bool,
   pub(crate) update_failed: bool,
 }
 
+struct ManagedUpdate {
+  updated: bool,
+  update_failed: bool,
+}
+
+/// How `prepare_extensions` may use the network, from `--no-update` and `--offline`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum UpdatePolicy {
+  Check,
+  /// Installed extensions are used as they are; missing ones are still downloaded.
+  SkipCheck,
+  /// Nothing is downloaded, so a missing extension fails.
+  Offline,
+}
+
+/// Installed and rollback versions of a managed extension, for the recovery screen.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExtensionVersions {
+  id: String,
+  current: Option<String>,
+  previous: Option<String>,
+  held: bool,
+}
+
 pub(crate) fn prepare_extensions(app: &tauri::AppHandle) -> Result<ExtensionSetup> {
   let config = load_config(app)?;
-  let app_data = app_data_root(app)?;
+  let extensions_root = extensions_root(app)?;
+  let previous_root = previous_extensions_dir(app)?;
+  let user_dir = user_extensions_dir(app)?;
 
-  let extensions_root = app_data.join("extensions");
-  let line_dir = extensions_root.join("line");
-  let user_dir = extensions_root.join("user");
+  let cli = cli_args();
+  let policy = if cli.offline {
+    UpdatePolicy::Offline
+  } else if cli.no_update {
+    UpdatePolicy::SkipCheck
+  } else {
+    UpdatePolicy::Check
+  };
 
   info!("[update] storage root={}", extensions_root.display());
   fs::create_dir_all(&user_dir)?;
 
-  let current_version = read_manifest_version(&line_dir);
+  let mut extensions = Vec::new();
+  let mut updated = false;
+  let mut update_failed = false;
+
+  for extension in &config.extensions {
+    let dir = extensions_root.join(&extension.id);
+    let previous = previous_root.join(&extension.id);
+    match prepare_managed_extension(extension, &dir, &previous, policy) {
+      Ok(result) => {
+        updated |= result.updated;
+        update_failed |= result.update_failed;
+        extensions.push(PreparedExtension {
+          id: extension.id.clone(),
+          dir,
+          entry_path: extension.entry_path.clone(),
+          primary: extension.primary,
+        });
+      }
+      Err(error) if extension.primary => return Err(error),
+      Err(error) => {
+        warn!("[update] {} skipped: {error:#}", extension.id);
+        update_failed = true;
+      }
+    }
+  }
+
+  Ok(ExtensionSetup {
+    extensions,
+    user_dir,
+    updated,
+    update_failed,
+  })
+}
+
+/// Extensions already on disk, without checking for updates. Used for windows opened after
+/// startup, when `prepare_extensions` has already run.
+pub(crate) fn installed_extensions(app: &tauri::AppHandle) -> Result<ExtensionSetup> {
+  let config = load_config(app)?;
+  let extensions_root = extensions_root(app)?;
+  let mut extensions = Vec::new();
+  for extension in &config.extensions {
+    let dir = extensions_root.join(&extension.id);
+    if !is_extension_dir(&dir) {
+      if extension.primary {
+        return Err(anyhow!("{} is not installed", extension.id));
+      }
+      continue;
+    }
+    extensions.push(PreparedExtension {
+      id: extension.id.clone(),
+      dir,
+      entry_path: extension.entry_path.clone(),
+      primary: extension.primary,
+    });
+  }
+  Ok(ExtensionSetup {
+    extensions,
+    user_dir: user_extensions_dir(app)?,
+    updated: false,
+    update_failed: false,
+  })
+}
+
+fn prepare_managed_extension(
+  extension: &ManagedExtension,
+  dir: &Path,
+  previous: &Path,
+  policy: UpdatePolicy,
+) -> Result<ManagedUpdate> {
+  let id = extension.id.as_str();
+  let current_version = read_manifest_version(dir);
   let update_url = build_update_url(
-    &config.update2_base_url,
-    &config.line_extension_id,
+    &extension.update2_base_url,
+    &extension.extension_id,
     current_version.as_deref(),
   );
-  let has_existing = is_extension_dir(&line_dir);
+  let has_existing = is_extension_dir(dir);
+
+  if has_existing && hold_marker(previous).is_file() {
+    info!(
+      "[update] {id} held at v{} after rollback",
+      current_version.as_deref().unwrap_or("?")
+    );
+    let _ = apply_patch_rules(dir, &extension.patches);
+    return Ok(ManagedUpdate {
+      updated: false,
+      update_failed: false,
+    });
+  }
+
+  if has_existing && policy != UpdatePolicy::Check {
+    info!(
+      "[update] {id} use local extension (v{}, update check skipped)",
+      current_version.as_deref().unwrap_or("?")
+    );
+    let _ = apply_patch_rules(dir, &extension.patches);
+    return Ok(ManagedUpdate {
+      updated: false,
+      update_failed: false,
+    });
+  }
+  if policy == UpdatePolicy::Offline {
+    return Err(anyhow!(
+      "{id} is not installed and --offline forbids downloading it"
+    ));
+  }
 
   let mut updated = false;
   let mut update_failed = false;
   let mut crx_bytes: Option<Vec<u8>> = None;
 
   if let Some(version) = current_version.as_deref() {
-    info!("[update] check v{} {}", version, update_url);
+    info!("[update] {id} check v{} {}", version, update_url);
     match check_update(&update_url) {
       Ok(UpdateCheck::NoUpdate) => {
         if has_existing {
-          info!("[update] use local extension (v{})", version);
-          let _ = disable_cache_clear(&line_dir);
-          let _ = disable_legacy_clear(&line_dir);
-          return Ok(ExtensionSetup {
-            line_dir,
-            user_dir,
+          info!("[update] {id} use local extension (v{})", version);
+          let _ = apply_patch_rules(dir, &extension.patches);
+          return Ok(ManagedUpdate {
             updated: false,
             update_failed: false,
           });
         }
       }
       Ok(UpdateCheck::UpdateAvailable(payload)) => {
-        info!("[update] update available");
+        info!("[update] {id} update available");
         updated = has_existing;
         crx_bytes = payload;
       }
       Err(error) => {
-        warn!("[update] check failed: {error:#}");
+        warn!("[update] {id} check failed: {error:#}");
       }
     }
   }
 
   if crx_bytes.is_none() {
-    info!("[update] download {}", update_url);
+    info!("[update] {id} download {}", update_url);
     match download_crx_with_retry(&update_url) {
       Ok(buffer) => {
         crx_bytes = Some(buffer);
       }
       Err(error) => {
-        warn!("[update] download failed: {error:#}");
+        warn!("[update] {id} download failed: {error:#}");
         update_failed = true;
       }
     }
@@ -105,111 +236,195 @@ pub(crate) fn prepare_extensions(app: &tauri::AppHandle) -> Result<ExtensionSetu
 
   if update_failed {
     if has_existing {
-      info!("[update] use local extension (update failed)");
-      let _ = disable_cache_clear(&line_dir);
-      let _ = disable_legacy_clear(&line_dir);
-      return Ok(ExtensionSetup {
-        line_dir,
-        user_dir,
+      info!("[update] {id} use local extension (update failed)");
+      let _ = apply_patch_rules(dir, &extension.patches);
+      return Ok(ManagedUpdate {
         updated: false,
         update_failed: true,
       });
     }
-    return Err(anyhow!("update download failed after retries"));
+    return Err(anyhow!("{id} update download failed after retries"));
   }
 
   let crx_bytes = crx_bytes.ok_or_else(|| anyhow!("crx bytes missing"))?;
   let parsed = parse_crx3(&crx_bytes)?;
-  ensure_clean_dir(&line_dir)?;
-  extract_zip(&parsed.zip_bytes, &line_dir)?;
-  inject_manifest_key(&line_dir, &parsed.public_key)?;
-  let _ = disable_cache_clear(&line_dir);
-  let _ = disable_legacy_clear(&line_dir);
-  if let Some(version) = read_manifest_version(&line_dir) {
-    info!("[update] installed extension v{} (network)", version);
+  if has_existing {
+    if let Err(error) = keep_previous_version(dir, previous) {
+      warn!("[update] {id} previous version not kept: {error:#}");
+    }
+  }
+  ensure_clean_dir(dir)?;
+  extract_zip(&parsed.zip_bytes, dir)?;
+  inject_manifest_key(dir, &parsed.public_key)?;
+  let _ = apply_patch_rules(dir, &extension.patches);
+  if let Some(version) = read_manifest_version(dir) {
+    info!("[update] {id} installed extension v{} (network)", version);
   } else {
-    info!("[update] installed extension (network)");
+    info!("[update] {id} installed extension (network)");
   }
 
-  Ok(ExtensionSetup {
-    line_dir,
-    user_dir,
+  Ok(ManagedUpdate {
     updated,
     update_failed: false,
   })
 }
 
-#[cfg(target_os = "windows")]
-fn add_browser_extension(
-  profile: &ICoreWebView2Profile7,
-  extension_dir: &Path,
-) -> Result<ICoreWebView2BrowserExtension> {
-  let path = extension_dir.canonicalize()?;
-  let path_hs = HSTRING::from(path.as_path());
-  let (tx, rx) = mpsc::channel();
-  let handler =
-    ProfileAddBrowserExtensionCompletedHandler::create(Box::new(move |result, extension| {
-      let _ = tx.send((result, extension));
-      Ok(())
-    }));
-
-  unsafe {
-    profile.AddBrowserExtension(&path_hs, &handler)?;
+/// Installs a local `.crx` from `--import-crx`: over the managed extension with the same
+/// extension id, keeping the replaced version for a rollback, or as a user extension named after
+/// the file. An update check may still replace an imported managed extension unless
+/// `--no-update` is set.
+pub(crate) fn import_crx(app: &tauri::AppHandle, path: &Path) -> Result<()> {
+  let bytes = fs::read(path).map_err(|error| anyhow!("read {} failed: {error}", path.display()))?;
+  let parsed = parse_crx3(&bytes)?;
+  let extension_id = extension_id_from_public_key(&parsed.public_key);
+  let config = load_config(app)?;
+  let managed = config
+    .extensions
+    .iter()
+    .find(|extension| extension.extension_id == extension_id);
+  let dir = match managed {
+    Some(extension) => {
+      let dir = extensions_root(app)?.join(&extension.id);
+      if is_extension_dir(&dir) {
+        let previous = previous_extensions_dir(app)?.join(&extension.id);
+        if let Err(error) = keep_previous_version(&dir, &previous) {
+          warn!(
+            "[update] {} previous version not kept: {error:#}",
+            extension.id
+          );
+        }
+      }
+      dir
+    }
+    None => {
+      let name = path
+        .file_stem()
+        .ok_or_else(|| anyhow!("{} has no file name", path.display()))?;
+      user_extensions_dir(app)?.join(name)
+    }
+  };
+  ensure_clean_dir(&dir)?;
+  extract_zip(&parsed.zip_bytes, &dir)?;
+  inject_manifest_key(&dir, &parsed.public_key)?;
+  if let Some(extension) = managed {
+    let _ = apply_patch_rules(&dir, &extension.patches);
   }
+  info!(
+    "[update] imported {} v{} into {}",
+    path.display(),
+    read_manifest_version(&dir).as_deref().unwrap_or("?"),
+    dir.display()
+  );
+  Ok(())
+}
 
-  let (result, extension) =
-    wait_with_pump(rx).map_err(|error| anyhow!("extension install callback error: {error:?}"))?;
-
-  if let Err(error) = result {
-    return Err(anyhow!("add extension failed: {error:?}"));
+/// Moves the installed version aside before an update replaces it.
+fn keep_previous_version(dir: &Path, previous: &Path) -> Result<()> {
+  if previous.exists() {
+    fs::remove_dir_all(previous)?;
+  }
+  if let Some(parent) = previous.parent() {
+    fs::create_dir_all(parent)?;
   }
+  fs::rename(dir, previous)?;
+  Ok(())
+}
 
-  extension.ok_or_else(|| anyhow!("add extension returned no extension"))
+/// Written by a rollback. While it exists the rolled back version is used without update checks;
+/// a reinstall removes it.
+fn hold_marker(previous: &Path) -> PathBuf {
+  let mut name = previous.file_name().unwrap_or_default().to_os_string();
+  name.push(".hold");
+  previous.with_file_name(name)
 }
 
-#[cfg(target_os = "windows")]
-fn browser_extension_id(extension: &ICoreWebView2BrowserExtension) -> Result<String> {
-  let mut id_ptr = PWSTR::null();
-  unsafe {
-    extension.Id(&mut id_ptr)?;
-  }
-  Ok(take_pwstr(id_ptr))
+pub(crate) fn managed_extension_versions(app: &tauri::AppHandle) -> Result<Vec<ExtensionVersions>> {
+  let config = load_config(app)?;
+  let extensions_root = extensions_root(app)?;
+  let previous_root = previous_extensions_dir(app)?;
+  Ok(
+    config
+      .extensions
+      .iter()
+      .map(|extension| {
+        let previous = previous_root.join(&extension.id);
+        ExtensionVersions {
+          id: extension.id.clone(),
+          current: read_manifest_version(&extensions_root.join(&extension.id)),
+          previous: read_manifest_version(&previous),
+          held: hold_marker(&previous).is_file(),
+        }
+      })
+      .collect(),
+  )
 }
 
-#[cfg(target_os = "windows")]
-fn ensure_extension_enabled(extension: &ICoreWebView2BrowserExtension) -> Result<()> {
-  let mut enabled = BOOL(0);
-  unsafe {
-    extension.IsEnabled(&mut enabled)?;
-  }
-  if enabled == true {
-    return Ok(());
+/// Removes the managed extensions so the next `prepare_extensions` downloads them again, and
+/// lifts any rollback hold. User extensions and previous versions are kept.
+pub(crate) fn reinstall_managed_extensions(app: &tauri::AppHandle) -> Result<()> {
+  let config = load_config(app)?;
+  let extensions_root = extensions_root(app)?;
+  let previous_root = previous_extensions_dir(app)?;
+  for extension in &config.extensions {
+    let dir = extensions_root.join(&extension.id);
+    if dir.exists() {
+      fs::remove_dir_all(&dir)?;
+    }
+    let hold = hold_marker(&previous_root.join(&extension.id));
+    if hold.exists() {
+      fs::remove_file(&hold)?;
+    }
+    info!("[update] {} removed for reinstall", extension.id);
   }
+  Ok(())
+}
 
-  let (tx, rx) = mpsc::channel();
-  let handler = BrowserExtensionEnableCompletedHandler::create(Box::new(move |result| {
-    let _ = tx.send(result);
-    Ok(())
-  }));
-
-  unsafe {
-    extension.Enable(true, &handler)?;
+/// Swaps every managed extension that has a previous version with it and holds it there.
+/// Returns how many were rolled back.
+pub(crate) fn rollback_managed_extensions(app: &tauri::AppHandle) -> Result<usize> {
+  let config = load_config(app)?;
+  let extensions_root = extensions_root(app)?;
+  let previous_root = previous_extensions_dir(app)?;
+  let mut rolled_back = 0usize;
+  for extension in &config.extensions {
+    let dir = extensions_root.join(&extension.id);
+    let previous = previous_root.join(&extension.id);
+    if !is_extension_dir(&previous) {
+      continue;
+    }
+    let from = read_manifest_version(&dir);
+    // The replaced version becomes the previous one, so a rollback can be undone the same way.
+    let swap = previous.with_file_name(format!("{}.swap", extension.id));
+    if swap.exists() {
+      fs::remove_dir_all(&swap)?;
+    }
+    if dir.exists() {
+      fs::rename(&dir, &swap)?;
+    }
+    fs::rename(&previous, &dir)?;
+    if swap.exists() {
+      fs::rename(&swap, &previous)?;
+    }
+    fs::write(hold_marker(&previous), from.as_deref().unwrap_or_default())?;
+    info!(
+      "[update] {} rolled back from v{} to v{}",
+      extension.id,
+      from.as_deref().unwrap_or("?"),
+      read_manifest_version(&dir).as_deref().unwrap_or("?")
+    );
+    rolled_back += 1;
   }
-
-  let result =
-    wait_with_pump(rx).map_err(|error| anyhow!("extension enable callback error: {error:?}"))?;
-  if let Err(error) = result {
-    return Err(anyhow!("enable extension failed: {error:?}"));
+  if rolled_back == 0 {
+    return Err(anyhow!("no previous version to roll back to"));
   }
-
-  Ok(())
+  Ok(rolled_back)
 }
 
 fn is_extension_dir(path: &Path) -> bool {
   path.join("manifest.json").is_file()
 }
 
-fn read_manifest_version(path: &Path) -> Option<String> {
+pub(crate) fn read_manifest_version(path: &Path) -> Option<String> {
   let manifest_path = path.join("manifest.json");
   let raw = fs::read_to_string(&manifest_path).ok()?;
   let value: Value = serde_json::from_str(&raw).ok()?;
@@ -219,37 +434,17 @@ fn read_manifest_version(path: &Path) -> Option<String> {
     .map(|v| v.to_string())
 }
 
-fn disable_cache_clear(line_dir: &Path) -> Result<()> {
-  let cache_path = line_dir.join("cache.js");
-  if !cache_path.is_file() {
-    return Ok(());
-  }
-  let raw = fs::read_to_string(&cache_path)?;
-  if raw.contains("caches.delete(CACHE_NAME)") {
-    let updated = raw.replace("caches.delete(CACHE_NAME)", "Promise.resolve()");
-    fs::write(&cache_path, updated)?;
-  }
-  Ok(())
-}
-
-fn disable_legacy_clear(line_dir: &Path) -> Result<()> {
-  let background_path = line_dir.join("background.js");
-  if !background_path.is_file() {
-    return Ok(());
-  }
-  let raw = fs::read_to_string(&background_path)?;
-  let mut updated = raw.clone();
-  let mut changed = false;
-  if raw.contains("chrome.storage.local.clear()") {
-    updated = updated.replace("chrome.storage.local.clear()", "Promise.resolve()");
-    changed = true;
-  }
-  if raw.contains("indexedDB.databases()") {
-    updated = updated.replace("indexedDB.databases()", "Promise.resolve([])");
-    changed = true;
-  }
-  if changed {
-    fs::write(&background_path, updated)?;
+fn apply_patch_rules(extension_dir: &Path, rules: &[PatchRule]) -> Result<()> {
+  for rule in rules {
+    let path = extension_dir.join(&rule.file);
+    if !path.is_file() {
+      continue;
+    }
+    let raw = fs::read_to_string(&path)?;
+    if raw.contains(rule.find.as_str()) {
+      let updated = raw.replace(rule.find.as_str(), rule.replace.as_str());
+      fs::write(&path, updated)?;
+    }
   }
   Ok(())
 }
@@ -292,65 +487,196 @@ fn collect_user_extension_dirs(user_dir: &Path) -> Result<Vec<PathBuf>> {
   Ok(dirs)
 }
 
+/// Installs the managed extensions in order, then the user extensions. Returns the URL of the
+/// primary extension's entry page.
+pub(crate) fn install_extensions<B: BrowserBackend>(
+  backend: &B,
+  extensions: &[PreparedExtension],
+  user_extensions: &[PathBuf],
+) -> Result<String> {
+  let mut entry: Option<(String, String)> = None;
+  for managed in extensions {
+    let extension_id = backend.add_extension(&managed.dir)?;
+    backend.enable_extension(&extension_id)?;
+    debug!("[open] installed {} as {}", managed.id, extension_id);
+    if managed.primary {
+      if let Some(entry_path) = managed.entry_path.clone() {
+        entry = Some((extension_id, entry_path));
+      }
+    }
+  }
+  let (primary_id, entry_path) = entry.ok_or_else(|| anyhow!("primary extension missing"))?;
+
+  for user_extension in user_extensions {
+    let extension_id = backend.add_extension(user_extension)?;
+    backend.enable_extension(&extension_id)?;
+  }
+
+  Ok(format!("chrome-extension://{primary_id}{entry_path}"))
+}
+
 #[cfg(target_os = "windows")]
 pub(crate) fn install_extensions_and_open(
   webview: PlatformWebview,
-  line_dir: PathBuf,
+  extensions: Vec<PreparedExtension>,
   user_dir: PathBuf,
-  entry_path: String,
+  cookie_domains: &[String],
 ) -> Result<()> {
-  let controller = webview.controller();
-  let core = unsafe { controller.CoreWebView2()? };
-  attach_new_window_handler(&webview)?;
-  attach_permission_handler(&webview)?;
+  let page_url = install_extensions_in_webview(&webview, extensions, user_dir)?;
+  navigate_to_extension(&webview, &page_url, cookie_domains)
+}
+
+/// Prepares the webview for the extensions and installs them. Returns the entry page URL.
+#[cfg(target_os = "windows")]
+pub(crate) fn install_extensions_in_webview(
+  webview: &PlatformWebview,
+  extensions: Vec<PreparedExtension>,
+  user_dir: PathBuf,
+) -> Result<String> {
+  attach_new_window_handler(webview)?;
+  attach_permission_handler(webview)?;
   unsafe {
-    let settings = core.Settings()?;
+    let settings = webview.controller().CoreWebView2()?.Settings()?;
     settings.SetIsScriptEnabled(true)?;
   }
-  let profile = unsafe {
-    core
-      .cast::<ICoreWebView2_13>()?
-      .Profile()?
-      .cast::<ICoreWebView2Profile7>()?
-  };
-
-  let line_extension = add_browser_extension(&profile, &line_dir)?;
-  ensure_extension_enabled(&line_extension)?;
-  let line_id = browser_extension_id(&line_extension)?;
-
-  for user_extension in collect_user_extension_dirs(&user_dir)? {
-    let extension = add_browser_extension(&profile, &user_extension)?;
-    ensure_extension_enabled(&extension)?;
-  }
+  let backend = WebView2Backend::new(webview)?;
+  install_extensions(
+    &backend,
+    &extensions,
+    &collect_user_extension_dirs(&user_dir)?,
+  )
+}
 
-  let page_url = format!("chrome-extension://{line_id}{entry_path}");
+#[cfg(target_os = "windows")]
+/// Opens the extension page and logs the cookies of `cookie_domains`, the configured
+/// `cookiePersistence.domains`, as they were before the page loads.
+pub(crate) fn navigate_to_extension(
+  webview: &PlatformWebview,
+  page_url: &str,
+  cookie_domains: &[String],
+) -> Result<()> {
+  let backend = WebView2Backend::new(webview)?;
   info!("[open] {}", page_url);
-  let target = HSTRING::from(page_url.as_str());
-  unsafe {
-    core.Navigate(&target)?;
-  }
-  if let Err(error) = log_cookies_snapshot(&webview, "initial") {
+  backend.navigate(page_url)?;
+  if let Err(error) = log_cookies_snapshot(&backend, "initial", cookie_domains) {
     warn!("[cookie] initial failed: {error:#}");
   }
-
   Ok(())
 }
 
+/// Persists and logs session cookies of the window `label` on the configured schedule, then
+/// periodically for as long as the window exists, so sessions refreshed later are kept too.
 #[cfg(target_os = "windows")]
-pub(crate) fn log_cookies_snapshot(webview: &PlatformWebview, tag: &str) -> Result<()> {
-  let controller = webview.controller();
-  let core = unsafe { controller.CoreWebView2()? };
-  log_all_cookies_summary(&core, tag)?;
-  log_cookies(&core, tag, "https://access.line.me")?;
-  log_cookies(&core, tag, "https://line.me")?;
-  log_cookies(&core, tag, "https://api.line.me")?;
-  Ok(())
+pub(crate) fn schedule_cookie_snapshots(app_handle: &tauri::AppHandle, label: &str) {
+  let config = load_config(app_handle)
+    .map(|config| config.cookie_persistence)
+    .unwrap_or_default();
+  let handle = app_handle.clone();
+  let label = label.to_string();
+  std::thread::spawn(move || {
+    let mut elapsed = 0u64;
+    for delay in config.schedule_seconds.iter().copied() {
+      std::thread::sleep(std::time::Duration::from_secs(
+        delay.saturating_sub(elapsed),
+      ));
+      elapsed = elapsed.max(delay);
+      if !persist_window_cookies(&handle, &label, &format!("after_{delay}s")) {
+        return;
+      }
+    }
+    if config.interval_minutes == 0 {
+      return;
+    }
+    let interval = std::time::Duration::from_secs(config.interval_minutes.saturating_mul(60));
+    loop {
+      std::thread::sleep(interval);
+      if !persist_window_cookies(&handle, &label, "periodic") {
+        return;
+      }
+    }
+  });
 }
 
+/// Persists cookies of the window `label` on the main thread. Returns false once the window is
+/// gone, which ends the schedule.
 #[cfg(target_os = "windows")]
-fn log_cookies(core: &ICoreWebView2, tag: &str, uri: &str) -> Result<()> {
-  let cookies = collect_cookies(core, Some(uri))?;
-  let session_count = cookies.iter().filter(|cookie| cookie.is_session).count();
+pub(crate) fn persist_window_cookies(
+  app_handle: &tauri::AppHandle,
+  label: &str,
+  tag: &str,
+) -> bool {
+  if app_handle.get_webview_window(label).is_none() {
+    return false;
+  }
+  let config = load_config(app_handle)
+    .map(|config| config.cookie_persistence)
+    .unwrap_or_default();
+  let handle = app_handle.clone();
+  let label = label.to_string();
+  let tag = tag.to_string();
+  let _ = app_handle.run_on_main_thread(move || {
+    let Some(window) = handle.get_webview_window(&label) else {
+      return;
+    };
+    let tag_for_webview = tag.clone();
+    let handle_for_webview = handle.clone();
+    let label_for_webview = label.clone();
+    if let Err(error) = window.with_webview(move |webview| {
+      let backend = match WebView2Backend::new(&webview) {
+        Ok(backend) => backend,
+        Err(error) => {
+          warn!("[cookie] {tag_for_webview} backend failed: {error:#}");
+          return;
+        }
+      };
+      if let Err(error) = persist_session_cookies_snapshot(&backend, &tag_for_webview, &config) {
+        warn!("[cookie] {tag_for_webview} persist failed: {error:#}");
+      }
+      if let Err(error) = log_cookies_snapshot(&backend, &tag_for_webview, &config.domains) {
+        warn!("[cookie] {tag_for_webview} failed: {error:#}");
+      }
+      match read_domain_cookie_records(&backend, &config.domains) {
+        // The timeline and the session guard read and write files; keep them off the UI thread.
+        Ok(records) => {
+          std::thread::spawn(move || {
+            if let Err(error) = record_cookie_timeline(
+              &handle_for_webview,
+              &label_for_webview,
+              &tag_for_webview,
+              &records,
+            ) {
+              warn!("[cookie] {tag_for_webview} timeline failed: {error:#}");
+            }
+            observe_session(&handle_for_webview, &label_for_webview, &records, &config);
+          });
+        }
+        Err(error) => warn!("[cookie] {tag_for_webview} domain cookies failed: {error:#}"),
+      }
+    }) {
+      warn!("[cookie] {tag} with_webview failed: {error:#}");
+    }
+  });
+  true
+}
+
+pub(crate) fn log_cookies_snapshot<B: BrowserBackend>(
+  backend: &B,
+  tag: &str,
+  domains: &[String],
+) -> Result<()> {
+  log_all_cookies_summary(backend, tag)?;
+  for domain in domains {
+    log_cookies(backend, tag, &format!("https://{domain}"))?;
+  }
+  Ok(())
+}
+
+fn log_cookies<B: BrowserBackend>(backend: &B, tag: &str, uri: &str) -> Result<()> {
+  let cookies = backend.list_cookies(Some(uri))?;
+  let session_count = cookies
+    .iter()
+    .filter(|cookie| cookie.expires.is_none())
+    .count();
   debug!(
     "[cookie] {tag} {uri} count={} session={}",
     cookies.len(),
@@ -359,229 +685,380 @@ fn log_cookies(core: &ICoreWebView2, tag: &str, uri: &str) -> Result<()> {
   Ok(())
 }
 
-#[cfg(target_os = "windows")]
-pub(crate) fn persist_session_cookies_snapshot(webview: &PlatformWebview, tag: &str) -> Result<()> {
-  let controller = webview.controller();
-  let core = unsafe { controller.CoreWebView2()? };
-  persist_all_session_cookies(&core, tag)?;
-  persist_session_cookies(&core, tag, "https://access.line.me")?;
-  persist_session_cookies(&core, tag, "https://line.me")?;
-  persist_session_cookies(&core, tag, "https://api.line.me")?;
+fn log_all_cookies_summary<B: BrowserBackend>(backend: &B, tag: &str) -> Result<()> {
+  let cookies = backend.list_cookies(None)?;
+  let session_count = cookies
+    .iter()
+    .filter(|cookie| cookie.expires.is_none())
+    .count();
+  debug!(
+    "[cookie] {tag} all count={} session={}",
+    cookies.len(),
+    session_count
+  );
   Ok(())
 }
 
-#[cfg(target_os = "windows")]
-fn persist_session_cookies(core: &ICoreWebView2, tag: &str, uri: &str) -> Result<()> {
-  let mut cookies = collect_cookies(core, Some(uri))?;
-  if cookies.is_empty() {
-    debug!("[cookie] {tag} {uri} persist skipped (count=0)");
-    return Ok(());
-  }
-
-  let session_total = cookies.iter().filter(|cookie| cookie.is_session).count();
-  if session_total == 0 {
-    debug!("[cookie] {tag} {uri} persist skipped (no session cookies)");
-    return Ok(());
-  }
-
+pub(crate) fn persist_session_cookies_snapshot<B: BrowserBackend>(
+  backend: &B,
+  tag: &str,
+  config: &CookiePersistenceConfig,
+) -> Result<()> {
   let now = SystemTime::now()
     .duration_since(UNIX_EPOCH)
     .unwrap_or_default()
     .as_secs() as f64;
-  let expires = now + (365.0 * 24.0 * 60.0 * 60.0);
+  let expires = now + (config.expiry_days as f64 * 24.0 * 60.0 * 60.0);
+  if config.all_domains {
+    persist_session_cookies(backend, tag, None, expires)?;
+  }
+  for domain in &config.domains {
+    persist_session_cookies(backend, tag, Some(&format!("https://{domain}")), expires)?;
+  }
+  Ok(())
+}
 
-  let webview = core.cast::<ICoreWebView2_2>()?;
-  let cookie_manager = unsafe { webview.CookieManager()? };
-  let mut updated = 0usize;
+/// Rewrites session cookies for `uri`, or for every host when `uri` is `None`, with `expires`.
+/// Returns how many were rewritten.
+fn persist_session_cookies<B: BrowserBackend>(
+  backend: &B,
+  tag: &str,
+  uri: Option<&str>,
+  expires: f64,
+) -> Result<usize> {
+  let scope = uri.unwrap_or("all");
+  let cookies = backend.list_cookies(uri)?;
+  if cookies.is_empty() {
+    debug!("[cookie] {tag} {scope} persist skipped (count=0)");
+    return Ok(0);
+  }
 
-  let host_fallback = Url::parse(uri)
-    .ok()
+  let session_total = cookies
+    .iter()
+    .filter(|cookie| cookie.expires.is_none())
+    .count();
+  if session_total == 0 {
+    debug!("[cookie] {tag} {scope} persist skipped (no session cookies)");
+    return Ok(0);
+  }
+
+  let host_fallback = uri
+    .and_then(|uri| Url::parse(uri).ok())
     .and_then(|url| url.host_str().map(|host| host.to_string()))
     .unwrap_or_default();
+  let mut updated = 0usize;
 
-  for cookie in cookies.iter_mut() {
-    if !cookie.is_session {
+  for cookie in cookies {
+    if cookie.expires.is_some() {
       continue;
     }
     let domain = if cookie.domain.is_empty() {
-      host_fallback.as_str()
+      host_fallback.clone()
     } else {
-      cookie.domain.as_str()
+      cookie.domain
     };
     if domain.is_empty() {
       continue;
     }
     let path = if cookie.path.is_empty() {
-      "/"
+      "/".to_string()
     } else {
-      cookie.path.as_str()
+      cookie.path
     };
-    let name_hs = HSTRING::from(cookie.name.as_str());
-    let value_hs = HSTRING::from(cookie.value.as_str());
-    let domain_hs = HSTRING::from(domain);
-    let path_hs = HSTRING::from(path);
-    let new_cookie =
-      unsafe { cookie_manager.CreateCookie(&name_hs, &value_hs, &domain_hs, &path_hs)? };
-    unsafe {
-      new_cookie.SetIsHttpOnly(cookie.is_http_only)?;
-      new_cookie.SetIsSecure(cookie.is_secure)?;
-      new_cookie.SetSameSite(cookie.same_site)?;
-      new_cookie.SetExpires(expires)?;
-      cookie_manager.AddOrUpdateCookie(&new_cookie)?;
-    }
+    backend.set_cookie(&CookieRecord {
+      domain,
+      path,
+      expires: Some(expires),
+      ..cookie
+    })?;
     updated += 1;
   }
 
-  debug!("[cookie] {tag} {uri} persisted {updated}/{session_total} session cookies");
-  Ok(())
+  debug!("[cookie] {tag} {scope} persisted {updated}/{session_total} session cookies");
+  Ok(updated)
 }
 
-#[cfg(target_os = "windows")]
-fn collect_cookies(core: &ICoreWebView2, uri: Option<&str>) -> Result<Vec<CookieInfo>> {
-  let webview = core.cast::<ICoreWebView2_2>()?;
-  let cookie_manager = unsafe { webview.CookieManager()? };
-  let (tx, rx) = mpsc::channel();
-  unsafe {
-    let handler = GetCookiesCompletedHandler::create(Box::new(move |result, cookies| {
-      result?;
-      let mut out: Vec<CookieInfo> = Vec::new();
-      if let Some(cookies) = cookies {
-        let mut count = 0;
-        cookies.Count(&mut count)?;
-        for idx in 0..count {
-          let cookie = cookies.GetValueAtIndex(idx)?;
-          let mut name_ptr = PWSTR::null();
-          cookie.Name(&mut name_ptr)?;
-          let name = take_pwstr(name_ptr);
-
-          let mut value_ptr = PWSTR::null();
-          cookie.Value(&mut value_ptr)?;
-          let value = take_pwstr(value_ptr);
-
-          let mut domain_ptr = PWSTR::null();
-          cookie.Domain(&mut domain_ptr)?;
-          let domain = take_pwstr(domain_ptr);
-
-          let mut path_ptr = PWSTR::null();
-          cookie.Path(&mut path_ptr)?;
-          let path = take_pwstr(path_ptr);
-
-          let mut is_session = BOOL(0);
-          cookie.IsSession(&mut is_session)?;
-
-          let mut is_http_only = BOOL(0);
-          cookie.IsHttpOnly(&mut is_http_only)?;
-
-          let mut is_secure = BOOL(0);
-          cookie.IsSecure(&mut is_secure)?;
-
-          let mut same_site = COREWEBVIEW2_COOKIE_SAME_SITE_KIND(0);
-          cookie.SameSite(&mut same_site)?;
-
-          out.push(CookieInfo {
-            name,
-            value,
-            domain,
-            path,
-            is_session: is_session.as_bool(),
-            is_http_only: is_http_only.as_bool(),
-            is_secure: is_secure.as_bool(),
-            same_site,
-          });
-        }
-      }
-      let _ = tx.send(out);
-      Ok(())
-    }));
-
-    match uri {
-      Some(uri) => {
-        let uri_hs = HSTRING::from(uri);
-        cookie_manager.GetCookies(&uri_hs, &handler)?;
-      }
-      None => {
-        cookie_manager.GetCookies(PCWSTR::null(), &handler)?;
+/// Reads every cookie of the profile for a cookie jar export.
+pub(crate) fn read_cookie_records<B: BrowserBackend>(backend: &B) -> Result<Vec<CookieRecord>> {
+  backend.list_cookies(None)
+}
+
+/// Cookies sent to any of `domains`, without the duplicates shared between parent and sub domains.
+pub(crate) fn read_domain_cookie_records<B: BrowserBackend>(
+  backend: &B,
+  domains: &[String],
+) -> Result<Vec<CookieRecord>> {
+  let mut records: Vec<CookieRecord> = Vec::new();
+  for domain in domains {
+    for cookie in backend.list_cookies(Some(&format!("https://{domain}")))? {
+      let known = records.iter().any(|record| {
+        record.name == cookie.name && record.domain == cookie.domain && record.path == cookie.path
+      });
+      if !known {
+        records.push(cookie);
       }
     }
   }
-  wait_with_pump(rx).map_err(Into::into)
-}
-
-#[cfg(target_os = "windows")]
-struct CookieInfo {
-  name: String,
-  value: String,
-  domain: String,
-  path: String,
-  is_session: bool,
-  is_http_only: bool,
-  is_secure: bool,
-  same_site: COREWEBVIEW2_COOKIE_SAME_SITE_KIND,
+  Ok(records)
 }
 
-#[cfg(target_os = "windows")]
-fn log_all_cookies_summary(core: &ICoreWebView2, tag: &str) -> Result<()> {
-  let cookies = collect_cookies(core, None)?;
-  let session_count = cookies.iter().filter(|cookie| cookie.is_session).count();
-  debug!(
-    "[cookie] {tag} all count={} session={}",
-    cookies.len(),
-    session_count
-  );
-  Ok(())
+/// Replays `records` and reloads the page so the extension picks the session up again.
+pub(crate) fn restore_cookie_records<B: BrowserBackend>(
+  backend: &B,
+  records: &[CookieRecord],
+) -> Result<usize> {
+  let replayed = replay_cookie_records(backend, records)?;
+  backend.reload()?;
+  Ok(replayed)
 }
 
-#[cfg(target_os = "windows")]
-fn persist_all_session_cookies(core: &ICoreWebView2, tag: &str) -> Result<()> {
-  let mut cookies = collect_cookies(core, None)?;
-  if cookies.is_empty() {
-    debug!("[cookie] {tag} all persist skipped (count=0)");
-    return Ok(());
-  }
-  let session_total = cookies.iter().filter(|cookie| cookie.is_session).count();
-  if session_total == 0 {
-    debug!("[cookie] {tag} all persist skipped (no session cookies)");
-    return Ok(());
-  }
-
+/// Writes imported cookies back into the profile. Cookies that already expired are skipped.
+pub(crate) fn replay_cookie_records<B: BrowserBackend>(
+  backend: &B,
+  records: &[CookieRecord],
+) -> Result<usize> {
   let now = SystemTime::now()
     .duration_since(UNIX_EPOCH)
     .unwrap_or_default()
-    .as_secs() as f64;
-  let expires = now + (365.0 * 24.0 * 60.0 * 60.0);
+    .as_secs_f64();
+  let mut replayed = 0usize;
+  for record in records {
+    if record.expires.is_some_and(|expires| expires <= now) || record.domain.is_empty() {
+      continue;
+    }
+    let mut cookie = record.clone();
+    if cookie.path.is_empty() {
+      cookie.path = "/".to_string();
+    }
+    backend.set_cookie(&cookie)?;
+    replayed += 1;
+  }
+  debug!("[cookie] replayed {replayed}/{} cookies", records.len());
+  Ok(replayed)
+}
 
-  let webview = core.cast::<ICoreWebView2_2>()?;
-  let cookie_manager = unsafe { webview.CookieManager()? };
-  let mut updated = 0usize;
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::browser::fake::{fake_extension_id, FakeBackend};
+  use crate::cookie_jar::CookieSameSite;
 
-  for cookie in cookies.iter_mut() {
-    if !cookie.is_session {
-      continue;
+  const EXPIRES: f64 = 2_000_000_000.0;
+
+  fn prepared(id: &str, primary: bool) -> PreparedExtension {
+    PreparedExtension {
+      id: id.to_string(),
+      dir: PathBuf::from(format!("/ext/{id}")),
+      entry_path: primary.then(|| "/index.html".to_string()),
+      primary,
     }
-    if cookie.domain.is_empty() {
-      continue;
+  }
+
+  fn cookie(name: &str, domain: &str, expires: Option<f64>) -> CookieRecord {
+    CookieRecord {
+      name: name.to_string(),
+      value: format!("{name}-value"),
+      domain: domain.to_string(),
+      path: "/".to_string(),
+      http_only: true,
+      secure: true,
+      same_site: CookieSameSite::Lax,
+      expires,
     }
-    let path = if cookie.path.is_empty() {
-      "/"
-    } else {
-      cookie.path.as_str()
+  }
+
+  fn find(backend: &FakeBackend, name: &str) -> CookieRecord {
+    backend
+      .cookies
+      .borrow()
+      .iter()
+      .find(|cookie| cookie.name == name)
+      .cloned()
+      .unwrap()
+  }
+
+  #[test]
+  fn installs_managed_then_user_extensions_without_navigating() {
+    let backend = FakeBackend::default();
+    let extensions = [prepared("helper", false), prepared("line", true)];
+    let user = [PathBuf::from("/user/tool")];
+
+    let url = install_extensions(&backend, &extensions, &user).unwrap();
+
+    assert_eq!(url, "chrome-extension://id-line/index.html");
+    assert_eq!(
+      backend.calls(),
+      [
+        "add:/ext/helper",
+        "enable:id-helper",
+        "add:/ext/line",
+        "enable:id-line",
+        "add:/user/tool",
+        "enable:id-tool",
+      ]
+    );
+    assert!(backend.is_enabled(&fake_extension_id(&user[0])));
+  }
+
+  #[test]
+  fn missing_primary_fails_before_user_extensions() {
+    let backend = FakeBackend::default();
+    let error = install_extensions(
+      &backend,
+      &[prepared("helper", false)],
+      &[PathBuf::from("/user/tool")],
+    )
+    .unwrap_err();
+
+    assert!(error.to_string().contains("primary"));
+    assert_eq!(backend.calls(), ["add:/ext/helper", "enable:id-helper"]);
+  }
+
+  #[test]
+  fn add_failure_stops_the_install() {
+    let backend = FakeBackend::default();
+    backend.fail_on("add:/ext/helper");
+
+    let extensions = [prepared("helper", false), prepared("line", true)];
+    assert!(install_extensions(&backend, &extensions, &[]).is_err());
+    assert_eq!(backend.calls(), ["add:/ext/helper"]);
+  }
+
+  #[test]
+  fn enable_failure_of_user_extension_fails_the_install() {
+    let backend = FakeBackend::default();
+    backend.fail_on("enable:id-tool");
+
+    let result = install_extensions(
+      &backend,
+      &[prepared("line", true)],
+      &[PathBuf::from("/user/tool")],
+    );
+
+    assert!(result.is_err());
+    assert_eq!(backend.calls().last().unwrap(), "enable:id-tool");
+  }
+
+  #[test]
+  fn persist_rewrites_only_session_cookies() {
+    let backend = FakeBackend::with_cookies(vec![
+      cookie("session", ".line.me", None),
+      cookie("persistent", ".line.me", Some(1_900_000_000.0)),
+    ]);
+
+    let updated = persist_session_cookies(&backend, "t", Some("https://line.me"), EXPIRES).unwrap();
+
+    assert_eq!(updated, 1);
+    assert_eq!(find(&backend, "session").expires, Some(EXPIRES));
+    assert_eq!(find(&backend, "persistent").expires, Some(1_900_000_000.0));
+    let rewritten = find(&backend, "session");
+    assert!(rewritten.http_only && rewritten.secure);
+    assert_eq!(rewritten.same_site, CookieSameSite::Lax);
+    assert_eq!(rewritten.value, "session-value");
+  }
+
+  #[test]
+  fn persist_defaults_path_and_skips_cookies_without_domain() {
+    let mut no_path = cookie("no-path", "line.me", None);
+    no_path.path.clear();
+    let backend = FakeBackend::with_cookies(vec![no_path, cookie("no-domain", "", None)]);
+
+    assert_eq!(
+      persist_session_cookies(&backend, "t", None, EXPIRES).unwrap(),
+      1
+    );
+    assert_eq!(backend.calls(), ["list:all", "set:no-path"]);
+    assert!(backend
+      .cookies
+      .borrow()
+      .iter()
+      .any(|cookie| cookie.name == "no-path" && cookie.path == "/" && cookie.expires.is_some()));
+  }
+
+  #[test]
+  fn persist_without_session_cookies_writes_nothing() {
+    let backend = FakeBackend::with_cookies(vec![cookie("kept", "line.me", Some(1.0))]);
+
+    assert_eq!(
+      persist_session_cookies(&backend, "t", None, EXPIRES).unwrap(),
+      0
+    );
+    assert!(!backend.calls().iter().any(|call| call.starts_with("set:")));
+  }
+
+  #[test]
+  fn persist_snapshot_covers_all_hosts_and_configured_domains() {
+    let backend = FakeBackend::with_cookies(vec![
+      cookie("line", ".line.me", None),
+      cookie("other", "example.com", None),
+    ]);
+    let config = CookiePersistenceConfig {
+      domains: vec!["line.me".to_string()],
+      all_domains: false,
+      ..CookiePersistenceConfig::default()
     };
-    let name_hs = HSTRING::from(cookie.name.as_str());
-    let value_hs = HSTRING::from(cookie.value.as_str());
-    let domain_hs = HSTRING::from(cookie.domain.as_str());
-    let path_hs = HSTRING::from(path);
-    let new_cookie =
-      unsafe { cookie_manager.CreateCookie(&name_hs, &value_hs, &domain_hs, &path_hs)? };
-    unsafe {
-      new_cookie.SetIsHttpOnly(cookie.is_http_only)?;
-      new_cookie.SetIsSecure(cookie.is_secure)?;
-      new_cookie.SetSameSite(cookie.same_site)?;
-      new_cookie.SetExpires(expires)?;
-      cookie_manager.AddOrUpdateCookie(&new_cookie)?;
-    }
-    updated += 1;
+
+    persist_session_cookies_snapshot(&backend, "t", &config).unwrap();
+
+    assert!(find(&backend, "line").expires.is_some());
+    assert!(find(&backend, "other").expires.is_none());
+
+    let config = CookiePersistenceConfig {
+      all_domains: true,
+      ..config
+    };
+    persist_session_cookies_snapshot(&backend, "t", &config).unwrap();
+    assert!(find(&backend, "other").expires.is_some());
   }
 
-  debug!("[cookie] {tag} all persisted {updated}/{session_total} session cookies");
-  Ok(())
+  #[test]
+  fn persist_failure_is_reported() {
+    let backend = FakeBackend::with_cookies(vec![cookie("session", "line.me", None)]);
+    backend.fail_on("set:session");
+
+    assert!(persist_session_cookies(&backend, "t", None, EXPIRES).is_err());
+  }
+
+  #[test]
+  fn domain_records_are_deduplicated_across_hosts() {
+    let backend = FakeBackend::with_cookies(vec![
+      cookie("shared", ".line.me", None),
+      cookie("access", "access.line.me", None),
+      cookie("other", "example.com", None),
+    ]);
+    let domains = ["access.line.me".to_string(), "line.me".to_string()];
+
+    let records = read_domain_cookie_records(&backend, &domains).unwrap();
+    let names: Vec<_> = records.iter().map(|record| record.name.as_str()).collect();
+
+    assert_eq!(names, ["shared", "access"]);
+  }
+
+  #[test]
+  fn replay_skips_expired_and_domainless_cookies() {
+    let backend = FakeBackend::default();
+    let mut no_path = cookie("no-path", "line.me", None);
+    no_path.path.clear();
+    let records = [
+      cookie("expired", "line.me", Some(1.0)),
+      cookie("no-domain", "", None),
+      cookie("valid", "line.me", Some(EXPIRES)),
+      no_path,
+    ];
+
+    let replayed = restore_cookie_records(&backend, &records).unwrap();
+
+    assert_eq!(replayed, 2);
+    assert_eq!(backend.calls(), ["set:valid", "set:no-path", "reload"]);
+    assert_eq!(find(&backend, "no-path").path, "/");
+  }
+
+  #[test]
+  fn restore_does_not_reload_after_a_failed_replay() {
+    let backend = FakeBackend::default();
+    backend.fail_on("set:");
+
+    assert!(restore_cookie_records(&backend, &[cookie("valid", "line.me", None)]).is_err());
+    assert!(!backend.calls().contains(&"reload".to_string()));
+  }
 }
@@ -2,6 +2,7 @@ const FONT_SCRIPT: &str = include_str!("../injections/font.js");
 const NO_SCROLLBAR_SCRIPT: &str = include_str!("../injections/no-scrollbar.js");
 const SIDEBAR_SCRIPT: &str = include_str!("../injections/sidebar.js");
 const NOTIFY_BADGE_SCRIPT: &str = include_str!("../injections/notify-badge.js");
+const SESSION_WATCH_SCRIPT: &str = include_str!("../injections/session-watch.js");
 const HOTKEYS_SCRIPT: &str = include_str!("../injections/hotkeys.js");
 const TITLEBAR_SCRIPT: &str = include_str!("../injections/titlebar.js");
 
@@ -12,6 +13,7 @@ pub(crate) fn inject_scripts<R: tauri::Runtime>(
   webview.eval(FONT_SCRIPT)?;
   webview.eval(NO_SCROLLBAR_SCRIPT)?;
   webview.eval(SIDEBAR_SCRIPT)?;
+  webview.eval(SESSION_WATCH_SCRIPT)?;
   Ok(())
 }
 
@@ -0,0 +1,110 @@
+use crate::cli::CliArgs;
+use crate::deep_link::handle_deep_link_args;
+use crate::paths::{default_root, AppPaths};
+use crate::profiles::{find_profile_id, open_profile_window};
+use crate::windowing::show_main_window;
+use clap::Parser;
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+
+/// Identifier the single-instance plugin locks on. Each data root runs its own instance, so a
+/// portable copy or a `--data-dir` launch is not swallowed by the installed app. The default
+/// root keeps the plain identifier, which older releases lock on as well.
+pub(crate) fn instance_identifier(
+  identifier: &str,
+  product_name: &str,
+  paths: &AppPaths,
+) -> String {
+  if default_root(product_name).is_ok_and(|root| root == paths.root()) {
+    return identifier.to_string();
+  }
+  // Windows paths are case-insensitive.
+  let root = paths.root().to_string_lossy().to_lowercase();
+  let digest = Sha256::digest(root.as_bytes());
+  let hash: String = digest[..8]
+    .iter()
+    .map(|byte| format!("{byte:02x}"))
+    .collect();
+  format!("{identifier}.{hash}")
+}
+
+/// Runs in the first instance when the single-instance plugin stopped a later launch. `args` are
+/// that launch's arguments, executable first. Deep links and `--profile` run here; flags that only
+/// take effect at startup are logged and ignored, and anything else brings the main window forward.
+pub(crate) fn handle_second_instance(app: &tauri::AppHandle, args: Vec<String>, cwd: String) {
+  info!(
+    "[instance] second launch args={:?} cwd={cwd}",
+    args.get(1..).unwrap_or_default()
+  );
+  let parsed = match CliArgs::try_parse_from(&args) {
+    Ok(parsed) => parsed,
+    Err(error) => {
+      warn!("[instance] unreadable arguments: {error}");
+      show_main_window(app);
+      return;
+    }
+  };
+  let ignored = startup_only_flags(&parsed);
+  if !ignored.is_empty() {
+    warn!("[instance] already running, ignoring {}", ignored.join(" "));
+  }
+  if let Some(profile) = &parsed.profile {
+    let opened = find_profile_id(app, profile).and_then(|id| open_profile_window(app, &id));
+    if let Err(error) = opened {
+      warn!("[instance] --profile {profile} failed: {error:#}");
+      show_main_window(app);
+    }
+  }
+  if !parsed.links.is_empty() {
+    handle_deep_link_args(app, &parsed);
+  } else if parsed.profile.is_none() {
+    show_main_window(app);
+  }
+}
+
+// Flags that decide paths, updates or what happens before LINE opens; a running instance is past
+// all of that.
+fn startup_only_flags(args: &CliArgs) -> Vec<&'static str> {
+  let flags = [
+    ("--minimized", args.minimized),
+    ("--log-level", args.log_level.is_some()),
+    ("--offline", args.offline),
+    ("--no-update", args.no_update),
+    ("--reset-profile", args.reset_profile),
+    ("--import-crx", args.import_crx.is_some()),
+    ("--data-dir", args.data_dir.is_some()),
+    ("--portable", args.portable.is_some()),
+    ("--set", !args.overrides.is_empty()),
+  ];
+  flags
+    .into_iter()
+    .filter(|(_, set)| *set)
+    .map(|(flag, _)| flag)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn lists_flags_a_running_instance_cannot_apply() {
+    let args = CliArgs::try_parse_from([
+      "refined-line",
+      "--minimized",
+      "--reset-profile",
+      "--import-crx",
+      "line.crx",
+      "--profile",
+      "work",
+      "refined-line://show",
+    ])
+    .unwrap();
+    assert_eq!(
+      startup_only_flags(&args),
+      ["--minimized", "--reset-profile", "--import-crx"]
+    );
+    let args = CliArgs::try_parse_from(["refined-line", "--profile", "work"]).unwrap();
+    assert!(startup_only_flags(&args).is_empty());
+  }
+}
@@ -1,56 +1,122 @@
 mod app_menu;
+mod atomic_write;
+mod browser;
+mod cache_cleanup;
+mod cli;
 mod commands;
 mod config;
 mod content_protection;
+mod cookie_jar;
+mod cookie_timeline;
 mod crx;
+mod deep_link;
+mod diagnostics;
 mod extensions;
 mod injections;
+mod instance;
 mod logger;
+mod maintenance;
 mod notify_badge;
 mod paths;
+mod policy;
+mod profile_backup;
+mod profiles;
+mod recovery;
+mod session_guard;
 mod settings;
+mod settings_archive;
+mod settings_service;
+mod settings_watcher;
+mod startup;
+mod storage_report;
 mod tray;
 mod updater;
 mod windowing;
 
+use anyhow::anyhow;
 use app_menu::{build_menu, handle_menu_event, menu_action};
+use cache_cleanup::clear_cache;
+use cli::{cli_args, parse_cli, run_without_window};
 use commands::{
-  confirm_reset_profile, get_is_dev, get_is_maximized, get_settings, reset_profile, update_settings,
+  confirm_reset_profile, get_effective_config, get_is_dev, get_is_maximized, get_settings,
+  reset_profile, update_settings,
 };
-use config::load_config;
+use config::{load_config, resolve_config, ProfileBackupConfig};
 use content_protection::{
   ensure_base_title, get_content_protection, is_content_protected, set_content_protected,
   set_content_protection, store_base_title, toggle_content_protection, WindowState,
 };
+use cookie_jar::{export_cookies, import_cookies};
+use cookie_timeline::{export_cookie_timeline, get_cookie_timeline, init_cookie_timeline_state};
+use deep_link::{handle_deep_link_args, register_deep_link_scheme};
 #[cfg(target_os = "windows")]
-use extensions::install_extensions_and_open;
-use extensions::{
-  log_cookies_snapshot, persist_session_cookies_snapshot, prepare_extensions, ExtensionSetup,
-};
+use extensions::persist_window_cookies;
 use injections::{inject_hotkeys, inject_scripts, inject_titlebar};
-use log::{debug, error, info, warn};
-use logger::{apply_log_level, build_plugin, resolve_log_level};
+use instance::{handle_second_instance, instance_identifier};
+use log::{info, warn};
+use logger::{apply_log_level, build_plugin};
+use maintenance::{
+  cancel_maintenance, enqueue_maintenance, init_maintenance_state, list_maintenance,
+  run_pending_maintenance, show_maintenance_failures, MaintenanceOp,
+};
 use notify_badge::{init_notify_badge_state, update_notification_badge};
-use paths::{profile_dir, profile_reset_marker};
-use settings::{load_settings, save_settings};
+use paths::{
+  get_app_paths, migrate_legacy_layout, open_data_folder, portable_root, profile_dir, AppPaths,
+};
+use policy::{current_policy, get_locked_settings, init_policy};
+use profile_backup::{
+  backup_profile, list_profile_backups, restore_profile_backup, run_scheduled_backup,
+  scheduled_backup_due,
+};
+use profiles::{
+  create_profile, delete_profile, list_profiles, main_window_title, open_profile, rename_profile,
+  switch_profile,
+};
+use recovery::{
+  get_recovery_info, init_recovery_state, reinstall_extensions, retry_startup, rollback_extensions,
+};
+use serde_json::{Map, Value};
+use session_guard::{get_session_stats, init_session_guard_state, report_session_route};
+use settings::{
+  ensure_settings_writable, load_settings_with_recovery, save_settings, AppSettings,
+  SettingsRecovery,
+};
+use settings_archive::{export_app_data, import_app_data};
+use settings_service::{current_settings, init_settings_service};
+use settings_watcher::start_settings_watcher;
+#[cfg(target_os = "windows")]
+use startup::start_main_extensions;
+use startup::{get_startup_report, init_startup_state, run_stage, skip_stages, StartupStage};
+use storage_report::{cleanup_storage, get_storage_report, spawn_storage_monitor};
 use tauri::webview::PageLoadEvent;
 #[cfg(target_os = "windows")]
 use tauri::webview::ScrollBarStyle;
-use tauri::{Manager, WebviewUrl, WebviewWindowBuilder};
+use tauri::{Manager, WebviewWindowBuilder};
 use tauri_plugin_autostart::ManagerExt;
 use tauri_plugin_dialog::DialogExt;
-use tauri_plugin_opener::OpenerExt;
 use tray::{init_tray_state, is_tray_enabled};
 use updater::spawn_update_check;
-#[cfg(target_os = "windows")]
-use windowing::{
-  attach_close_requested_handler, attach_new_window_handler, attach_permission_handler,
-};
-use windowing::{next_popup_label, should_open_external};
+use windowing::with_link_handlers;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+  let cli = parse_cli();
+  let mut context = tauri::generate_context!();
+  if let Some(code) = run_without_window(cli, context.package_info()) {
+    std::process::exit(code);
+  }
+  let app_paths =
+    AppPaths::resolve(&context.package_info().name).expect("error while resolving app data dir");
+  // The single-instance plugin locks on the identifier; webviews and data use explicit paths.
+  let identifier = instance_identifier(
+    &context.config().identifier,
+    &context.package_info().name,
+    &app_paths,
+  );
+  context.config_mut().identifier = identifier;
+  let log_dir = app_paths.logs();
   tauri::Builder::default()
+    .manage(app_paths)
     .on_page_load(|webview, payload| {
       if payload.event() != PageLoadEvent::Finished {
         return;
@@ -88,11 +154,42 @@ pub fn run() {
       set_content_protection,
       get_settings,
       update_settings,
+      get_effective_config,
+      get_locked_settings,
+      export_app_data,
+      import_app_data,
+      backup_profile,
+      list_profile_backups,
+      restore_profile_backup,
+      export_cookies,
+      import_cookies,
+      get_cookie_timeline,
+      export_cookie_timeline,
       get_is_dev,
       get_is_maximized,
       confirm_reset_profile,
       reset_profile,
+      clear_cache,
+      get_storage_report,
+      get_app_paths,
+      open_data_folder,
+      list_profiles,
+      create_profile,
+      rename_profile,
+      delete_profile,
+      switch_profile,
+      open_profile,
+      cleanup_storage,
+      list_maintenance,
+      cancel_maintenance,
       update_notification_badge,
+      report_session_route,
+      get_session_stats,
+      get_recovery_info,
+      retry_startup,
+      reinstall_extensions,
+      rollback_extensions,
+      get_startup_report,
       menu_action
     ])
     .on_window_event(|window, event| {
@@ -100,10 +197,15 @@ pub fn run() {
         if window.label() == "main" && is_tray_enabled(window.app_handle()) {
           api.prevent_close();
           let _ = window.hide();
+          #[cfg(target_os = "windows")]
+          persist_window_cookies(window.app_handle(), "main", "hide");
         }
       }
     })
-    .plugin(build_plugin())
+    // Must come first: a second launch exits here, before touching the profile or the tray.
+    .plugin(tauri_plugin_single_instance::init(handle_second_instance))
+    .plugin(tauri_plugin_deep_link::init())
+    .plugin(build_plugin(log_dir))
     .plugin(tauri_plugin_autostart::init(
       tauri_plugin_autostart::MacosLauncher::LaunchAgent,
       None,
@@ -113,284 +215,218 @@ pub fn run() {
     .plugin(tauri_plugin_updater::Builder::new().build())
     .setup(|app| {
       let app_handle = app.handle().clone();
-      let mut settings = load_settings(&app_handle).unwrap_or_default();
-      if let Ok(enabled) = app_handle.autolaunch().is_enabled() {
-        if settings.auto_start != enabled {
-          settings.auto_start = enabled;
-          let _ = save_settings(&app_handle, &settings);
-        }
+      init_startup_state(&app_handle);
+      if cli_args().data_dir.is_some() {
+        info!(
+          "[paths] data dir root={}",
+          app.state::<AppPaths>().root().display()
+        );
+      } else if let Some(root) = portable_root() {
+        info!("[paths] portable mode root={}", root.display());
+      }
+      if let Err(error) = migrate_legacy_layout(&app_handle, app.state::<AppPaths>().inner()) {
+        warn!("[paths] layout migration failed: {error:#}");
       }
-      app.manage(WindowState::new(settings.content_protection));
+      init_policy(&app_handle);
+      let (settings, overrides) = run_stage(&app_handle, StartupStage::Settings, || {
+        load_startup_settings(&app_handle)
+      })
+      .unwrap_or_else(|_| {
+        let mut settings = AppSettings::default();
+        current_policy(&app_handle).enforce(&mut settings);
+        (settings, Map::new())
+      });
+      init_settings_service(&app_handle, &settings, overrides);
+      let effective = current_settings(&app_handle);
+      app.manage(WindowState::new(effective.content_protection));
       init_notify_badge_state(&app_handle);
-      apply_log_level(resolve_log_level(&settings.log_level));
-      let config = load_config(&app_handle)?;
-      let menu_state = build_menu(&app_handle, &settings)?;
-      let profile_dir = profile_dir(&app_handle)?;
-      let reset_marker = profile_reset_marker(&app_handle)?;
-      if reset_marker.is_file() {
-        info!("[webview] reset profile requested");
-        if profile_dir.exists() {
-          match std::fs::remove_dir_all(&profile_dir) {
-            Ok(()) => {
-              info!("[webview] profile dir removed");
-              let _ = std::fs::remove_file(&reset_marker);
-            }
-            Err(error) => {
-              warn!("[webview] profile reset failed: {error:#}");
-            }
-          }
-        } else {
-          let _ = std::fs::remove_file(&reset_marker);
+      init_session_guard_state(&app_handle);
+      init_cookie_timeline_state(&app_handle);
+      init_recovery_state(&app_handle);
+      apply_log_level(effective.log_level);
+      let backup = run_stage(&app_handle, StartupStage::Maintenance, || {
+        run_startup_maintenance(&app_handle)
+      })
+      .unwrap_or(None);
+      match backup {
+        // Archiving the profile can take minutes. It runs before the webview locks the cookie
+        // files, but off the main thread, and the window is built once it is done.
+        Some(config) => {
+          std::thread::spawn(move || {
+            run_scheduled_backup(&app_handle, &config);
+            let handle = app_handle.clone();
+            let _ = app_handle.run_on_main_thread(move || open_main_window(&handle, &effective));
+          });
         }
+        None => open_main_window(&app_handle, &effective),
       }
-      std::fs::create_dir_all(&profile_dir)?;
-
-      let base_title = "refined-line";
-      let conf = app_handle
-        .config()
-        .app
-        .windows
-        .get(0)
-        .ok_or("window config not found")?;
-      let mut builder = WebviewWindowBuilder::from_config(&app_handle, conf)?
-        .data_directory(profile_dir.clone())
-        .browser_extensions_enabled(true)
-        .on_menu_event(|window, event| {
-          handle_menu_event(window.app_handle(), event);
-        })
-        .on_navigation({
-          let app_handle = app_handle.clone();
-          move |url| {
-            debug!("[open] on_navigation url={}", url);
-            if should_open_external(url) {
-              debug!("[open] on_navigation external url={}", url);
-              let _ = app_handle.opener().open_url(url.as_str(), None::<&str>);
-              return false;
-            }
-            true
-          }
-        })
-        .on_new_window({
-          let app_handle = app_handle.clone();
-          let profile_dir = profile_dir.clone();
-          move |url, features| {
-            debug!("[open] on_new_window url={} features={:?}", url, features);
-            if should_open_external(&url) {
-              debug!("[open] on_new_window external url={}", url);
-              let _ = app_handle.opener().open_url(url.as_str(), None::<&str>);
-              return tauri::webview::NewWindowResponse::Deny;
-            }
-
-            let label = next_popup_label();
-            let popup_label = label.clone();
-            let popup_base_title = url.as_str().to_string();
-            store_base_title(&app_handle, popup_label.as_str(), &popup_base_title);
-
-            let mut builder =
-              WebviewWindowBuilder::new(&app_handle, label, WebviewUrl::External(url.clone()))
-                .data_directory(profile_dir.clone())
-                .disable_drag_drop_handler()
-                .title(popup_base_title.as_str())
-                .decorations(false)
-                .browser_extensions_enabled(true)
-                .on_navigation({
-                  let app_handle = app_handle.clone();
-                  move |url| {
-                    debug!("[open] popup on_navigation url={}", url);
-                    if should_open_external(url) {
-                      debug!("[open] popup on_navigation external url={}", url);
-                      let _ = app_handle.opener().open_url(url.as_str(), None::<&str>);
-                      return false;
-                    }
-                    true
-                  }
-                });
-
-            #[cfg(target_os = "windows")]
-            {
-              builder = builder.scroll_bar_style(ScrollBarStyle::FluentOverlay);
-            }
-
-            if let Some(size) = features.size() {
-              builder = builder.inner_size(size.width, size.height);
-            }
+      Ok(())
+    })
+    .run(context)
+    .expect("error while running tauri application");
+}
 
-            #[cfg(windows)]
-            {
-              builder = builder.with_environment(features.opener().environment.clone());
-            }
+/// Loads the saved settings and the overrides that only shape this run. Returns
+/// `(saved, overrides)`.
+fn load_startup_settings(
+  app_handle: &tauri::AppHandle,
+) -> anyhow::Result<(AppSettings, Map<String, Value>)> {
+  let (mut settings, recovery) = load_settings_with_recovery(app_handle)?;
+  if let Some(recovery) = recovery {
+    show_settings_recovery_dialog(app_handle, &recovery);
+  }
+  let writable = match ensure_settings_writable(app_handle) {
+    Ok(()) => true,
+    Err(error) => {
+      warn!("[settings] read-only: {error:#}");
+      show_settings_read_only_dialog(app_handle);
+      false
+    }
+  };
+  if let Ok(enabled) = app_handle.autolaunch().is_enabled() {
+    if settings.auto_start != enabled {
+      settings.auto_start = enabled;
+      if writable {
+        if let Err(error) = save_settings(app_handle, &settings) {
+          warn!("[settings] autostart sync not saved: {error:#}");
+        }
+      }
+    }
+  }
+  // Env and CLI overrides only shape this run; the settings service keeps persisting what
+  // the user chose and applies the overrides on top.
+  let resolved = resolve_config(app_handle).and_then(|config| {
+    let mut saved = config.saved_settings()?;
+    saved.auto_start = settings.auto_start;
+    current_policy(app_handle).enforce(&mut saved);
+    Ok((saved, config.runtime_overrides()?))
+  });
+  match resolved {
+    Ok(resolved) => Ok(resolved),
+    Err(error) => {
+      warn!("[config] resolve failed: {error:#}");
+      Ok((settings, Map::new()))
+    }
+  }
+}
 
-            let window = match builder.build() {
-              Ok(window) => window,
-              Err(error) => {
-                error!("[new-window] failed: {error:#}");
-                return tauri::webview::NewWindowResponse::Deny;
-              }
-            };
+/// Builds the main window and everything that needs it, then starts opening LINE.
+fn open_main_window(app_handle: &tauri::AppHandle, effective: &AppSettings) {
+  let window = run_stage(app_handle, StartupStage::Window, || {
+    build_main_window(app_handle, effective)
+  })
+  .ok();
+  let _ = run_stage(app_handle, StartupStage::Tray, || {
+    init_tray_state(app_handle, effective.start_minimized)
+  });
+  if let Err(error) = start_settings_watcher(app_handle) {
+    warn!("[settings] watcher failed: {error:#}");
+  }
+  spawn_update_check(app_handle);
 
-            let protected = is_content_protected(&app_handle);
-            let window_for_tasks = window.clone();
-            let app_handle_for_tasks = app_handle.clone();
-            let popup_label_for_tasks = popup_label.clone();
-            let popup_title_for_tasks = popup_base_title.clone();
-            let _ = window.run_on_main_thread(move || {
-              set_content_protected(
-                &window_for_tasks,
-                &popup_label_for_tasks,
-                protected,
-                Some(popup_title_for_tasks.as_str()),
-              );
+  let Some(window) = window else {
+    skip_stages(app_handle, &StartupStage::EXTENSION, "Window failed");
+    show_window_failure_dialog(app_handle);
+    return;
+  };
+  if effective.start_minimized {
+    let _ = window.minimize();
+  }
 
-              #[cfg(target_os = "windows")]
-              if let Err(error) = window_for_tasks.with_webview({
-                let app_handle = app_handle_for_tasks.clone();
-                let popup_label = popup_label_for_tasks.clone();
-                move |webview| {
-                  if let Err(error) = attach_new_window_handler(&webview) {
-                    warn!("[new-window] handler failed: {error:#}");
-                  }
-                  if let Err(error) = attach_permission_handler(&webview) {
-                    warn!("[new-window] permission handler failed: {error:#}");
-                  }
-                  if let Err(error) = attach_close_requested_handler(
-                    app_handle.clone(),
-                    &webview,
-                    popup_label.clone(),
-                  ) {
-                    warn!("[new-window] close handler failed: {error:#}");
-                  }
-                }
-              }) {
-                error!("[new-window] with_webview failed: {error:#}");
-              }
-            });
+  #[cfg(target_os = "windows")]
+  start_main_extensions(app_handle);
 
-            tauri::webview::NewWindowResponse::Create { window }
-          }
-        });
+  register_deep_link_scheme(app_handle);
+  handle_deep_link_args(app_handle, cli_args());
+}
 
-      #[cfg(target_os = "windows")]
-      {
-        builder = builder.scroll_bar_style(ScrollBarStyle::FluentOverlay);
-      }
+/// Runs queued maintenance such as a profile reset, including one asked for by `--reset-profile`,
+/// then starts the storage monitor. Returns the backup config when a scheduled backup is due;
+/// neither looks at a profile that is about to be replaced.
+fn run_startup_maintenance(
+  app_handle: &tauri::AppHandle,
+) -> anyhow::Result<Option<ProfileBackupConfig>> {
+  if cli_args().reset_profile {
+    enqueue_maintenance(app_handle, MaintenanceOp::ResetProfile)?;
+  }
+  let maintenance = run_pending_maintenance(app_handle)?;
+  let profile_replaced = maintenance
+    .iter()
+    .any(|result| result.succeeded && result.op.rewrites_profile());
+  show_maintenance_failures(app_handle, &maintenance);
+  init_maintenance_state(app_handle, maintenance);
+  std::fs::create_dir_all(profile_dir(app_handle)?)?;
+  let config = load_config(app_handle)?;
+  spawn_storage_monitor(app_handle, &config.storage_monitor);
+  let due = !profile_replaced && scheduled_backup_due(app_handle, &config.profile_backup);
+  Ok(due.then_some(config.profile_backup))
+}
 
-      let _window = builder.build()?;
+fn build_main_window(
+  app_handle: &tauri::AppHandle,
+  effective: &AppSettings,
+) -> anyhow::Result<tauri::WebviewWindow> {
+  let menu_state = build_menu(app_handle, effective)?;
+  let profile_dir = profile_dir(app_handle)?;
+  let base_title = main_window_title(app_handle);
+  let conf = app_handle
+    .config()
+    .app
+    .windows
+    .first()
+    .ok_or_else(|| anyhow!("window config not found"))?;
+  let builder = WebviewWindowBuilder::from_config(app_handle, conf)?
+    .data_directory(profile_dir.clone())
+    .title(&base_title)
+    .browser_extensions_enabled(true)
+    .on_menu_event(|window, event| {
+      handle_menu_event(window.app_handle(), event);
+    });
+  let mut builder = with_link_handlers(builder, app_handle, profile_dir);
 
-      store_base_title(&app_handle, "main", base_title);
-      app.manage(menu_state);
-      if let Err(error) = init_tray_state(&app_handle, settings.start_minimized) {
-        warn!("[tray] failed: {error:#}");
-      }
-      spawn_update_check(&app_handle);
-      if settings.start_minimized {
-        let _ = _window.minimize();
-      }
+  #[cfg(target_os = "windows")]
+  {
+    builder = builder.scroll_bar_style(ScrollBarStyle::FluentOverlay);
+  }
 
-      let entry_path = config.line_entry_path.clone();
-      let app_handle_for_update = app_handle.clone();
-      #[cfg(target_os = "windows")]
-      std::thread::spawn(move || {
-        let ExtensionSetup {
-          line_dir,
-          user_dir,
-          updated,
-          update_failed,
-        } = match prepare_extensions(&app_handle_for_update) {
-          Ok(result) => result,
-          Err(error) => {
-            error!("[update] failed: {error:#}");
-            let app_handle = app_handle_for_update.clone();
-            let dialog_handle = app_handle.clone();
-            let _ = app_handle.run_on_main_thread(move || {
-              dialog_handle
-                .dialog()
-                .message("アップデートに失敗しました。")
-                .title("更新失敗")
-                .show(|_| {});
-            });
-            return;
-          }
-        };
+  let window = builder.build()?;
 
-        let app_handle_for_install = app_handle_for_update.clone();
-        let entry_path_for_install = entry_path.clone();
-        let handle_for_task = app_handle_for_install.clone();
-        let updated_for_dialog = updated;
-        let update_failed_for_dialog = update_failed;
-        let _ = app_handle_for_install.run_on_main_thread(move || {
-          let Some(window) = handle_for_task.get_webview_window("main") else {
-            warn!("[open] main window not found");
-            return;
-          };
-          let line_dir_for_install = line_dir.clone();
-          let user_dir_for_install = user_dir.clone();
-          let entry_path_for_install = entry_path_for_install.clone();
-          if let Err(error) = window.with_webview(move |webview| {
-            let result = install_extensions_and_open(
-              webview,
-              line_dir_for_install.clone(),
-              user_dir_for_install.clone(),
-              entry_path_for_install.clone(),
-            );
-            if let Err(error) = result {
-              error!("[open] failed: {error:#}");
-              panic!("failed to open LINE extension");
-            }
-          }) {
-            error!("[open] with_webview failed: {error:#}");
-          }
+  store_base_title(app_handle, "main", &base_title);
+  app_handle.manage(menu_state);
+  Ok(window)
+}
 
-          let handle_for_cookies = handle_for_task.clone();
-          std::thread::spawn(move || {
-            let schedule = [(10u64, "after_10s"), (30u64, "after_30s")];
-            for (delay, tag) in schedule {
-              std::thread::sleep(std::time::Duration::from_secs(delay));
-              let handle = handle_for_cookies.clone();
-              let tag = tag.to_string();
-              let handle_for_main = handle.clone();
-              let _ = handle.run_on_main_thread(move || {
-                if let Some(window) = handle_for_main.get_webview_window("main") {
-                  let tag_for_webview = tag.clone();
-                  if let Err(error) = window.with_webview(move |webview| {
-                    if let Err(error) = persist_session_cookies_snapshot(&webview, &tag_for_webview)
-                    {
-                      warn!("[cookie] {tag_for_webview} persist failed: {error:#}");
-                    }
-                    if let Err(error) = log_cookies_snapshot(&webview, &tag_for_webview) {
-                      warn!("[cookie] {tag_for_webview} failed: {error:#}");
-                    }
-                  }) {
-                    warn!("[cookie] {tag} with_webview failed: {error:#}");
-                  }
-                }
-              });
-            }
-          });
+/// Without the main window there is nothing to recover from, so the app quits once the user has
+/// seen why.
+fn show_window_failure_dialog(app_handle: &tauri::AppHandle) {
+  let app_handle_for_exit = app_handle.clone();
+  app_handle
+    .dialog()
+    .message("ウィンドウを作成できませんでした。詳しくはログを確認してください。")
+    .title("起動失敗")
+    .show(move |_| app_handle_for_exit.exit(1));
+}
 
-          if update_failed_for_dialog {
-            let app_handle = handle_for_task.clone();
-            app_handle
-              .dialog()
-              .message("アップデートに失敗しました。")
-              .title("更新失敗")
-              .show(|_| {});
-          } else if updated_for_dialog {
-            let app_handle = handle_for_task.clone();
-            app_handle
-              .dialog()
-              .message("拡張機能を更新しました。再起動しますか？")
-              .title("更新完了")
-              .show(move |confirmed| {
-                if confirmed {
-                  app_handle.restart();
-                }
-              });
-          }
-        });
-      });
+fn show_settings_read_only_dialog(app_handle: &tauri::AppHandle) {
+  app_handle
+    .dialog()
+    .message("設定ファイルは新しいバージョンのアプリで保存されています。アプリを更新するまで、設定の変更は保存されません。")
+    .title("設定")
+    .show(|_| {});
+}
 
-      Ok(())
-    })
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+fn show_settings_recovery_dialog(app_handle: &tauri::AppHandle, recovery: &SettingsRecovery) {
+  let summary = if recovery.restored_from_backup {
+    "設定ファイルが破損していたため、バックアップから復元しました。"
+  } else {
+    "設定ファイルが破損していたため、初期設定に戻しました。"
+  };
+  app_handle
+    .dialog()
+    .message(format!(
+      "{summary}\n破損したファイル: {}",
+      recovery.corrupt_path.display()
+    ))
+    .title("設定の復元")
+    .show(|_| {});
 }
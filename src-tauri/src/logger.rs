@@ -1,13 +1,13 @@
 use log::LevelFilter;
-use std::env;
+use std::path::PathBuf;
 use tauri_plugin_log::{RotationStrategy, Target, TargetKind, TimezoneStrategy};
 
-pub(crate) const DEFAULT_LOG_LEVEL: &str = "info";
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
 pub(crate) enum LogLevel {
   Error,
   Warn,
+  #[default]
   Info,
   Debug,
   Verbose,
@@ -46,20 +46,11 @@ impl LogLevel {
   }
 }
 
-pub(crate) fn resolve_log_level(settings_level: &str) -> LogLevel {
-  if let Ok(env_level) = env::var("REFINED_LINE_LOG") {
-    if let Some(parsed) = LogLevel::from_str(&env_level) {
-      return parsed;
-    }
-  }
-  LogLevel::from_str(settings_level).unwrap_or(LogLevel::Info)
-}
-
 pub(crate) fn apply_log_level(level: LogLevel) {
   log::set_max_level(level.to_level_filter());
 }
 
-pub(crate) fn build_plugin() -> tauri::plugin::TauriPlugin<tauri::Wry> {
+pub(crate) fn build_plugin(log_dir: PathBuf) -> tauri::plugin::TauriPlugin<tauri::Wry> {
   tauri_plugin_log::Builder::new()
     // Allow all records through the logger; runtime level is controlled via `log::set_max_level`.
     .level(LevelFilter::Trace)
@@ -67,6 +58,9 @@ pub(crate) fn build_plugin() -> tauri::plugin::TauriPlugin<tauri::Wry> {
     .rotation_strategy(RotationStrategy::KeepAll)
     .clear_targets()
     .target(Target::new(TargetKind::Stdout))
-    .target(Target::new(TargetKind::LogDir { file_name: None }))
+    .target(Target::new(TargetKind::Folder {
+      path: log_dir,
+      file_name: None,
+    }))
     .build()
 }
@@ -0,0 +1,394 @@
+use crate::atomic_write::write_atomic;
+use crate::cache_cleanup::{clear_cache_for_maintenance, CacheCategory};
+use crate::extensions::reinstall_managed_extensions;
+use crate::paths::{
+  maintenance_queue_path, profile_dir, profile_reset_marker, user_extensions_dir,
+};
+use crate::profile_backup::restore_profile;
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::Manager;
+use tauri_plugin_dialog::DialogExt;
+
+/// A failing operation is retried on this many startups before it is dropped and reported.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Work that needs the profile or extensions unlocked, so it runs in `setup` before the
+/// webview is built.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub(crate) enum MaintenanceOp {
+  ResetProfile,
+  RestoreProfileBackup { path: String },
+  ClearCache { categories: Vec<CacheCategory> },
+  ReinstallExtensions,
+  ClearUserExtensions,
+}
+
+impl MaintenanceOp {
+  /// Whether the profile on disk was replaced, which makes a fresh backup pointless.
+  pub(crate) fn rewrites_profile(&self) -> bool {
+    matches!(
+      self,
+      MaintenanceOp::ResetProfile | MaintenanceOp::RestoreProfileBackup { .. }
+    )
+  }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MaintenanceTask {
+  id: u64,
+  op: MaintenanceOp,
+  requested_at: u64,
+  #[serde(default)]
+  attempts: u32,
+  #[serde(default)]
+  last_error: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct MaintenanceQueue {
+  #[serde(default)]
+  tasks: Vec<MaintenanceTask>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MaintenanceResult {
+  id: u64,
+  pub(crate) op: MaintenanceOp,
+  pub(crate) succeeded: bool,
+  error: Option<String>,
+  /// Bytes freed by cleanup operations.
+  reclaimed_bytes: Option<u64>,
+  /// Set when the task failed and will not be retried.
+  dropped: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MaintenanceStatus {
+  pending: Vec<MaintenanceTask>,
+  last_run: Vec<MaintenanceResult>,
+}
+
+#[derive(Default)]
+pub(crate) struct MaintenanceState {
+  last_run: Mutex<Vec<MaintenanceResult>>,
+}
+
+/// Adds `op` to the persisted queue. An identical pending op is reused instead of duplicated.
+pub(crate) fn enqueue_maintenance(app: &tauri::AppHandle, op: MaintenanceOp) -> Result<u64> {
+  let path = maintenance_queue_path(app)?;
+  let mut queue = read_queue(&path)?;
+  let (id, added) = queue.push(op.clone(), unix_now());
+  if added {
+    info!("[maintenance] queued #{id} {op:?}");
+    write_queue(&path, &queue)?;
+  }
+  Ok(id)
+}
+
+impl MaintenanceQueue {
+  /// Returns the id of the task for `op` and whether it was added.
+  fn push(&mut self, op: MaintenanceOp, now: u64) -> (u64, bool) {
+    if let Some(task) = self.tasks.iter().find(|task| task.op == op) {
+      return (task.id, false);
+    }
+    let id = self
+      .tasks
+      .iter()
+      .map(|task| task.id)
+      .max()
+      .unwrap_or(0)
+      .max(now)
+      + 1;
+    self.tasks.push(MaintenanceTask {
+      id,
+      op,
+      requested_at: now,
+      attempts: 0,
+      last_error: None,
+    });
+    (id, true)
+  }
+
+  fn remove(&mut self, id: u64) -> bool {
+    let before = self.tasks.len();
+    self.tasks.retain(|task| task.id != id);
+    self.tasks.len() != before
+  }
+}
+
+/// Runs every queued operation in order. Successful ones leave the queue; failed ones stay for
+/// the next startup until they run out of attempts.
+pub(crate) fn run_pending_maintenance(app: &tauri::AppHandle) -> Result<Vec<MaintenanceResult>> {
+  migrate_legacy_markers(app);
+  let path = maintenance_queue_path(app)?;
+  let queue = read_queue(&path)?;
+  let mut remaining = Vec::new();
+  let mut results = Vec::new();
+  for mut task in queue.tasks {
+    task.attempts += 1;
+    info!(
+      "[maintenance] run #{} {:?} attempt={}",
+      task.id, task.op, task.attempts
+    );
+    let outcome = run_operation(app, &task.op).map_err(|error| format!("{error:#}"));
+    let (result, retry) = settle_task(task, outcome);
+    match &result.error {
+      None => info!(
+        "[maintenance] #{} done reclaimed={:?}",
+        result.id, result.reclaimed_bytes
+      ),
+      Some(error) => warn!(
+        "[maintenance] #{} failed dropped={}: {error}",
+        result.id, result.dropped
+      ),
+    }
+    results.push(result);
+    remaining.extend(retry);
+  }
+  if !results.is_empty() {
+    write_queue(&path, &MaintenanceQueue { tasks: remaining })?;
+  }
+  Ok(results)
+}
+
+/// Turns the outcome of one attempt into its result, plus the task to keep when it failed and
+/// still has attempts left.
+fn settle_task(
+  mut task: MaintenanceTask,
+  outcome: Result<Option<u64>, String>,
+) -> (MaintenanceResult, Option<MaintenanceTask>) {
+  match outcome {
+    Ok(reclaimed_bytes) => (
+      MaintenanceResult {
+        id: task.id,
+        op: task.op,
+        succeeded: true,
+        error: None,
+        reclaimed_bytes,
+        dropped: false,
+      },
+      None,
+    ),
+    Err(error) => {
+      let dropped = task.attempts >= MAX_ATTEMPTS;
+      let result = MaintenanceResult {
+        id: task.id,
+        op: task.op.clone(),
+        succeeded: false,
+        error: Some(error.clone()),
+        reclaimed_bytes: None,
+        dropped,
+      };
+      task.last_error = Some(error);
+      (result, (!dropped).then_some(task))
+    }
+  }
+}
+
+pub(crate) fn init_maintenance_state(app: &tauri::AppHandle, results: Vec<MaintenanceResult>) {
+  app.manage(MaintenanceState {
+    last_run: Mutex::new(results),
+  });
+}
+
+pub(crate) fn show_maintenance_failures(app: &tauri::AppHandle, results: &[MaintenanceResult]) {
+  let failures: Vec<String> = results
+    .iter()
+    .filter(|result| !result.succeeded)
+    .map(|result| {
+      let retry = if result.dropped {
+        "中止"
+      } else {
+        "次回起動時に再試行"
+      };
+      format!(
+        "・{}（{retry}）: {}",
+        op_label(&result.op),
+        result.error.as_deref().unwrap_or_default()
+      )
+    })
+    .collect();
+  if failures.is_empty() {
+    return;
+  }
+  app
+    .dialog()
+    .message(format!(
+      "起動時のメンテナンス処理に失敗しました。\n{}",
+      failures.join("\n")
+    ))
+    .title("メンテナンス")
+    .show(|_| {});
+}
+
+fn run_operation(app: &tauri::AppHandle, op: &MaintenanceOp) -> Result<Option<u64>> {
+  match op {
+    MaintenanceOp::ResetProfile => remove_dir_if_exists(&profile_dir(app)?).map(|()| None),
+    MaintenanceOp::RestoreProfileBackup { path } => {
+      restore_profile(app, Path::new(path)).map(|()| None)
+    }
+    MaintenanceOp::ClearCache { categories } => {
+      clear_cache_for_maintenance(app, categories).map(Some)
+    }
+    // Also lifts rollback holds, so the fresh download keeps receiving updates.
+    MaintenanceOp::ReinstallExtensions => reinstall_managed_extensions(app).map(|()| None),
+    MaintenanceOp::ClearUserExtensions => {
+      remove_dir_if_exists(&user_extensions_dir(app)?).map(|()| None)
+    }
+  }
+}
+
+fn remove_dir_if_exists(path: &Path) -> Result<()> {
+  match fs::remove_dir_all(path) {
+    Ok(()) => {
+      info!("[maintenance] removed {}", path.display());
+      Ok(())
+    }
+    Err(error) if error.kind() == ErrorKind::NotFound => Ok(()),
+    Err(error) => Err(anyhow!("remove {} failed: {error}", path.display())),
+  }
+}
+
+// Versions before the queue wrote a flag file for profile resets.
+fn migrate_legacy_markers(app: &tauri::AppHandle) {
+  let Ok(marker) = profile_reset_marker(app) else {
+    return;
+  };
+  if !marker.is_file() {
+    return;
+  }
+  match enqueue_maintenance(app, MaintenanceOp::ResetProfile) {
+    Ok(_) => {
+      let _ = fs::remove_file(&marker);
+    }
+    Err(error) => warn!("[maintenance] legacy reset marker not migrated: {error:#}"),
+  }
+}
+
+fn read_queue(path: &Path) -> Result<MaintenanceQueue> {
+  match fs::read_to_string(path) {
+    Ok(raw) => Ok(serde_json::from_str(&raw)?),
+    Err(error) if error.kind() == ErrorKind::NotFound => Ok(MaintenanceQueue::default()),
+    Err(error) => Err(error.into()),
+  }
+}
+
+fn write_queue(path: &Path, queue: &MaintenanceQueue) -> Result<()> {
+  write_atomic(path, &serde_json::to_vec_pretty(queue)?)
+}
+
+fn op_label(op: &MaintenanceOp) -> &'static str {
+  match op {
+    MaintenanceOp::ResetProfile => "プロファイルデータのリセット",
+    MaintenanceOp::RestoreProfileBackup { .. } => "プロファイルの復元",
+    MaintenanceOp::ClearCache { .. } => "キャッシュの削除",
+    MaintenanceOp::ReinstallExtensions => "拡張機能の再インストール",
+    MaintenanceOp::ClearUserExtensions => "ユーザー拡張機能の削除",
+  }
+}
+
+fn unix_now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_secs()
+}
+
+#[tauri::command]
+pub(crate) fn list_maintenance(app_handle: tauri::AppHandle) -> Result<MaintenanceStatus, String> {
+  let path = maintenance_queue_path(&app_handle).map_err(|error| error.to_string())?;
+  let pending = read_queue(&path).map_err(|error| error.to_string())?.tasks;
+  let last_run = app_handle
+    .try_state::<MaintenanceState>()
+    .and_then(|state| state.last_run.lock().ok().map(|results| results.clone()))
+    .unwrap_or_default();
+  Ok(MaintenanceStatus { pending, last_run })
+}
+
+#[tauri::command]
+pub(crate) fn cancel_maintenance(app_handle: tauri::AppHandle, id: u64) -> Result<bool, String> {
+  let path = maintenance_queue_path(&app_handle).map_err(|error| error.to_string())?;
+  let mut queue = read_queue(&path).map_err(|error| error.to_string())?;
+  if !queue.remove(id) {
+    return Ok(false);
+  }
+  write_queue(&path, &queue).map_err(|error| error.to_string())?;
+  info!("[maintenance] cancelled #{id}");
+  Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn task(op: MaintenanceOp, attempts: u32) -> MaintenanceTask {
+    MaintenanceTask {
+      id: 7,
+      op,
+      requested_at: 0,
+      attempts,
+      last_error: None,
+    }
+  }
+
+  #[test]
+  fn identical_ops_share_one_task() {
+    let mut queue = MaintenanceQueue::default();
+    let (reset, added) = queue.push(MaintenanceOp::ResetProfile, 100);
+    assert!(added);
+    assert_eq!(reset, 101);
+    // Ids stay unique even when the clock has not moved.
+    let (reinstall, _) = queue.push(MaintenanceOp::ReinstallExtensions, 100);
+    assert_eq!(reinstall, 102);
+    assert_eq!(queue.push(MaintenanceOp::ResetProfile, 200), (reset, false));
+    assert_eq!(queue.tasks.len(), 2);
+  }
+
+  #[test]
+  fn failures_are_retried_until_max_attempts() {
+    let (result, retry) = settle_task(
+      task(MaintenanceOp::ResetProfile, 1),
+      Err("locked".to_string()),
+    );
+    assert!(!result.succeeded && !result.dropped);
+    let retry = retry.expect("retried on the next startup");
+    assert_eq!(retry.last_error.as_deref(), Some("locked"));
+
+    let (result, retry) = settle_task(
+      task(MaintenanceOp::ResetProfile, MAX_ATTEMPTS),
+      Err("locked".to_string()),
+    );
+    assert!(result.dropped);
+    assert!(retry.is_none());
+  }
+
+  #[test]
+  fn success_leaves_the_queue() {
+    let (result, retry) = settle_task(task(MaintenanceOp::ClearUserExtensions, 2), Ok(Some(42)));
+    assert!(result.succeeded && !result.dropped);
+    assert_eq!(result.reclaimed_bytes, Some(42));
+    assert!(retry.is_none());
+  }
+
+  #[test]
+  fn cancel_removes_only_the_given_task() {
+    let mut queue = MaintenanceQueue::default();
+    let (reset, _) = queue.push(MaintenanceOp::ResetProfile, 100);
+    let (reinstall, _) = queue.push(MaintenanceOp::ReinstallExtensions, 100);
+    assert!(queue.remove(reset));
+    assert!(!queue.remove(reset));
+    assert_eq!(queue.tasks.len(), 1);
+    assert_eq!(queue.tasks[0].id, reinstall);
+  }
+}
@@ -1,5 +1,6 @@
 mod assets;
 
+use crate::profiles::is_profile_window;
 use anyhow::Result;
 use assets::{badge_png_bytes, parse_badge_token, BadgeToken, BADGE_SIZES};
 use std::collections::HashMap;
@@ -14,7 +15,8 @@ use windows::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SM_CXSMICON};
 #[derive(Default)]
 pub(crate) struct NotifyBadgeState {
   cache: Mutex<HashMap<(BadgeToken, u32), Image<'static>>>,
-  last_applied: Mutex<Option<(Option<BadgeToken>, u32)>>,
+  /// Keyed by window label; every profile window carries its own badge.
+  last_applied: Mutex<HashMap<String, (Option<BadgeToken>, u32)>>,
 }
 
 pub(crate) fn init_notify_badge_state(app_handle: &tauri::AppHandle) {
@@ -24,16 +26,25 @@ pub(crate) fn init_notify_badge_state(app_handle: &tauri::AppHandle) {
 #[tauri::command]
 pub(crate) fn update_notification_badge(
   app_handle: tauri::AppHandle,
+  window: tauri::WebviewWindow,
   text: Option<String>,
 ) -> Result<(), String> {
+  // Popups report through their own webview; their badge belongs to the main window.
+  let label = if is_profile_window(window.label()) {
+    window.label()
+  } else {
+    "main"
+  };
+
   #[cfg(target_os = "windows")]
   {
-    apply_notification_badge(&app_handle, text.as_deref()).map_err(|error| error.to_string())
+    apply_notification_badge(&app_handle, label, text.as_deref()).map_err(|error| error.to_string())
   }
 
   #[cfg(not(target_os = "windows"))]
   {
     let _ = app_handle;
+    let _ = label;
     let _ = text;
     Ok(())
   }
@@ -42,9 +53,10 @@ pub(crate) fn update_notification_badge(
 #[cfg(target_os = "windows")]
 fn apply_notification_badge<R: Runtime>(
   app_handle: &AppHandle<R>,
+  label: &str,
   text: Option<&str>,
 ) -> Result<()> {
-  let Some(window) = app_handle.get_webview_window("main") else {
+  let Some(window) = app_handle.get_webview_window(label) else {
     return Ok(());
   };
 
@@ -58,10 +70,10 @@ fn apply_notification_badge<R: Runtime>(
       .last_applied
       .lock()
       .map_err(|_| anyhow::anyhow!("notify badge state lock failed"))?;
-    if last_applied.as_ref() == Some(&next) {
+    if last_applied.get(label) == Some(&next) {
       return Ok(());
     }
-    *last_applied = Some(next);
+    last_applied.insert(label.to_string(), next);
   }
 
   if let Some(token) = token {
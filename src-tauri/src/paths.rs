@@ -1,15 +1,366 @@
+use crate::cli::cli_args;
+use crate::profiles::{active_profile_id, DEFAULT_PROFILE_ID};
 use anyhow::{anyhow, Result};
-use std::path::PathBuf;
-pub(crate) fn app_data_root(app: &tauri::AppHandle) -> Result<PathBuf> {
-  let app_name = app.package_info().name.clone();
-  let base = dirs::data_dir().ok_or_else(|| anyhow!("app data dir error"))?;
-  Ok(base.join(app_name))
+use log::{info, warn};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::Manager;
+use tauri_plugin_opener::OpenerExt;
+
+const PORTABLE_MARKER: &str = "portable.flag";
+const PORTABLE_ENV: &str = "REFINED_LINE_PORTABLE";
+const PORTABLE_DATA_DIR: &str = "data";
+
+// Bumped when the layout under the root changes; recorded in `LAYOUT_MARKER` after migrating.
+const LAYOUT_VERSION: &str = "2";
+const LAYOUT_MARKER: &str = ".layout";
+
+static PORTABLE_ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// Root of every writable path in portable mode, decided once per process. The log plugin is
+/// built before any `AppHandle` exists, so this must not depend on one.
+pub(crate) fn portable_root() -> Option<&'static Path> {
+  PORTABLE_ROOT.get_or_init(detect_portable_root).as_deref()
+}
+
+// `--portable[=<dir>]`, then `REFINED_LINE_PORTABLE`, then a marker file next to the exe whose
+// optional content names the directory. Relative directories resolve against the exe dir.
+fn detect_portable_root() -> Option<PathBuf> {
+  let exe_dir = std::env::current_exe()
+    .ok()
+    .and_then(|exe| exe.parent().map(Path::to_path_buf))?;
+  let requested = cli_args()
+    .portable
+    .clone()
+    .or_else(|| {
+      std::env::var(PORTABLE_ENV)
+        .ok()
+        .filter(|value| !matches!(value.trim(), "" | "0" | "false"))
+        .map(|value| match value.trim() {
+          "1" | "true" => String::new(),
+          dir => dir.to_string(),
+        })
+    })
+    .or_else(|| {
+      std::fs::read_to_string(exe_dir.join(PORTABLE_MARKER))
+        .ok()
+        .map(|raw| raw.trim().to_string())
+    })?;
+  let root = if requested.is_empty() {
+    exe_dir.join(PORTABLE_DATA_DIR)
+  } else {
+    exe_dir.join(requested)
+  };
+  Some(root)
+}
+
+/// Every location the app writes to. All of them live under one root: `--data-dir`, the portable
+/// root or `<data_dir>/<productName>`. Only the machine config dir, which the app never writes,
+/// lives elsewhere.
+#[derive(Clone, Debug)]
+pub(crate) struct AppPaths {
+  root: PathBuf,
+  /// Profile this process runs as; see `profiles::active_profile_id`.
+  profile_id: String,
+}
+
+#[derive(Clone, Copy, Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum DataFolder {
+  Root,
+  Profile,
+  Extensions,
+  Logs,
+  Backups,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AppPathsInfo {
+  root: PathBuf,
+  portable: bool,
+  profile: PathBuf,
+  profile_id: String,
+  extensions: PathBuf,
+  settings: PathBuf,
+  logs: PathBuf,
+  backups: PathBuf,
+}
+
+/// Where an installed app keeps its data when neither `--data-dir` nor portable mode moves it.
+pub(crate) fn default_root(product_name: &str) -> Result<PathBuf> {
+  Ok(
+    dirs::data_dir()
+      .ok_or_else(|| anyhow!("app data dir error"))?
+      .join(product_name),
+  )
+}
+
+impl AppPaths {
+  /// Resolved before the app is built, because the log plugin needs the log dir up front.
+  pub(crate) fn resolve(product_name: &str) -> Result<Self> {
+    let root = match (&cli_args().data_dir, portable_root()) {
+      (Some(dir), _) => std::path::absolute(dir)?,
+      (None, Some(root)) => root.to_path_buf(),
+      (None, None) => default_root(product_name)?,
+    };
+    let profile_id = active_profile_id(&root);
+    Ok(Self { root, profile_id })
+  }
+
+  pub(crate) fn profile_id(&self) -> &str {
+    &self.profile_id
+  }
+
+  pub(crate) fn profiles_file(&self) -> PathBuf {
+    self.root.join("profiles.json")
+  }
+
+  /// Per-profile data of named profiles lives in `profiles/<id>`; the default profile keeps
+  /// using the root so installs from before profiles existed stay logged in.
+  pub(crate) fn profile_base(&self, profile_id: &str) -> PathBuf {
+    if profile_id == DEFAULT_PROFILE_ID {
+      self.root.clone()
+    } else {
+      self.root.join("profiles").join(profile_id)
+    }
+  }
+
+  pub(crate) fn profile_data(&self, profile_id: &str) -> PathBuf {
+    self.profile_base(profile_id).join("webview2-profile")
+  }
+
+  pub(crate) fn root(&self) -> &Path {
+    &self.root
+  }
+
+  pub(crate) fn profile(&self) -> PathBuf {
+    self.profile_data(&self.profile_id)
+  }
+
+  pub(crate) fn extensions(&self) -> PathBuf {
+    self.root.join("extensions")
+  }
+
+  pub(crate) fn user_extensions(&self) -> PathBuf {
+    self.extensions().join("user")
+  }
+
+  /// Managed extension versions replaced by the last update, kept for a rollback. Outside of
+  /// `extensions` so a reinstall keeps them.
+  pub(crate) fn previous_extensions(&self) -> PathBuf {
+    self.root.join("extensions-previous")
+  }
+
+  /// Settings of the default profile, which named profiles start from.
+  pub(crate) fn shared_settings_file(&self) -> PathBuf {
+    self.root.join("settings.json")
+  }
+
+  /// The settings file this process saves to. A named profile keeps its own on top of the
+  /// shared one, so toggles made in it do not change other profiles.
+  pub(crate) fn settings_file(&self) -> PathBuf {
+    self.profile_base(&self.profile_id).join("settings.json")
+  }
+
+  pub(crate) fn user_config(&self) -> PathBuf {
+    self.root.join("config.json")
+  }
+
+  pub(crate) fn logs(&self) -> PathBuf {
+    self.root.join("logs")
+  }
+
+  pub(crate) fn profile_backups(&self) -> PathBuf {
+    self.profile_base(&self.profile_id).join("profile-backups")
+  }
+
+  pub(crate) fn maintenance_queue(&self) -> PathBuf {
+    self.profile_base(&self.profile_id).join("maintenance.json")
+  }
+
+  /// Login-state counters of `profile_id`; see `session_guard`.
+  pub(crate) fn session_stats(&self, profile_id: &str) -> PathBuf {
+    self.profile_base(profile_id).join("session-stats.json")
+  }
+
+  /// Last cookie set of the LINE hosts seen while logged in, sealed for the current user.
+  pub(crate) fn session_snapshot(&self, profile_id: &str) -> PathBuf {
+    self.profile_base(profile_id).join("session-snapshot.bin")
+  }
+
+  /// Cookie metadata history of `profile_id` for diagnosing session loss.
+  pub(crate) fn cookie_timeline(&self, profile_id: &str) -> PathBuf {
+    self.profile_base(profile_id).join("cookie-timeline.json")
+  }
+
+  /// Legacy reset flag; only read to migrate it into the maintenance queue.
+  pub(crate) fn reset_marker(&self) -> PathBuf {
+    self.root.join("reset-profile.flag")
+  }
+
+  fn folder(&self, folder: DataFolder) -> PathBuf {
+    match folder {
+      DataFolder::Root => self.root.clone(),
+      DataFolder::Profile => self.profile(),
+      DataFolder::Extensions => self.extensions(),
+      DataFolder::Logs => self.logs(),
+      DataFolder::Backups => self.profile_backups(),
+    }
+  }
+
+  pub(crate) fn info(&self) -> AppPathsInfo {
+    AppPathsInfo {
+      root: self.root.clone(),
+      portable: portable_root().is_some(),
+      profile: self.profile(),
+      profile_id: self.profile_id.clone(),
+      extensions: self.extensions(),
+      settings: self.settings_file(),
+      logs: self.logs(),
+      backups: self.profile_backups(),
+    }
+  }
+}
+
+pub(crate) fn app_paths(app: &tauri::AppHandle) -> Result<AppPaths> {
+  if let Some(paths) = app.try_state::<AppPaths>() {
+    return Ok(paths.inner().clone());
+  }
+  AppPaths::resolve(&app.package_info().name)
 }
 
 pub(crate) fn profile_dir(app: &tauri::AppHandle) -> Result<PathBuf> {
-  Ok(app_data_root(app)?.join("webview2-profile"))
+  Ok(app_paths(app)?.profile())
+}
+
+pub(crate) fn extensions_root(app: &tauri::AppHandle) -> Result<PathBuf> {
+  Ok(app_paths(app)?.extensions())
+}
+
+pub(crate) fn user_extensions_dir(app: &tauri::AppHandle) -> Result<PathBuf> {
+  Ok(app_paths(app)?.user_extensions())
+}
+
+pub(crate) fn previous_extensions_dir(app: &tauri::AppHandle) -> Result<PathBuf> {
+  Ok(app_paths(app)?.previous_extensions())
 }
 
 pub(crate) fn profile_reset_marker(app: &tauri::AppHandle) -> Result<PathBuf> {
-  Ok(app_data_root(app)?.join("reset-profile.flag"))
+  Ok(app_paths(app)?.reset_marker())
+}
+
+pub(crate) fn maintenance_queue_path(app: &tauri::AppHandle) -> Result<PathBuf> {
+  Ok(app_paths(app)?.maintenance_queue())
+}
+
+pub(crate) fn profile_backups_dir(app: &tauri::AppHandle) -> Result<PathBuf> {
+  Ok(app_paths(app)?.profile_backups())
+}
+
+pub(crate) fn user_config_path(app: &tauri::AppHandle) -> Result<PathBuf> {
+  Ok(app_paths(app)?.user_config())
+}
+
+pub(crate) fn settings_file_path(app: &tauri::AppHandle) -> Result<PathBuf> {
+  Ok(app_paths(app)?.settings_file())
+}
+
+pub(crate) fn log_dir(app: &tauri::AppHandle) -> Result<PathBuf> {
+  Ok(app_paths(app)?.logs())
+}
+
+/// Machine-wide location that administrators manage; the app only ever reads from it.
+pub(crate) fn machine_config_dir(app: &tauri::AppHandle) -> Result<PathBuf> {
+  let app_name = app.package_info().name.clone();
+  #[cfg(target_os = "windows")]
+  let base = std::env::var_os("ProgramData")
+    .map(PathBuf::from)
+    .ok_or_else(|| anyhow!("ProgramData not set"))?;
+  #[cfg(not(target_os = "windows"))]
+  let base = PathBuf::from("/etc");
+  Ok(base.join(app_name))
+}
+
+/// Moves data written by older versions into the unified layout. Settings used to live in the
+/// identifier-based `app_data_dir()` and logs in `app_log_dir()`; the profile and extensions were
+/// already under the root and stay where they are, so the LINE login survives.
+pub(crate) fn migrate_legacy_layout(app: &tauri::AppHandle, paths: &AppPaths) -> Result<()> {
+  let marker = paths.root().join(LAYOUT_MARKER);
+  if fs::read_to_string(&marker).is_ok_and(|version| version.trim() == LAYOUT_VERSION) {
+    return Ok(());
+  }
+  fs::create_dir_all(paths.root())?;
+  if portable_root().is_none() {
+    if let Ok(legacy_dir) = app.path().app_data_dir() {
+      if legacy_dir != paths.root() {
+        for name in ["settings.json", "settings.json.bak"] {
+          move_if_absent(&legacy_dir.join(name), &paths.root().join(name))?;
+        }
+        remove_dir_if_empty(&legacy_dir);
+      }
+    }
+    if let Ok(legacy_logs) = app.path().app_log_dir() {
+      if legacy_logs != paths.logs() && legacy_logs.is_dir() {
+        fs::create_dir_all(paths.logs())?;
+        for entry in fs::read_dir(&legacy_logs)? {
+          let path = entry?.path();
+          if let Some(name) = path.file_name() {
+            move_if_absent(&path, &paths.logs().join(name))?;
+          }
+        }
+        remove_dir_if_empty(&legacy_logs);
+      }
+    }
+  }
+  fs::write(&marker, LAYOUT_VERSION)?;
+  info!(
+    "[paths] layout v{LAYOUT_VERSION} at {}",
+    paths.root().display()
+  );
+  Ok(())
+}
+
+fn move_if_absent(from: &Path, to: &Path) -> Result<()> {
+  if !from.is_file() || to.exists() {
+    return Ok(());
+  }
+  // Rename fails across volumes; copying keeps the migration working there too.
+  if fs::rename(from, to).is_err() {
+    fs::copy(from, to)?;
+    let _ = fs::remove_file(from);
+  }
+  info!("[paths] migrated {} -> {}", from.display(), to.display());
+  Ok(())
+}
+
+fn remove_dir_if_empty(dir: &Path) {
+  if let Err(error) = fs::remove_dir(dir) {
+    if dir.exists() {
+      warn!("[paths] legacy dir kept {}: {error}", dir.display());
+    }
+  }
+}
+
+#[tauri::command]
+pub(crate) fn get_app_paths(app_handle: tauri::AppHandle) -> Result<AppPathsInfo, String> {
+  app_paths(&app_handle)
+    .map(|paths| paths.info())
+    .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+pub(crate) fn open_data_folder(
+  app_handle: tauri::AppHandle,
+  folder: Option<DataFolder>,
+) -> Result<(), String> {
+  let paths = app_paths(&app_handle).map_err(|error| error.to_string())?;
+  let dir = paths.folder(folder.unwrap_or(DataFolder::Root));
+  fs::create_dir_all(&dir).map_err(|error| error.to_string())?;
+  app_handle
+    .opener()
+    .open_path(dir.display().to_string(), None::<&str>)
+    .map_err(|error| error.to_string())
 }
@@ -0,0 +1,181 @@
+use crate::logger::LogLevel;
+use crate::paths::machine_config_dir;
+use crate::settings::AppSettings;
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use tauri::Manager;
+use tauri_plugin_autostart::ManagerExt;
+
+pub(crate) const MANAGED_HINT: &str = "組織によって管理されています";
+
+/// Mandatory values from the read-only `policy.json` next to the machine-wide config.
+/// Every field that is set locks the matching setting.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Policy {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) content_protection: Option<bool>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) auto_start: Option<bool>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) start_minimized: Option<bool>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) log_level: Option<LogLevel>,
+  /// Pins the update source of every managed extension.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) update2_base_url: Option<String>,
+}
+
+impl Policy {
+  pub(crate) fn is_empty(&self) -> bool {
+    self.locked_keys().is_empty() && self.update2_base_url.is_none()
+  }
+
+  /// camelCase names of the settings this policy locks.
+  pub(crate) fn locked_keys(&self) -> Vec<&'static str> {
+    let mut keys = Vec::new();
+    if self.content_protection.is_some() {
+      keys.push("contentProtection");
+    }
+    if self.auto_start.is_some() {
+      keys.push("autoStart");
+    }
+    if self.start_minimized.is_some() {
+      keys.push("startMinimized");
+    }
+    if self.log_level.is_some() {
+      keys.push("logLevel");
+    }
+    keys
+  }
+
+  pub(crate) fn is_locked(&self, key: &str) -> bool {
+    self.locked_keys().contains(&key)
+  }
+
+  pub(crate) fn enforce(&self, settings: &mut AppSettings) {
+    if let Some(value) = self.content_protection {
+      settings.content_protection = value;
+    }
+    if let Some(value) = self.auto_start {
+      settings.auto_start = value;
+    }
+    if let Some(value) = self.start_minimized {
+      settings.start_minimized = value;
+    }
+    if let Some(value) = self.log_level {
+      settings.log_level = value;
+    }
+  }
+
+  /// Returns the first locked key whose requested value differs from the mandated one.
+  pub(crate) fn violation(&self, requested: &AppSettings) -> Option<&'static str> {
+    let mut enforced = requested.clone();
+    self.enforce(&mut enforced);
+    if enforced.content_protection != requested.content_protection {
+      return Some("contentProtection");
+    }
+    if enforced.auto_start != requested.auto_start {
+      return Some("autoStart");
+    }
+    if enforced.start_minimized != requested.start_minimized {
+      return Some("startMinimized");
+    }
+    if enforced.log_level != requested.log_level {
+      return Some("logLevel");
+    }
+    None
+  }
+
+  pub(crate) fn to_values(&self) -> Result<Map<String, Value>> {
+    match serde_json::to_value(self)? {
+      Value::Object(values) => Ok(values),
+      _ => Err(anyhow!("policy is not an object")),
+    }
+  }
+}
+
+/// Name of a lockable setting as the menus show it.
+pub(crate) fn setting_label(key: &str) -> &str {
+  match key {
+    "contentProtection" => "画面を保護",
+    "autoStart" => "Windows 起動時に自動起動",
+    "startMinimized" => "起動時に最小化",
+    "logLevel" => "ログレベル",
+    _ => key,
+  }
+}
+
+pub(crate) fn locked_error(key: &str) -> String {
+  format!("「{}」は{MANAGED_HINT}", setting_label(key))
+}
+
+pub(crate) fn policy_path(app: &tauri::AppHandle) -> Result<PathBuf> {
+  Ok(machine_config_dir(app)?.join("policy.json"))
+}
+
+/// Reads the policy file. An unreadable policy is logged and treated as absent rather than
+/// blocking startup.
+pub(crate) fn load_policy(app: &tauri::AppHandle) -> Policy {
+  let path = match policy_path(app) {
+    Ok(path) => path,
+    Err(error) => {
+      warn!("[policy] path error: {error:#}");
+      return Policy::default();
+    }
+  };
+  let raw = match fs::read_to_string(&path) {
+    Ok(raw) => raw,
+    Err(error) if error.kind() == ErrorKind::NotFound => return Policy::default(),
+    Err(error) => {
+      warn!("[policy] read failed {}: {error:#}", path.display());
+      return Policy::default();
+    }
+  };
+  match serde_json::from_str::<Policy>(&raw) {
+    Ok(policy) => policy,
+    Err(error) => {
+      warn!("[policy] invalid {}: {error:#}", path.display());
+      Policy::default()
+    }
+  }
+}
+
+pub(crate) fn current_policy(app: &tauri::AppHandle) -> Policy {
+  app
+    .try_state::<Policy>()
+    .map(|policy| policy.inner().clone())
+    .unwrap_or_else(|| load_policy(app))
+}
+
+pub(crate) fn init_policy(app: &tauri::AppHandle) -> Policy {
+  let policy = load_policy(app);
+  if !policy.is_empty() {
+    info!("[policy] locked keys={:?}", policy.locked_keys());
+  }
+  if let Some(forced) = policy.auto_start {
+    let enabled = app.autolaunch().is_enabled().unwrap_or(!forced);
+    if enabled != forced {
+      let result = if forced {
+        app.autolaunch().enable()
+      } else {
+        app.autolaunch().disable()
+      };
+      if let Err(error) = result {
+        warn!("[policy] autostart enforce failed: {error:#}");
+      }
+    }
+  }
+  app.manage(policy.clone());
+  policy
+}
+
+#[tauri::command]
+pub(crate) fn get_locked_settings(app_handle: tauri::AppHandle) -> Vec<&'static str> {
+  current_policy(&app_handle).locked_keys()
+}
@@ -0,0 +1,330 @@
+use crate::cache_cleanup::is_rebuildable_cache;
+use crate::config::ProfileBackupConfig;
+use crate::crx::extract_zip_file;
+use crate::maintenance::{enqueue_maintenance, MaintenanceOp};
+use crate::paths::{profile_backups_dir, profile_dir};
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const BACKUP_PREFIX: &str = "profile-";
+const BACKUP_EXTENSION: &str = "zip";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ProfileBackup {
+  name: String,
+  path: String,
+  created_at: u64,
+  size_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CreatedBackup {
+  path: String,
+  /// Files the running webview held locked, which the archive is missing.
+  skipped: usize,
+}
+
+/// Archives the profile directory. While the webview is running some files are locked;
+/// those are skipped and counted, so on-demand backups are best effort.
+pub(crate) fn create_profile_backup(
+  app: &tauri::AppHandle,
+  config: &ProfileBackupConfig,
+) -> Result<CreatedBackup> {
+  let profile = profile_dir(app)?;
+  if !profile.is_dir() {
+    return Err(anyhow!("profile dir not found: {}", profile.display()));
+  }
+  let backups_dir = profile_backups_dir(app)?;
+  fs::create_dir_all(&backups_dir)?;
+
+  let started = Instant::now();
+  let created_at = unix_now();
+  let out_path = backups_dir.join(format!("{BACKUP_PREFIX}{created_at}.{BACKUP_EXTENSION}"));
+  let temp_path = out_path.with_extension("partial");
+  let file = fs::File::create(&temp_path)?;
+  let mut zip = ZipWriter::new(file);
+  let options = SimpleFileOptions::default()
+    .compression_method(CompressionMethod::Deflated)
+    .large_file(true);
+  let mut skipped = 0usize;
+  if let Err(error) = add_profile_dir(&mut zip, &profile, "", options, &mut skipped) {
+    let _ = fs::remove_file(&temp_path);
+    return Err(error);
+  }
+  zip.finish()?;
+  fs::rename(&temp_path, &out_path)?;
+  info!(
+    "[profile-backup] created {} skipped={skipped} in {:?}",
+    out_path.display(),
+    started.elapsed()
+  );
+  if skipped > 0 {
+    warn!("[profile-backup] {skipped} locked files are missing from the archive");
+  }
+
+  prune_backups(app, config.retention)?;
+  Ok(CreatedBackup {
+    path: out_path.display().to_string(),
+    skipped,
+  })
+}
+
+pub(crate) fn list_backups(app: &tauri::AppHandle) -> Result<Vec<ProfileBackup>> {
+  let backups_dir = profile_backups_dir(app)?;
+  let mut backups = Vec::new();
+  if !backups_dir.is_dir() {
+    return Ok(backups);
+  }
+  for entry in fs::read_dir(&backups_dir)? {
+    let entry = entry?;
+    let path = entry.path();
+    let Some(created_at) = backup_timestamp(&path) else {
+      continue;
+    };
+    backups.push(ProfileBackup {
+      name: entry.file_name().to_string_lossy().to_string(),
+      path: path.display().to_string(),
+      created_at,
+      size_bytes: entry.metadata().map(|meta| meta.len()).unwrap_or(0),
+    });
+  }
+  backups.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+  Ok(backups)
+}
+
+/// Whether the newest backup is older than the configured interval.
+pub(crate) fn scheduled_backup_due(app: &tauri::AppHandle, config: &ProfileBackupConfig) -> bool {
+  if config.interval_hours == 0 {
+    return false;
+  }
+  let newest = list_backups(app)
+    .ok()
+    .and_then(|backups| backups.first().map(|backup| backup.created_at));
+  let interval = config.interval_hours.saturating_mul(60 * 60);
+  match newest {
+    Some(newest) if unix_now().saturating_sub(newest) < interval => {
+      debug!("[profile-backup] schedule not due");
+      false
+    }
+    _ => true,
+  }
+}
+
+/// Takes the scheduled backup. Runs before the webview locks the profile, so nothing is skipped.
+pub(crate) fn run_scheduled_backup(app: &tauri::AppHandle, config: &ProfileBackupConfig) {
+  if let Err(error) = create_profile_backup(app, config) {
+    warn!("[profile-backup] scheduled backup failed: {error:#}");
+  }
+}
+
+/// Replaces the profile with `backup`. The current profile is kept aside until the archive is
+/// fully extracted so a broken backup never leaves the user without one.
+pub(crate) fn restore_profile(app: &tauri::AppHandle, backup: &Path) -> Result<()> {
+  if !backup.is_file() {
+    return Err(anyhow!("backup not found: {}", backup.display()));
+  }
+
+  let profile = profile_dir(app)?;
+  let aside = profile.with_extension("before-restore");
+  if aside.exists() {
+    fs::remove_dir_all(&aside)?;
+  }
+  if profile.exists() {
+    fs::rename(&profile, &aside)?;
+  }
+  match extract_zip_file(backup, &profile) {
+    Ok(()) => {
+      if aside.exists() {
+        if let Err(error) = fs::remove_dir_all(&aside) {
+          warn!("[profile-backup] cleanup failed: {error:#}");
+        }
+      }
+      info!("[profile-backup] restored {}", backup.display());
+      Ok(())
+    }
+    Err(error) => {
+      let _ = fs::remove_dir_all(&profile);
+      if aside.exists() {
+        fs::rename(&aside, &profile)?;
+      }
+      Err(error)
+    }
+  }
+}
+
+/// Resolves a backup by the name `list_profile_backups` reported, so callers never pass a path.
+fn find_backup(app: &tauri::AppHandle, name: &str) -> Result<PathBuf> {
+  list_backups(app)?
+    .into_iter()
+    .find(|backup| backup.name == name)
+    .map(|backup| PathBuf::from(backup.path))
+    .ok_or_else(|| anyhow!("backup not found: {name}"))
+}
+
+fn request_restore(app: &tauri::AppHandle, backup: &Path) -> Result<()> {
+  if backup_timestamp(backup).is_none() || !backup.is_file() {
+    return Err(anyhow!("not a profile backup: {}", backup.display()));
+  }
+  enqueue_maintenance(
+    app,
+    MaintenanceOp::RestoreProfileBackup {
+      path: backup.display().to_string(),
+    },
+  )?;
+  Ok(())
+}
+
+fn prune_backups(app: &tauri::AppHandle, retention: usize) -> Result<()> {
+  let backups = list_backups(app)?;
+  for backup in backups.iter().skip(retention.max(1)) {
+    match fs::remove_file(&backup.path) {
+      Ok(()) => debug!("[profile-backup] pruned {}", backup.name),
+      Err(error) => warn!("[profile-backup] prune {} failed: {error:#}", backup.name),
+    }
+  }
+  Ok(())
+}
+
+fn add_profile_dir(
+  zip: &mut ZipWriter<fs::File>,
+  dir: &Path,
+  prefix: &str,
+  options: SimpleFileOptions,
+  skipped: &mut usize,
+) -> Result<()> {
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+      continue;
+    };
+    let entry_name = format!("{prefix}{name}");
+    if path.is_dir() {
+      // Caches are rebuilt by WebView2 on demand and only bloat the archive.
+      if is_rebuildable_cache(Path::new(&entry_name)) {
+        continue;
+      }
+      zip.add_directory(format!("{entry_name}/"), options)?;
+      add_profile_dir(zip, &path, &format!("{entry_name}/"), options, skipped)?;
+      continue;
+    }
+    match fs::read(&path) {
+      Ok(bytes) => {
+        zip.start_file(entry_name, options)?;
+        zip.write_all(&bytes)?;
+      }
+      Err(error) => {
+        debug!("[profile-backup] skip {}: {error}", path.display());
+        *skipped += 1;
+      }
+    }
+  }
+  Ok(())
+}
+
+fn backup_timestamp(path: &Path) -> Option<u64> {
+  if path.extension().and_then(|ext| ext.to_str()) != Some(BACKUP_EXTENSION) {
+    return None;
+  }
+  path
+    .file_stem()?
+    .to_str()?
+    .strip_prefix(BACKUP_PREFIX)?
+    .parse()
+    .ok()
+}
+
+fn unix_now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_secs()
+}
+
+#[tauri::command]
+pub(crate) async fn backup_profile(app_handle: tauri::AppHandle) -> Result<CreatedBackup, String> {
+  let config = crate::config::load_config(&app_handle)
+    .map(|config| config.profile_backup)
+    .unwrap_or_default();
+  let handle = app_handle.clone();
+  let backup = tauri::async_runtime::spawn_blocking(move || {
+    create_profile_backup(&handle, &config).map_err(|error| error.to_string())
+  })
+  .await
+  .map_err(|error| error.to_string())??;
+
+  let mut message = "プロファイルのバックアップを作成しました。".to_string();
+  if backup.skipped > 0 {
+    message.push_str(&format!(
+      "\n使用中の {} 個のファイルは保存できませんでした。ログイン情報（Cookie など）が含まれていない可能性があります。",
+      backup.skipped
+    ));
+  }
+  app_handle
+    .dialog()
+    .message(message)
+    .title("プロファイルのバックアップ")
+    .show(|_| {});
+  Ok(backup)
+}
+
+#[tauri::command]
+pub(crate) fn list_profile_backups(
+  app_handle: tauri::AppHandle,
+) -> Result<Vec<ProfileBackup>, String> {
+  list_backups(&app_handle).map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+pub(crate) async fn restore_profile_backup(
+  app_handle: tauri::AppHandle,
+  name: Option<String>,
+) -> Result<bool, String> {
+  let backup = match name {
+    Some(name) => find_backup(&app_handle, &name).map_err(|error| error.to_string())?,
+    None => {
+      let backups_dir = profile_backups_dir(&app_handle).map_err(|error| error.to_string())?;
+      let (tx, mut rx) = tauri::async_runtime::channel(1);
+      app_handle
+        .dialog()
+        .file()
+        .add_filter("プロファイルのバックアップ", &[BACKUP_EXTENSION])
+        .set_directory(backups_dir)
+        .pick_file(move |path| {
+          let _ = tx.try_send(path);
+        });
+      let Some(path) = rx.recv().await.flatten() else {
+        return Ok(false);
+      };
+      path.into_path().map_err(|error| error.to_string())?
+    }
+  };
+
+  let (tx, mut rx) = tauri::async_runtime::channel(1);
+  app_handle
+    .dialog()
+    .message(
+      "次回起動時にWebView のプロファイルをバックアップ時点に戻します。アプリを再起動しますか？",
+    )
+    .title("プロファイルの復元")
+    .buttons(MessageDialogButtons::YesNo)
+    .show(move |confirmed| {
+      let _ = tx.try_send(confirmed);
+    });
+  if !rx.recv().await.unwrap_or(false) {
+    return Ok(false);
+  }
+
+  request_restore(&app_handle, &backup).map_err(|error| error.to_string())?;
+  info!("[profile-backup] restore scheduled {}", backup.display());
+  app_handle.restart();
+}
@@ -0,0 +1,393 @@
+use crate::atomic_write::write_atomic;
+use crate::cli::cli_args;
+#[cfg(target_os = "windows")]
+use crate::config::load_config;
+use crate::content_protection::store_base_title;
+#[cfg(target_os = "windows")]
+use crate::extensions::{
+  install_extensions_and_open, installed_extensions, schedule_cookie_snapshots,
+};
+use crate::paths::app_paths;
+use crate::tray::refresh_tray_menu;
+use crate::windowing::with_link_handlers;
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::{Manager, WebviewWindowBuilder};
+
+pub(crate) const DEFAULT_PROFILE_ID: &str = "default";
+const DEFAULT_PROFILE_NAME: &str = "既定";
+const PROFILE_WINDOW_PREFIX: &str = "profile:";
+const APP_TITLE: &str = "refined-line";
+pub(crate) const PROFILE_MENU_SWITCH_PREFIX: &str = "profile.switch.";
+pub(crate) const PROFILE_MENU_OPEN_PREFIX: &str = "profile.open.";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ProfileEntry {
+  pub(crate) id: String,
+  pub(crate) name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProfileRegistry {
+  #[serde(default = "default_profile_id")]
+  active: String,
+  #[serde(default)]
+  profiles: Vec<ProfileEntry>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ProfileList {
+  /// Profile of the main window; switching changes it after a restart.
+  active: String,
+  /// Profile of the window that asked.
+  current: String,
+  profiles: Vec<ProfileEntry>,
+  open: Vec<String>,
+}
+
+impl Default for ProfileRegistry {
+  fn default() -> Self {
+    Self {
+      active: default_profile_id(),
+      profiles: Vec::new(),
+    }
+  }
+}
+
+impl ProfileRegistry {
+  /// The default profile always exists and always comes first, even in a hand-edited file.
+  fn normalized(mut self) -> Self {
+    self
+      .profiles
+      .retain(|profile| profile.id != DEFAULT_PROFILE_ID && is_valid_profile_id(&profile.id));
+    self.profiles.insert(
+      0,
+      ProfileEntry {
+        id: DEFAULT_PROFILE_ID.to_string(),
+        name: DEFAULT_PROFILE_NAME.to_string(),
+      },
+    );
+    if self.find(&self.active).is_none() {
+      self.active = default_profile_id();
+    }
+    self
+  }
+
+  fn find(&self, id: &str) -> Option<&ProfileEntry> {
+    self.profiles.iter().find(|profile| profile.id == id)
+  }
+
+  /// By id, or else by display name, so the command line can use either.
+  fn lookup(&self, id_or_name: &str) -> Option<&ProfileEntry> {
+    self.find(id_or_name).or_else(|| {
+      self
+        .profiles
+        .iter()
+        .find(|profile| profile.name == id_or_name)
+    })
+  }
+}
+
+fn default_profile_id() -> String {
+  DEFAULT_PROFILE_ID.to_string()
+}
+
+fn is_valid_profile_id(id: &str) -> bool {
+  !id.is_empty()
+    && id
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn read_registry(path: &Path) -> ProfileRegistry {
+  let registry = match fs::read_to_string(path) {
+    Ok(raw) => serde_json::from_str(&raw).unwrap_or_else(|error| {
+      warn!("[profiles] registry invalid: {error}");
+      ProfileRegistry::default()
+    }),
+    Err(_) => ProfileRegistry::default(),
+  };
+  registry.normalized()
+}
+
+fn write_registry(path: &Path, registry: &ProfileRegistry) -> Result<()> {
+  write_atomic(path, &serde_json::to_vec_pretty(registry)?)
+}
+
+fn load_registry(app: &tauri::AppHandle) -> Result<ProfileRegistry> {
+  Ok(read_registry(&app_paths(app)?.profiles_file()))
+}
+
+fn save_registry(app: &tauri::AppHandle, registry: &ProfileRegistry) -> Result<()> {
+  write_registry(&app_paths(app)?.profiles_file(), registry)?;
+  refresh_tray_menu(app);
+  Ok(())
+}
+
+/// `--profile <id or name>` picks the profile for this run only; otherwise the saved active one
+/// is used.
+pub(crate) fn active_profile_id(root: &Path) -> String {
+  let registry = read_registry(&root.join("profiles.json"));
+  if let Some(requested) = cli_args().profile.as_deref() {
+    if let Some(profile) = registry.lookup(requested) {
+      return profile.id.clone();
+    }
+    warn!("[profiles] unknown profile {requested}");
+  }
+  registry.active
+}
+
+/// Id of the profile with this id or display name.
+pub(crate) fn find_profile_id(app: &tauri::AppHandle, id_or_name: &str) -> Result<String> {
+  load_registry(app)?
+    .lookup(id_or_name)
+    .map(|profile| profile.id.clone())
+    .ok_or_else(|| anyhow!("unknown profile {id_or_name}"))
+}
+
+pub(crate) fn list_profile_entries(app: &tauri::AppHandle) -> Vec<ProfileEntry> {
+  load_registry(app)
+    .map(|registry| registry.profiles)
+    .unwrap_or_default()
+}
+
+pub(crate) fn current_profile_id(app: &tauri::AppHandle) -> String {
+  app_paths(app)
+    .map(|paths| paths.profile_id().to_string())
+    .unwrap_or_else(|_| default_profile_id())
+}
+
+pub(crate) fn profile_window_title(name: Option<&str>) -> String {
+  match name {
+    Some(name) => format!("{APP_TITLE} - {name}"),
+    None => APP_TITLE.to_string(),
+  }
+}
+
+/// Title of the main window: plain for the default profile, suffixed for named ones.
+pub(crate) fn main_window_title(app: &tauri::AppHandle) -> String {
+  let id = current_profile_id(app);
+  if id == DEFAULT_PROFILE_ID {
+    return profile_window_title(None);
+  }
+  let name = load_registry(app)
+    .ok()
+    .and_then(|registry| registry.find(&id).map(|entry| entry.name.clone()));
+  profile_window_title(name.as_deref().or(Some(id.as_str())))
+}
+
+pub(crate) fn is_profile_window(label: &str) -> bool {
+  label.starts_with(PROFILE_WINDOW_PREFIX)
+}
+
+pub(crate) fn profile_of_window(app: &tauri::AppHandle, label: &str) -> String {
+  label
+    .strip_prefix(PROFILE_WINDOW_PREFIX)
+    .map(str::to_string)
+    .unwrap_or_else(|| current_profile_id(app))
+}
+
+fn open_profile_ids(app: &tauri::AppHandle) -> Vec<String> {
+  let mut open = vec![current_profile_id(app)];
+  for label in app.webview_windows().keys() {
+    if let Some(id) = label.strip_prefix(PROFILE_WINDOW_PREFIX) {
+      open.push(id.to_string());
+    }
+  }
+  open
+}
+
+fn create_profile_entry(app: &tauri::AppHandle, name: &str) -> Result<ProfileEntry> {
+  let name = name.trim();
+  if name.is_empty() {
+    return Err(anyhow!("profile name is empty"));
+  }
+  let mut registry = load_registry(app)?;
+  let next = registry
+    .profiles
+    .iter()
+    .filter_map(|profile| profile.id.strip_prefix("profile-")?.parse::<u32>().ok())
+    .max()
+    .unwrap_or(0)
+    + 1;
+  let entry = ProfileEntry {
+    id: format!("profile-{next}"),
+    name: name.to_string(),
+  };
+  registry.profiles.push(entry.clone());
+  save_registry(app, &registry)?;
+  info!("[profiles] created {}", entry.id);
+  Ok(entry)
+}
+
+fn switch_active_profile(app: &tauri::AppHandle, id: &str) -> Result<()> {
+  let mut registry = load_registry(app)?;
+  if registry.find(id).is_none() {
+    return Err(anyhow!("unknown profile {id}"));
+  }
+  registry.active = id.to_string();
+  save_registry(app, &registry)?;
+  info!("[profiles] switching to {id}");
+  app.restart();
+}
+
+/// Opens `id` next to the main window. Each profile window gets its own WebView2 data directory,
+/// so logins, cookies and badges stay separate.
+pub(crate) fn open_profile_window(app: &tauri::AppHandle, id: &str) -> Result<()> {
+  if id == current_profile_id(app) {
+    return focus_window(app, "main");
+  }
+  let label = format!("{PROFILE_WINDOW_PREFIX}{id}");
+  if app.get_webview_window(&label).is_some() {
+    return focus_window(app, &label);
+  }
+  let registry = load_registry(app)?;
+  let entry = registry
+    .find(id)
+    .ok_or_else(|| anyhow!("unknown profile {id}"))?;
+  let data_dir = app_paths(app)?.profile_data(id);
+  fs::create_dir_all(&data_dir)?;
+
+  let title = profile_window_title(Some(&entry.name));
+  let mut conf = app
+    .config()
+    .app
+    .windows
+    .first()
+    .cloned()
+    .ok_or_else(|| anyhow!("window config not found"))?;
+  conf.label = label.clone();
+  conf.title = title.clone();
+  store_base_title(app, &label, &title);
+  let builder = WebviewWindowBuilder::from_config(app, &conf)?
+    .data_directory(data_dir.clone())
+    .browser_extensions_enabled(true);
+  let window = with_link_handlers(builder, app, data_dir).build()?;
+  info!("[profiles] opened {id} in {}", window.label());
+
+  #[cfg(target_os = "windows")]
+  {
+    let setup = installed_extensions(app)?;
+    let cookie_domains = load_config(app)
+      .map(|config| config.cookie_persistence)
+      .unwrap_or_default()
+      .domains;
+    window.with_webview(move |webview| {
+      let opened =
+        install_extensions_and_open(webview, setup.extensions, setup.user_dir, &cookie_domains);
+      if let Err(error) = opened {
+        warn!("[profiles] open failed: {error:#}");
+      }
+    })?;
+    schedule_cookie_snapshots(app, window.label());
+  }
+  Ok(())
+}
+
+fn focus_window(app: &tauri::AppHandle, label: &str) -> Result<()> {
+  let window = app
+    .get_webview_window(label)
+    .ok_or_else(|| anyhow!("window {label} not found"))?;
+  let _ = window.unminimize();
+  window.show()?;
+  window.set_focus()?;
+  Ok(())
+}
+
+/// Handles `profile.switch.<id>` and `profile.open.<id>` from the titlebar and tray menus.
+pub(crate) fn handle_profile_menu_action(
+  app: &tauri::AppHandle,
+  id: &str,
+) -> Option<Result<(), String>> {
+  let result = if let Some(profile) = id.strip_prefix(PROFILE_MENU_SWITCH_PREFIX) {
+    if profile == current_profile_id(app) {
+      Ok(())
+    } else {
+      switch_active_profile(app, profile)
+    }
+  } else if let Some(profile) = id.strip_prefix(PROFILE_MENU_OPEN_PREFIX) {
+    open_profile_window(app, profile)
+  } else {
+    return None;
+  };
+  Some(result.map_err(|error| error.to_string()))
+}
+
+#[tauri::command]
+pub(crate) fn list_profiles(
+  app_handle: tauri::AppHandle,
+  window: tauri::WebviewWindow,
+) -> Result<ProfileList, String> {
+  let registry = load_registry(&app_handle).map_err(|error| error.to_string())?;
+  Ok(ProfileList {
+    active: current_profile_id(&app_handle),
+    current: profile_of_window(&app_handle, window.label()),
+    profiles: registry.profiles,
+    open: open_profile_ids(&app_handle),
+  })
+}
+
+#[tauri::command]
+pub(crate) fn create_profile(
+  app_handle: tauri::AppHandle,
+  name: String,
+) -> Result<ProfileEntry, String> {
+  create_profile_entry(&app_handle, &name).map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+pub(crate) fn rename_profile(
+  app_handle: tauri::AppHandle,
+  id: String,
+  name: String,
+) -> Result<(), String> {
+  let name = name.trim();
+  if id == DEFAULT_PROFILE_ID || name.is_empty() {
+    return Err("この名前には変更できません".to_string());
+  }
+  let mut registry = load_registry(&app_handle).map_err(|error| error.to_string())?;
+  let entry = registry
+    .profiles
+    .iter_mut()
+    .find(|profile| profile.id == id)
+    .ok_or_else(|| format!("unknown profile {id}"))?;
+  entry.name = name.to_string();
+  save_registry(&app_handle, &registry).map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+pub(crate) fn delete_profile(app_handle: tauri::AppHandle, id: String) -> Result<(), String> {
+  if id == DEFAULT_PROFILE_ID || open_profile_ids(&app_handle).contains(&id) {
+    return Err("使用中のプロファイルは削除できません".to_string());
+  }
+  let mut registry = load_registry(&app_handle).map_err(|error| error.to_string())?;
+  if registry.find(&id).is_none() {
+    return Err(format!("unknown profile {id}"));
+  }
+  registry.profiles.retain(|profile| profile.id != id);
+  save_registry(&app_handle, &registry).map_err(|error| error.to_string())?;
+  let base = app_paths(&app_handle)
+    .map_err(|error| error.to_string())?
+    .profile_base(&id);
+  if let Err(error) = fs::remove_dir_all(&base) {
+    warn!("[profiles] remove {} failed: {error}", base.display());
+  }
+  info!("[profiles] deleted {id}");
+  Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn switch_profile(app_handle: tauri::AppHandle, id: String) -> Result<(), String> {
+  switch_active_profile(&app_handle, &id).map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+pub(crate) fn open_profile(app_handle: tauri::AppHandle, id: String) -> Result<(), String> {
+  open_profile_window(&app_handle, &id).map_err(|error| error.to_string())
+}
@@ -0,0 +1,164 @@
+use crate::extensions::{
+  managed_extension_versions, reinstall_managed_extensions, rollback_managed_extensions,
+  ExtensionVersions,
+};
+#[cfg(target_os = "windows")]
+use crate::startup::spawn_main_extensions;
+use crate::startup::{Repair, StartupStage};
+use log::info;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{Emitter, Manager};
+
+/// Sent to the main window when opening the extensions failed; the payload is a `StartupFailure`.
+pub(crate) const STARTUP_FAILED_EVENT: &str = "startup-failed";
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StartupFailure {
+  stage: StartupStage,
+  message: String,
+  at: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RecoveryInfo {
+  failure: Option<StartupFailure>,
+  running: bool,
+  extensions: Vec<ExtensionVersions>,
+}
+
+/// Last failure of the main window's extension startup, shown by the recovery screen in
+/// `index.html`, and whether a run is in progress.
+#[derive(Default)]
+pub(crate) struct RecoveryState {
+  failure: Mutex<Option<StartupFailure>>,
+  running: AtomicBool,
+}
+
+pub(crate) fn init_recovery_state(app: &tauri::AppHandle) {
+  app.manage(RecoveryState::default());
+}
+
+/// Claims the extension startup. Returns false when another run has not finished yet.
+pub(crate) fn begin_extension_startup(app: &tauri::AppHandle) -> bool {
+  let Some(state) = app.try_state::<RecoveryState>() else {
+    return true;
+  };
+  !state.running.swap(true, Ordering::SeqCst)
+}
+
+/// Ends the run claimed by `begin_extension_startup`. A failure is kept for the recovery screen
+/// and sent to the main window, which still shows `index.html` at that point.
+pub(crate) fn finish_extension_startup(
+  app: &tauri::AppHandle,
+  result: Result<(), (StartupStage, anyhow::Error)>,
+) {
+  let failure = result.err().map(|(stage, error)| StartupFailure {
+    stage,
+    message: format!("{error:#}"),
+    at: unix_now(),
+  });
+  if let Some(state) = app.try_state::<RecoveryState>() {
+    *state
+      .failure
+      .lock()
+      .unwrap_or_else(|error| error.into_inner()) = failure.clone();
+    state.running.store(false, Ordering::SeqCst);
+  }
+  if let Some(failure) = failure {
+    let _ = app.emit_to("main", STARTUP_FAILED_EVENT, failure);
+  }
+}
+
+/// Opens the extensions again, running `repair` first as part of the prepare stage. Only a failed
+/// startup can be recovered, and the startup claim is taken here, so a repair never touches files
+/// a running startup is using.
+fn run_recovery(
+  app: &tauri::AppHandle,
+  action: &str,
+  repair: Option<Repair>,
+) -> Result<(), String> {
+  let Some(state) = app.try_state::<RecoveryState>() else {
+    return Err("recovery is not available".to_string());
+  };
+  let failed = state
+    .failure
+    .lock()
+    .unwrap_or_else(|error| error.into_inner())
+    .is_some();
+  if !failed {
+    return Err("startup has not failed".to_string());
+  }
+  if !begin_extension_startup(app) {
+    return Err("startup is already running".to_string());
+  }
+  info!("[recovery] {action}");
+  #[cfg(target_os = "windows")]
+  {
+    spawn_main_extensions(app, repair);
+    Ok(())
+  }
+  #[cfg(not(target_os = "windows"))]
+  {
+    // Nothing would finish the claim taken above.
+    drop(repair);
+    state.running.store(false, Ordering::SeqCst);
+    Err("extensions are only supported on Windows".to_string())
+  }
+}
+
+fn unix_now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_secs()
+}
+
+#[tauri::command]
+pub(crate) fn get_recovery_info(app_handle: tauri::AppHandle) -> Result<RecoveryInfo, String> {
+  let (failure, running) = match app_handle.try_state::<RecoveryState>() {
+    Some(state) => (
+      state
+        .failure
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+        .clone(),
+      state.running.load(Ordering::SeqCst),
+    ),
+    None => (None, false),
+  };
+  Ok(RecoveryInfo {
+    failure,
+    running,
+    extensions: managed_extension_versions(&app_handle).map_err(|error| error.to_string())?,
+  })
+}
+
+#[tauri::command]
+pub(crate) fn retry_startup(app_handle: tauri::AppHandle) -> Result<(), String> {
+  run_recovery(&app_handle, "retry", None)
+}
+
+#[tauri::command]
+pub(crate) fn reinstall_extensions(app_handle: tauri::AppHandle) -> Result<(), String> {
+  run_recovery(
+    &app_handle,
+    "reinstall",
+    Some(Box::new(reinstall_managed_extensions)),
+  )
+}
+
+#[tauri::command]
+pub(crate) fn rollback_extensions(app_handle: tauri::AppHandle) -> Result<(), String> {
+  run_recovery(
+    &app_handle,
+    "rollback",
+    Some(Box::new(|app: &tauri::AppHandle| {
+      rollback_managed_extensions(app).map(|_| ())
+    })),
+  )
+}
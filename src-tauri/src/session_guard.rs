@@ -0,0 +1,638 @@
+use crate::atomic_write::write_atomic;
+use crate::config::{load_config, CookiePersistenceConfig};
+use crate::paths::app_paths;
+use crate::profiles::{is_profile_window, profile_of_window};
+use anyhow::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::Manager;
+use tauri_plugin_dialog::DialogExt;
+
+#[cfg(target_os = "windows")]
+use crate::browser::WebView2Backend;
+#[cfg(target_os = "windows")]
+use crate::cookie_jar::CookieRecord;
+#[cfg(target_os = "windows")]
+use crate::extensions::{read_domain_cookie_records, restore_cookie_records};
+#[cfg(target_os = "windows")]
+use log::info;
+#[cfg(target_os = "windows")]
+use std::io::ErrorKind;
+#[cfg(target_os = "windows")]
+use std::time::Duration;
+
+// The last good count must be at least this for a drop or the login view to count as a logout,
+// so a profile that never logged in does not trigger a restore.
+const MIN_BASELINE_COOKIES: usize = 2;
+// The extension needs a moment after the reload before its login view or chat list shows.
+#[cfg(target_os = "windows")]
+const RESTORE_VERIFY_DELAY: Duration = Duration::from_secs(20);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LogoutSignal {
+  #[cfg(any(target_os = "windows", test))]
+  CookiesCollapsed,
+  LoginView,
+}
+
+/// Per-profile counters for how often session persistence holds up.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct SessionStats {
+  checks: u64,
+  snapshots_saved: u64,
+  logouts_detected: u64,
+  restores_attempted: u64,
+  restores_succeeded: u64,
+  restores_failed: u64,
+  last_good_cookie_count: usize,
+  last_logout_at: Option<u64>,
+  last_restore_at: Option<u64>,
+}
+
+#[derive(Default)]
+struct WindowSession {
+  on_login_view: bool,
+  /// Set from detection until a logged-in check; one logout episode triggers one restore.
+  logged_out: bool,
+  restoring: bool,
+}
+
+/// What a persistence check of a window's cookies leads to.
+#[cfg(any(target_os = "windows", test))]
+#[derive(Debug, PartialEq, Eq)]
+enum CheckOutcome {
+  /// A restore is running and verifies the session itself.
+  Skip,
+  Logout(LogoutSignal),
+  /// Logged in: the cookies become the snapshot to restore from.
+  SaveSnapshot,
+  /// No cookies, or still logged out; nothing worth keeping.
+  Ignore,
+}
+
+impl WindowSession {
+  #[cfg(any(target_os = "windows", test))]
+  fn check_cookies(&mut self, last_good: usize, count: usize) -> CheckOutcome {
+    if self.restoring {
+      return CheckOutcome::Skip;
+    }
+    if cookies_collapsed(last_good, count) {
+      if self.logged_out {
+        return CheckOutcome::Ignore;
+      }
+      self.logged_out = true;
+      return CheckOutcome::Logout(LogoutSignal::CookiesCollapsed);
+    }
+    if count > 0 && !self.on_login_view {
+      self.logged_out = false;
+    }
+    if count == 0 || self.logged_out || self.on_login_view {
+      CheckOutcome::Ignore
+    } else {
+      CheckOutcome::SaveSnapshot
+    }
+  }
+
+  /// Records whether the page shows the login view. Returns true when entering it starts a
+  /// logout episode, which needs a logged-in baseline to have been seen first.
+  fn route_changed(&mut self, login_view: bool, last_good: usize) -> bool {
+    let entered = login_view && !self.on_login_view;
+    self.on_login_view = login_view;
+    if entered && last_good >= MIN_BASELINE_COOKIES && !self.logged_out && !self.restoring {
+      self.logged_out = true;
+      true
+    } else {
+      false
+    }
+  }
+
+  #[cfg(any(target_os = "windows", test))]
+  fn finish_restore(&mut self, restored: bool) {
+    self.restoring = false;
+    self.logged_out = !restored;
+  }
+}
+
+#[derive(Default)]
+pub(crate) struct SessionGuardState {
+  windows: Mutex<HashMap<String, WindowSession>>,
+  // Serializes read-modify-write of the stats files.
+  stats: Mutex<()>,
+}
+
+pub(crate) fn init_session_guard_state(app: &tauri::AppHandle) {
+  app.manage(SessionGuardState::default());
+}
+
+/// Checks the LINE cookie set of `label` during a persistence run. A logged-in set is kept as
+/// the snapshot to restore from; a collapsed one is treated as a logout. Reads and writes files,
+/// so callers keep it off the UI thread.
+#[cfg(target_os = "windows")]
+pub(crate) fn observe_session(
+  app: &tauri::AppHandle,
+  label: &str,
+  records: &[CookieRecord],
+  config: &CookiePersistenceConfig,
+) {
+  let Some(state) = app.try_state::<SessionGuardState>() else {
+    return;
+  };
+  let count = records.len();
+  let last_good = update_stats(app, label, |stats| stats.checks += 1)
+    .map(|stats| stats.last_good_cookie_count)
+    .unwrap_or(0);
+
+  let outcome = state
+    .windows
+    .lock()
+    .unwrap_or_else(|error| error.into_inner())
+    .entry(label.to_string())
+    .or_default()
+    .check_cookies(last_good, count);
+  match outcome {
+    CheckOutcome::Logout(signal) => {
+      handle_logout(app, label, signal, config);
+      return;
+    }
+    CheckOutcome::SaveSnapshot => {}
+    CheckOutcome::Skip | CheckOutcome::Ignore => return,
+  }
+  match save_snapshot(app, label, records) {
+    Ok(()) => {
+      let _ = update_stats(app, label, |stats| {
+        stats.snapshots_saved += 1;
+        stats.last_good_cookie_count = count;
+      });
+    }
+    Err(error) => warn!("[session] {label} snapshot failed: {error:#}"),
+  }
+}
+
+#[cfg(any(target_os = "windows", test))]
+fn cookies_collapsed(last_good: usize, current: usize) -> bool {
+  last_good >= MIN_BASELINE_COOKIES && current * 2 < last_good
+}
+
+fn is_login_view(url: &str, patterns: &[String]) -> bool {
+  patterns
+    .iter()
+    .any(|pattern| !pattern.is_empty() && url.contains(pattern.as_str()))
+}
+
+fn handle_logout(
+  app: &tauri::AppHandle,
+  label: &str,
+  signal: LogoutSignal,
+  config: &CookiePersistenceConfig,
+) {
+  warn!("[session] {label} logged out ({signal:?})");
+  let _ = update_stats(app, label, |stats| {
+    stats.logouts_detected += 1;
+    stats.last_logout_at = Some(unix_now());
+  });
+  if !config.restore_on_logout {
+    return;
+  }
+  #[cfg(target_os = "windows")]
+  if has_snapshot(app, label) {
+    restore_session(app, label);
+  } else {
+    info!("[session] {label} has no snapshot to restore");
+  }
+}
+
+/// Replays the last good snapshot, reloads, and reports the outcome once the page settled.
+#[cfg(target_os = "windows")]
+fn restore_session(app: &tauri::AppHandle, label: &str) {
+  let Some(state) = app.try_state::<SessionGuardState>() else {
+    return;
+  };
+  if let Ok(mut windows) = state.windows.lock() {
+    windows.entry(label.to_string()).or_default().restoring = true;
+  }
+  let _ = update_stats(app, label, |stats| {
+    stats.restores_attempted += 1;
+    stats.last_restore_at = Some(unix_now());
+  });
+
+  let app = app.clone();
+  let label = label.to_string();
+  std::thread::spawn(move || {
+    let result = load_snapshot(&app, &label).and_then(|records| {
+      let window = app
+        .get_webview_window(&label)
+        .ok_or_else(|| anyhow::anyhow!("window {label} is gone"))?;
+      let (tx, rx) = std::sync::mpsc::channel();
+      window.with_webview(move |webview| {
+        let result = WebView2Backend::new(&webview)
+          .and_then(|backend| restore_cookie_records(&backend, &records));
+        let _ = tx.send(result);
+      })?;
+      rx.recv()?
+    });
+    let restored = match result {
+      Ok(count) => {
+        info!("[session] {label} replayed {count} cookies, verifying");
+        std::thread::sleep(RESTORE_VERIFY_DELAY);
+        verify_restore(&app, &label)
+      }
+      Err(error) => {
+        warn!("[session] {label} restore failed: {error:#}");
+        false
+      }
+    };
+    finish_restore(&app, &label, restored);
+  });
+}
+
+#[cfg(target_os = "windows")]
+fn verify_restore(app: &tauri::AppHandle, label: &str) -> bool {
+  let Some(window) = app.get_webview_window(label) else {
+    return false;
+  };
+  let domains = load_config(app)
+    .map(|config| config.cookie_persistence)
+    .unwrap_or_default()
+    .domains;
+  let (tx, rx) = std::sync::mpsc::channel();
+  if window
+    .with_webview(move |webview| {
+      let count = WebView2Backend::new(&webview)
+        .and_then(|backend| read_domain_cookie_records(&backend, &domains))
+        .map(|records| records.len());
+      let _ = tx.send(count);
+    })
+    .is_err()
+  {
+    return false;
+  }
+  let count = match rx.recv() {
+    Ok(Ok(count)) => count,
+    _ => return false,
+  };
+  let last_good = load_stats(app, label).last_good_cookie_count;
+  let on_login_view = app
+    .try_state::<SessionGuardState>()
+    .and_then(|state| {
+      state.windows.lock().ok().map(|windows| {
+        windows
+          .get(label)
+          .is_some_and(|session| session.on_login_view)
+      })
+    })
+    .unwrap_or(false);
+  count > 0 && !cookies_collapsed(last_good, count) && !on_login_view
+}
+
+#[cfg(target_os = "windows")]
+fn finish_restore(app: &tauri::AppHandle, label: &str, restored: bool) {
+  if let Some(state) = app.try_state::<SessionGuardState>() {
+    if let Ok(mut windows) = state.windows.lock() {
+      windows
+        .entry(label.to_string())
+        .or_default()
+        .finish_restore(restored);
+    }
+  }
+  let _ = update_stats(app, label, |stats| {
+    if restored {
+      stats.restores_succeeded += 1;
+    } else {
+      stats.restores_failed += 1;
+    }
+  });
+  if restored {
+    info!("[session] {label} session restored");
+    return;
+  }
+  warn!("[session] {label} session could not be restored");
+  app
+    .dialog()
+    .message("LINE からログアウトされました。ログイン情報を自動で復元できなかったため、もう一度ログインしてください。")
+    .title("ログイン状態")
+    .show(|_| {});
+}
+
+#[cfg(target_os = "windows")]
+fn has_snapshot(app: &tauri::AppHandle, label: &str) -> bool {
+  app_paths(app)
+    .map(|paths| {
+      paths
+        .session_snapshot(&profile_of_window(app, label))
+        .is_file()
+    })
+    .unwrap_or(false)
+}
+
+#[cfg(target_os = "windows")]
+fn save_snapshot(app: &tauri::AppHandle, label: &str, records: &[CookieRecord]) -> Result<()> {
+  let path = app_paths(app)?.session_snapshot(&profile_of_window(app, label));
+  write_atomic(&path, &dpapi(&serde_json::to_vec(records)?, true)?)
+}
+
+#[cfg(target_os = "windows")]
+fn load_snapshot(app: &tauri::AppHandle, label: &str) -> Result<Vec<CookieRecord>> {
+  let path = app_paths(app)?.session_snapshot(&profile_of_window(app, label));
+  let sealed = fs::read(&path).map_err(|error| match error.kind() {
+    ErrorKind::NotFound => anyhow::anyhow!("no session snapshot yet"),
+    _ => error.into(),
+  })?;
+  Ok(serde_json::from_slice(&dpapi(&sealed, false)?)?)
+}
+
+// Snapshots hold live session tokens, so they are bound to the Windows user like the profile.
+#[cfg(target_os = "windows")]
+fn dpapi(data: &[u8], protect: bool) -> Result<Vec<u8>> {
+  use windows::core::PCWSTR;
+  use windows::Win32::Foundation::{LocalFree, HLOCAL};
+  use windows::Win32::Security::Cryptography::{
+    CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+  };
+
+  let input = CRYPT_INTEGER_BLOB {
+    cbData: data.len() as u32,
+    pbData: data.as_ptr() as *mut u8,
+  };
+  let mut output = CRYPT_INTEGER_BLOB::default();
+  unsafe {
+    if protect {
+      CryptProtectData(
+        &input,
+        PCWSTR::null(),
+        None,
+        None,
+        None,
+        CRYPTPROTECT_UI_FORBIDDEN,
+        &mut output,
+      )?;
+    } else {
+      CryptUnprotectData(
+        &input,
+        None,
+        None,
+        None,
+        None,
+        CRYPTPROTECT_UI_FORBIDDEN,
+        &mut output,
+      )?;
+    }
+    let bytes = std::slice::from_raw_parts(output.pbData, output.cbData as usize).to_vec();
+    let _ = LocalFree(Some(HLOCAL(output.pbData as _)));
+    Ok(bytes)
+  }
+}
+
+fn load_stats(app: &tauri::AppHandle, label: &str) -> SessionStats {
+  let Ok(paths) = app_paths(app) else {
+    return SessionStats::default();
+  };
+  fs::read_to_string(paths.session_stats(&profile_of_window(app, label)))
+    .ok()
+    .and_then(|raw| serde_json::from_str(&raw).ok())
+    .unwrap_or_default()
+}
+
+fn update_stats(
+  app: &tauri::AppHandle,
+  label: &str,
+  update: impl FnOnce(&mut SessionStats),
+) -> Result<SessionStats> {
+  let state = app.try_state::<SessionGuardState>();
+  let _guard = state.as_ref().map(|state| {
+    state
+      .stats
+      .lock()
+      .unwrap_or_else(|error| error.into_inner())
+  });
+  let mut stats = load_stats(app, label);
+  update(&mut stats);
+  let path = app_paths(app)?.session_stats(&profile_of_window(app, label));
+  write_atomic(&path, &serde_json::to_vec_pretty(&stats)?)?;
+  Ok(stats)
+}
+
+fn unix_now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_secs()
+}
+
+/// Called by the injected route watcher whenever the extension page changes its URL.
+#[tauri::command]
+pub(crate) fn report_session_route(
+  app_handle: tauri::AppHandle,
+  window: tauri::WebviewWindow,
+  url: String,
+) {
+  let label = window.label();
+  // Popups have their own pages; only the LINE window itself shows the login view.
+  if label != "main" && !is_profile_window(label) {
+    return;
+  }
+  let Some(state) = app_handle.try_state::<SessionGuardState>() else {
+    return;
+  };
+  let config = load_config(&app_handle)
+    .map(|config| config.cookie_persistence)
+    .unwrap_or_default();
+  let login_view = is_login_view(&url, &config.login_view_patterns);
+  let last_good = load_stats(&app_handle, label).last_good_cookie_count;
+  let detected = state
+    .windows
+    .lock()
+    .unwrap_or_else(|error| error.into_inner())
+    .entry(label.to_string())
+    .or_default()
+    .route_changed(login_view, last_good);
+  if detected {
+    handle_logout(&app_handle, label, LogoutSignal::LoginView, &config);
+  }
+}
+
+#[tauri::command]
+pub(crate) fn get_session_stats(
+  app_handle: tauri::AppHandle,
+  window: tauri::WebviewWindow,
+) -> SessionStats {
+  let label = if is_profile_window(window.label()) {
+    window.label()
+  } else {
+    "main"
+  };
+  load_stats(&app_handle, label)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::browser::fake::FakeBackend;
+  use crate::cookie_jar::{CookieRecord, CookieSameSite};
+  use crate::extensions::read_domain_cookie_records;
+
+  fn cookie(name: &str, domain: &str) -> CookieRecord {
+    CookieRecord {
+      name: name.to_string(),
+      value: format!("{name}-value"),
+      domain: domain.to_string(),
+      path: "/".to_string(),
+      http_only: true,
+      secure: true,
+      same_site: CookieSameSite::Lax,
+      expires: None,
+    }
+  }
+
+  fn logged_in() -> Vec<CookieRecord> {
+    vec![
+      cookie("ses", ".line.me"),
+      cookie("tok", "access.line.me"),
+      cookie("api", "api.line.me"),
+      cookie("lang", ".line.me"),
+    ]
+  }
+
+  // Counts what a persistence run would see for the default LINE domains.
+  fn count(backend: &FakeBackend) -> usize {
+    let domains = CookiePersistenceConfig::default().domains;
+    read_domain_cookie_records(backend, &domains).unwrap().len()
+  }
+
+  #[test]
+  fn collapse_needs_a_baseline_and_a_halving() {
+    assert!(!cookies_collapsed(0, 0));
+    assert!(!cookies_collapsed(MIN_BASELINE_COOKIES - 1, 0));
+    assert!(cookies_collapsed(MIN_BASELINE_COOKIES, 0));
+    assert!(!cookies_collapsed(10, 5));
+    assert!(cookies_collapsed(10, 4));
+  }
+
+  #[test]
+  fn login_view_matches_non_empty_patterns() {
+    let patterns = vec!["#/login".to_string(), String::new()];
+    assert!(is_login_view(
+      "chrome-extension://id/index.html#/login",
+      &patterns
+    ));
+    assert!(!is_login_view(
+      "chrome-extension://id/index.html#/chats",
+      &patterns
+    ));
+    assert!(!is_login_view("anything", &[]));
+  }
+
+  #[test]
+  fn collapsed_cookies_log_out_once_until_restored() {
+    let backend = FakeBackend::with_cookies(logged_in());
+    let mut session = WindowSession::default();
+    let last_good = count(&backend);
+    assert_eq!(last_good, 4);
+    assert_eq!(
+      session.check_cookies(0, last_good),
+      CheckOutcome::SaveSnapshot
+    );
+
+    backend.cookies.borrow_mut().truncate(1);
+    assert_eq!(
+      session.check_cookies(last_good, count(&backend)),
+      CheckOutcome::Logout(LogoutSignal::CookiesCollapsed)
+    );
+    assert!(session.logged_out);
+    // One logout episode triggers one restore.
+    assert_eq!(
+      session.check_cookies(last_good, count(&backend)),
+      CheckOutcome::Ignore
+    );
+
+    session.restoring = true;
+    assert_eq!(
+      session.check_cookies(last_good, count(&backend)),
+      CheckOutcome::Skip
+    );
+    *backend.cookies.borrow_mut() = logged_in();
+    session.finish_restore(true);
+    assert!(!session.restoring && !session.logged_out);
+    assert_eq!(
+      session.check_cookies(last_good, count(&backend)),
+      CheckOutcome::SaveSnapshot
+    );
+  }
+
+  #[test]
+  fn failed_restore_stays_logged_out_until_cookies_return() {
+    let backend = FakeBackend::with_cookies(Vec::new());
+    let mut session = WindowSession {
+      restoring: true,
+      ..WindowSession::default()
+    };
+    session.finish_restore(false);
+    assert!(session.logged_out);
+    assert_eq!(
+      session.check_cookies(4, count(&backend)),
+      CheckOutcome::Ignore
+    );
+
+    // The user logged in again by hand.
+    *backend.cookies.borrow_mut() = logged_in();
+    assert_eq!(
+      session.check_cookies(4, count(&backend)),
+      CheckOutcome::SaveSnapshot
+    );
+    assert!(!session.logged_out);
+  }
+
+  #[test]
+  fn login_view_logs_out_and_blocks_snapshots() {
+    let backend = FakeBackend::with_cookies(logged_in());
+    let mut session = WindowSession::default();
+    assert!(session.route_changed(true, 4));
+    assert!(session.logged_out);
+    // Staying on the login view is the same episode.
+    assert!(!session.route_changed(true, 4));
+    assert_eq!(
+      session.check_cookies(4, count(&backend)),
+      CheckOutcome::Ignore
+    );
+    assert!(session.logged_out);
+
+    assert!(!session.route_changed(false, 4));
+    assert_eq!(
+      session.check_cookies(4, count(&backend)),
+      CheckOutcome::SaveSnapshot
+    );
+    assert!(!session.logged_out);
+  }
+
+  #[test]
+  fn login_view_during_a_restore_is_not_a_new_logout() {
+    let mut session = WindowSession {
+      restoring: true,
+      ..WindowSession::default()
+    };
+    assert!(!session.route_changed(true, 4));
+    assert!(session.on_login_view && !session.logged_out);
+  }
+
+  #[test]
+  fn login_view_without_a_baseline_is_not_a_logout() {
+    let backend = FakeBackend::with_cookies(Vec::new());
+    let mut session = WindowSession::default();
+    // A fresh profile opens on the login view before it ever had a session.
+    assert!(!session.route_changed(true, 0));
+    assert!(!session.route_changed(false, MIN_BASELINE_COOKIES - 1));
+    assert!(!session.route_changed(true, MIN_BASELINE_COOKIES - 1));
+    assert!(!session.logged_out);
+
+    *backend.cookies.borrow_mut() = logged_in();
+    assert!(!session.route_changed(false, 0));
+    assert_eq!(
+      session.check_cookies(0, count(&backend)),
+      CheckOutcome::SaveSnapshot
+    );
+  }
+}
@@ -1,65 +1,362 @@
+use crate::atomic_write::{sibling_path, write_atomic};
+use crate::logger::LogLevel;
+use crate::paths::settings_file_path;
+use crate::policy::current_policy;
 use anyhow::{anyhow, Result};
+use log::warn;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::fs;
 use std::io::ErrorKind;
-use std::path::PathBuf;
-use tauri::Manager;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+/// Bump this and append a step to `MIGRATIONS` whenever a field is renamed or changes type.
+pub(crate) const SETTINGS_SCHEMA_VERSION: u32 = 1;
+
+type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+/// `MIGRATIONS[n]` upgrades a version `n` document to version `n + 1`.
+const MIGRATIONS: [Migration; SETTINGS_SCHEMA_VERSION as usize] = [migrate_v0_to_v1];
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(default, rename_all = "camelCase")]
 pub(crate) struct AppSettings {
+  pub(crate) schema_version: u32,
   pub(crate) auto_start: bool,
   pub(crate) start_minimized: bool,
   pub(crate) content_protection: bool,
-  pub(crate) log_level: String,
+  pub(crate) log_level: LogLevel,
 }
 
 impl Default for AppSettings {
   fn default() -> Self {
     Self {
+      schema_version: SETTINGS_SCHEMA_VERSION,
       auto_start: false,
       start_minimized: false,
       content_protection: true,
-      log_level: crate::logger::DEFAULT_LOG_LEVEL.to_string(),
+      log_level: LogLevel::default(),
     }
   }
 }
 
+/// Describes how an unreadable `settings.json` was recovered during load.
+pub(crate) struct SettingsRecovery {
+  pub(crate) corrupt_path: PathBuf,
+  pub(crate) restored_from_backup: bool,
+}
+
 pub(crate) fn load_settings(app: &tauri::AppHandle) -> Result<AppSettings> {
+  load_settings_with_recovery(app).map(|(settings, _)| settings)
+}
+
+/// Loads the persisted settings with the organization policy merged in as mandatory values.
+pub(crate) fn load_settings_with_recovery(
+  app: &tauri::AppHandle,
+) -> Result<(AppSettings, Option<SettingsRecovery>)> {
+  let (mut settings, recovery) = read_or_recover_settings(app)?;
+  current_policy(app).enforce(&mut settings);
+  Ok((settings, recovery))
+}
+
+fn read_or_recover_settings(
+  app: &tauri::AppHandle,
+) -> Result<(AppSettings, Option<SettingsRecovery>)> {
   let path = settings_path(app)?;
   let raw = match fs::read_to_string(&path) {
     Ok(raw) => raw,
-    Err(error) if error.kind() == ErrorKind::NotFound => return Ok(AppSettings::default()),
+    Err(error) if error.kind() == ErrorKind::NotFound => return Ok((AppSettings::default(), None)),
     Err(error) => return Err(error.into()),
   };
-  let parsed = serde_json::from_str(&raw);
-  Ok(parsed.unwrap_or_default())
+  match parse_settings(&raw) {
+    Ok(settings) => Ok((settings, None)),
+    Err(error) => {
+      warn!("[settings] parse failed: {error:#}");
+      recover_settings(&path)
+    }
+  }
 }
 
+/// Refuses to overwrite a file written by a newer release, whose keys this one only partly reads.
 pub(crate) fn save_settings(app: &tauri::AppHandle, settings: &AppSettings) -> Result<()> {
   let path = settings_path(app)?;
-  if let Some(parent) = path.parent() {
-    fs::create_dir_all(parent)?;
-  }
+  check_writable(&path)?;
+  rotate_backup(&path)?;
   let payload = serde_json::to_string_pretty(settings)?;
-  fs::write(path, payload)?;
+  write_atomic(&path, payload.as_bytes())?;
   Ok(())
 }
 
-pub(crate) fn update_content_protection(
-  app: &tauri::AppHandle,
-  enabled: bool,
-) -> Result<AppSettings> {
-  let mut settings = load_settings(app).unwrap_or_default();
-  settings.content_protection = enabled;
-  save_settings(app, &settings)?;
-  Ok(settings)
-}
-
-fn settings_path(app: &tauri::AppHandle) -> Result<PathBuf> {
-  let dir = app
-    .path()
-    .app_data_dir()
-    .map_err(|error| anyhow!("settings dir error: {error}"))?;
-  Ok(dir.join("settings.json"))
+/// Reads and validates `path` without any corruption recovery, for callers that must not
+/// touch the file when it is invalid.
+pub(crate) fn read_settings_file(path: &Path) -> Result<AppSettings> {
+  let raw = fs::read_to_string(path)?;
+  parse_settings(&raw)
+}
+
+fn parse_settings(raw: &str) -> Result<AppSettings> {
+  settings_from_value(serde_json::from_str(raw)?)
+}
+
+/// Builds settings from a JSON document, migrating it first when it is older than the
+/// current schema. A document without `schemaVersion` is treated as v0.
+pub(crate) fn settings_from_value(value: Value) -> Result<AppSettings> {
+  let document = migrate_settings_document(value)?;
+  Ok(serde_json::from_value(Value::Object(document))?)
+}
+
+/// Upgrades a settings document to the current schema without filling in defaults, so
+/// callers can still tell which keys it actually contained.
+pub(crate) fn migrate_settings_document(value: Value) -> Result<Map<String, Value>> {
+  let Value::Object(mut document) = value else {
+    return Err(anyhow!("settings root is not an object"));
+  };
+  let version = match document.get("schemaVersion") {
+    None => 0,
+    Some(value) => value
+      .as_u64()
+      .and_then(|version| u32::try_from(version).ok())
+      .ok_or_else(|| anyhow!("invalid schemaVersion: {value}"))?,
+  };
+  if version > SETTINGS_SCHEMA_VERSION {
+    warn!(
+      "[settings] schema v{version} is newer than v{SETTINGS_SCHEMA_VERSION}; reading known keys"
+    );
+    return known_keys_only(document);
+  }
+  for (from, migrate) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+    migrate(&mut document).map_err(|error| anyhow!("migration v{from} failed: {error:#}"))?;
+    document.insert("schemaVersion".to_string(), Value::from(from as u32 + 1));
+  }
+  Ok(document)
+}
+
+// A newer release may add keys or change what a key holds. Each key this version knows is kept
+// only when its value still reads as this version expects; the rest of the file is left alone.
+fn known_keys_only(document: Map<String, Value>) -> Result<Map<String, Value>> {
+  let Value::Object(defaults) = serde_json::to_value(AppSettings::default())? else {
+    return Err(anyhow!("settings root is not an object"));
+  };
+  let mut known = Map::new();
+  for (key, value) in document {
+    if key == "schemaVersion" || !defaults.contains_key(&key) {
+      continue;
+    }
+    let mut candidate = defaults.clone();
+    candidate.insert(key.clone(), value.clone());
+    if serde_json::from_value::<AppSettings>(Value::Object(candidate)).is_ok() {
+      known.insert(key, value);
+    }
+  }
+  known.insert(
+    "schemaVersion".to_string(),
+    Value::from(SETTINGS_SCHEMA_VERSION),
+  );
+  Ok(known)
+}
+
+/// Fails when `settings.json` comes from a newer release, so callers can refuse a change before
+/// applying any of it.
+pub(crate) fn ensure_settings_writable(app: &tauri::AppHandle) -> Result<()> {
+  check_writable(&settings_path(app)?)
+}
+
+fn check_writable(path: &Path) -> Result<()> {
+  match newer_schema_on_disk(path) {
+    Some(version) => Err(anyhow!(
+      "settings.json has schema v{version} from a newer version; not overwriting it"
+    )),
+    None => Ok(()),
+  }
+}
+
+fn newer_schema_on_disk(path: &Path) -> Option<u64> {
+  let raw = fs::read_to_string(path).ok()?;
+  let value: Value = serde_json::from_str(&raw).ok()?;
+  let version = value.get("schemaVersion")?.as_u64()?;
+  (version > u64::from(SETTINGS_SCHEMA_VERSION)).then_some(version)
+}
+
+// v0 was unversioned and stored `logLevel` as a free-form string ("warning", "trace", ...).
+fn migrate_v0_to_v1(document: &mut Map<String, Value>) -> Result<()> {
+  let normalized = document
+    .get("logLevel")
+    .and_then(Value::as_str)
+    .and_then(LogLevel::from_str);
+  match normalized {
+    Some(level) => {
+      document.insert("logLevel".to_string(), Value::from(level.as_str()));
+    }
+    None => {
+      document.remove("logLevel");
+    }
+  }
+  Ok(())
+}
+
+fn recover_settings(path: &Path) -> Result<(AppSettings, Option<SettingsRecovery>)> {
+  let corrupt_path = quarantine_corrupt(path)?;
+  warn!(
+    "[settings] corrupted settings kept at {}",
+    corrupt_path.display()
+  );
+
+  let backup_path = sibling_path(path, "bak")?;
+  let backup = fs::read_to_string(&backup_path)
+    .map_err(anyhow::Error::from)
+    .and_then(|raw| parse_settings(&raw).map(|settings| (raw, settings)));
+  match backup {
+    Ok((raw, settings)) => {
+      write_atomic(path, raw.as_bytes())?;
+      warn!("[settings] restored from backup {}", backup_path.display());
+      Ok((
+        settings,
+        Some(SettingsRecovery {
+          corrupt_path,
+          restored_from_backup: true,
+        }),
+      ))
+    }
+    Err(error) => {
+      warn!("[settings] backup unavailable, using defaults: {error:#}");
+      Ok((
+        AppSettings::default(),
+        Some(SettingsRecovery {
+          corrupt_path,
+          restored_from_backup: false,
+        }),
+      ))
+    }
+  }
+}
+
+fn quarantine_corrupt(path: &Path) -> Result<PathBuf> {
+  let timestamp = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_secs();
+  let corrupt_path = sibling_path(path, &format!("corrupt-{timestamp}"))?;
+  fs::rename(path, &corrupt_path)?;
+  Ok(corrupt_path)
+}
+
+// Only a file that still parses is worth keeping as the last known good copy.
+fn rotate_backup(path: &Path) -> Result<()> {
+  let raw = match fs::read_to_string(path) {
+    Ok(raw) => raw,
+    Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+    Err(error) => return Err(error.into()),
+  };
+  if parse_settings(&raw).is_ok() {
+    write_atomic(&sibling_path(path, "bak")?, raw.as_bytes())?;
+  }
+  Ok(())
+}
+
+pub(crate) fn settings_path(app: &tauri::AppHandle) -> Result<PathBuf> {
+  settings_file_path(app)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn v0_empty_object_uses_defaults() {
+    let settings = parse_settings("{}").unwrap();
+    assert_eq!(settings.schema_version, SETTINGS_SCHEMA_VERSION);
+    assert!(settings.content_protection);
+    assert!(!settings.auto_start);
+    assert!(!settings.start_minimized);
+    assert_eq!(settings.log_level, LogLevel::Info);
+  }
+
+  #[test]
+  fn v0_full_document_keeps_values() {
+    let raw = r#"{
+      "autoStart": true,
+      "startMinimized": true,
+      "contentProtection": false,
+      "logLevel": "debug"
+    }"#;
+    let settings = parse_settings(raw).unwrap();
+    assert_eq!(settings.schema_version, SETTINGS_SCHEMA_VERSION);
+    assert!(settings.auto_start);
+    assert!(settings.start_minimized);
+    assert!(!settings.content_protection);
+    assert_eq!(settings.log_level, LogLevel::Debug);
+  }
+
+  #[test]
+  fn v0_log_level_aliases_are_normalized() {
+    let cases = [
+      (" Warning ", LogLevel::Warn),
+      ("TRACE", LogLevel::Verbose),
+      ("verbose", LogLevel::Verbose),
+      ("Error", LogLevel::Error),
+    ];
+    for (raw_level, expected) in cases {
+      let raw = format!(r#"{{"logLevel": "{raw_level}"}}"#);
+      assert_eq!(parse_settings(&raw).unwrap().log_level, expected);
+    }
+  }
+
+  #[test]
+  fn v0_unknown_log_level_falls_back_to_default() {
+    let settings = parse_settings(r#"{"logLevel": "loud", "autoStart": true}"#).unwrap();
+    assert_eq!(settings.log_level, LogLevel::Info);
+    assert!(settings.auto_start);
+  }
+
+  #[test]
+  fn v0_non_string_log_level_falls_back_to_default() {
+    let settings = parse_settings(r#"{"logLevel": 3}"#).unwrap();
+    assert_eq!(settings.log_level, LogLevel::Info);
+  }
+
+  #[test]
+  fn v1_round_trips() {
+    let settings = AppSettings {
+      auto_start: true,
+      log_level: LogLevel::Warn,
+      ..AppSettings::default()
+    };
+    let raw = serde_json::to_string(&settings).unwrap();
+    assert!(raw.contains(r#""schemaVersion":1"#));
+    assert!(raw.contains(r#""logLevel":"warn""#));
+    let parsed = parse_settings(&raw).unwrap();
+    assert!(parsed.auto_start);
+    assert_eq!(parsed.log_level, LogLevel::Warn);
+  }
+
+  #[test]
+  fn v1_rejects_legacy_log_level_spelling() {
+    assert!(parse_settings(r#"{"schemaVersion": 1, "logLevel": "warning"}"#).is_err());
+  }
+
+  #[test]
+  fn newer_schema_reads_known_keys() {
+    let raw = format!(
+      r#"{{
+        "schemaVersion": {},
+        "autoStart": true,
+        "logLevel": {{"file": "debug"}},
+        "futureKey": [1, 2]
+      }}"#,
+      SETTINGS_SCHEMA_VERSION + 1
+    );
+    let settings = parse_settings(&raw).unwrap();
+    assert_eq!(settings.schema_version, SETTINGS_SCHEMA_VERSION);
+    assert!(settings.auto_start);
+    // A value whose shape changed falls back to the default instead of failing the file.
+    assert_eq!(settings.log_level, LogLevel::Info);
+  }
+
+  #[test]
+  fn invalid_documents_are_rejected() {
+    assert!(parse_settings("[]").is_err());
+    assert!(parse_settings(r#"{"schemaVersion": "1"}"#).is_err());
+    assert!(parse_settings("{").is_err());
+  }
 }
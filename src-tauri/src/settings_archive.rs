@@ -0,0 +1,577 @@
+use crate::atomic_write::{sibling_path, write_atomic};
+use crate::crx::ensure_clean_dir;
+use crate::paths::{user_config_path, user_extensions_dir};
+use crate::policy::current_policy;
+use crate::settings::{
+  migrate_settings_document, settings_from_value, AppSettings, SETTINGS_SCHEMA_VERSION,
+};
+use crate::settings_service::{current_settings, modify_settings, saved_settings};
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri_plugin_dialog::{
+  DialogExt, MessageDialogButtons, MessageDialogKind, MessageDialogResult,
+};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const ARCHIVE_FORMAT_VERSION: u32 = 1;
+const MANIFEST_ENTRY: &str = "manifest.json";
+const SETTINGS_ENTRY: &str = "settings.json";
+// The per-user config override, which is where patch rule overrides live.
+const USER_CONFIG_ENTRY: &str = "user-config.json";
+const USER_EXTENSIONS_PREFIX: &str = "extensions/user/";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ArchiveContent {
+  Settings,
+  UserConfig,
+  UserExtensions,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchiveManifest {
+  format_version: u32,
+  app_version: String,
+  settings_schema_version: u32,
+  created_at: u64,
+  contents: Vec<ArchiveContent>,
+  #[serde(default)]
+  user_extensions: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ImportMode {
+  /// Keeps local values and extensions that the archive does not mention.
+  Merge,
+  /// Makes local state match the archive exactly.
+  Replace,
+}
+
+// What an import does to the per-user config file.
+enum UserConfigChange {
+  Remove,
+  Write(Value),
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ImportReport {
+  settings: bool,
+  user_config: bool,
+  user_extensions: Vec<String>,
+  restart_required: bool,
+}
+
+pub(crate) fn export_archive(app: &tauri::AppHandle, out_path: &Path) -> Result<()> {
+  let file = fs::File::create(out_path)?;
+  let mut zip = ZipWriter::new(file);
+  let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+  let mut contents = Vec::new();
+
+  let settings = saved_settings(app);
+  zip.start_file(SETTINGS_ENTRY, options)?;
+  zip.write_all(serde_json::to_string_pretty(&settings)?.as_bytes())?;
+  contents.push(ArchiveContent::Settings);
+
+  let user_config = user_config_path(app)?;
+  if user_config.is_file() {
+    zip.start_file(USER_CONFIG_ENTRY, options)?;
+    zip.write_all(&fs::read(&user_config)?)?;
+    contents.push(ArchiveContent::UserConfig);
+  }
+
+  let user_dir = user_extensions_dir(app)?;
+  let mut user_extensions = Vec::new();
+  if user_dir.is_dir() {
+    for entry in fs::read_dir(&user_dir)? {
+      let path = entry?.path();
+      let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        continue;
+      };
+      if !path.is_dir() {
+        continue;
+      }
+      add_dir_to_zip(
+        &mut zip,
+        &path,
+        &format!("{USER_EXTENSIONS_PREFIX}{name}"),
+        options,
+      )?;
+      user_extensions.push(name.to_string());
+    }
+  }
+  if !user_extensions.is_empty() {
+    contents.push(ArchiveContent::UserExtensions);
+  }
+
+  let manifest = ArchiveManifest {
+    format_version: ARCHIVE_FORMAT_VERSION,
+    app_version: app.package_info().version.to_string(),
+    settings_schema_version: SETTINGS_SCHEMA_VERSION,
+    created_at: SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_secs(),
+    contents,
+    user_extensions,
+  };
+  zip.start_file(MANIFEST_ENTRY, options)?;
+  zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+  zip.finish()?;
+  info!("[archive] exported {}", out_path.display());
+  Ok(())
+}
+
+pub(crate) fn import_archive(
+  app: &tauri::AppHandle,
+  archive_path: &Path,
+  mode: ImportMode,
+) -> Result<ImportReport> {
+  let mut archive = ZipArchive::new(fs::File::open(archive_path)?)?;
+  let manifest: ArchiveManifest = serde_json::from_str(&read_entry(&mut archive, MANIFEST_ENTRY)?)?;
+  if manifest.format_version > ARCHIVE_FORMAT_VERSION {
+    return Err(anyhow!(
+      "archive format v{} is newer than supported v{ARCHIVE_FORMAT_VERSION}",
+      manifest.format_version
+    ));
+  }
+  if manifest.settings_schema_version > SETTINGS_SCHEMA_VERSION {
+    return Err(anyhow!(
+      "archive settings schema v{} from {} is newer than supported v{SETTINGS_SCHEMA_VERSION}",
+      manifest.settings_schema_version,
+      manifest.app_version
+    ));
+  }
+  info!(
+    "[archive] import {} from {} mode={mode:?}",
+    archive_path.display(),
+    manifest.app_version
+  );
+
+  // Everything is read, checked and staged before local state changes.
+  let imported_settings = if manifest.contents.contains(&ArchiveContent::Settings) {
+    let raw: Value = serde_json::from_str(&read_entry(&mut archive, SETTINGS_ENTRY)?)?;
+    let imported = migrate_settings_document(raw)?;
+    merge_settings(&current_settings(app), imported.clone(), mode)?;
+    Some(imported)
+  } else {
+    None
+  };
+  let imported_config = if manifest.contents.contains(&ArchiveContent::UserConfig) {
+    let raw: Value = serde_json::from_str(&read_entry(&mut archive, USER_CONFIG_ENTRY)?)?;
+    if !raw.is_object() {
+      return Err(anyhow!("{USER_CONFIG_ENTRY} is not a JSON object"));
+    }
+    Some(raw)
+  } else {
+    None
+  };
+
+  let user_config = user_config_path(app)?;
+  let config_change = plan_user_config(&user_config, imported_config, mode)?;
+
+  // Replace also clears local extensions when the archive has none.
+  let import_extensions = manifest.contents.contains(&ArchiveContent::UserExtensions)
+    || matches!(mode, ImportMode::Replace);
+  let user_dir = user_extensions_dir(app)?;
+  let staging = sibling_path(&user_dir, "import")?;
+  if import_extensions {
+    for name in &manifest.user_extensions {
+      validate_extension_name(name)?;
+    }
+    if let Err(error) = stage_user_extensions(&mut archive, &manifest.user_extensions, &staging) {
+      let _ = fs::remove_dir_all(&staging);
+      return Err(error);
+    }
+  }
+
+  let mut report = ImportReport::default();
+  if let Some(change) = config_change {
+    match change {
+      UserConfigChange::Remove => fs::remove_file(&user_config)?,
+      UserConfigChange::Write(document) => write_atomic(
+        &user_config,
+        serde_json::to_string_pretty(&document)?.as_bytes(),
+      )?,
+    }
+    report.user_config = true;
+    report.restart_required = true;
+  }
+  if import_extensions {
+    swap_user_extensions(&staging, &user_dir, &manifest.user_extensions, mode)?;
+    report.user_extensions = manifest.user_extensions.clone();
+    report.restart_required = true;
+  }
+
+  if let Some(imported) = imported_settings {
+    let policy = current_policy(app);
+    // Merged again onto the copy the service hands out, so a change made while the
+    // archive was being extracted is not overwritten with the earlier snapshot.
+    modify_settings(app, move |settings| {
+      if let Ok(mut merged) = merge_settings(settings, imported, mode) {
+        policy.enforce(&mut merged);
+        *settings = merged;
+      }
+    })?;
+    report.settings = true;
+  }
+
+  Ok(report)
+}
+
+fn merge_settings(
+  current: &AppSettings,
+  imported: Map<String, Value>,
+  mode: ImportMode,
+) -> Result<AppSettings> {
+  let document = match mode {
+    ImportMode::Replace => imported,
+    ImportMode::Merge => {
+      let Value::Object(mut current) = serde_json::to_value(current)? else {
+        return Err(anyhow!("settings root is not an object"));
+      };
+      current.extend(imported);
+      current
+    }
+  };
+  settings_from_value(Value::Object(document))
+}
+
+fn plan_user_config(
+  path: &Path,
+  imported: Option<Value>,
+  mode: ImportMode,
+) -> Result<Option<UserConfigChange>> {
+  let document = match (mode, imported) {
+    (ImportMode::Replace, None) => return Ok(path.is_file().then_some(UserConfigChange::Remove)),
+    (ImportMode::Merge, None) => return Ok(None),
+    (ImportMode::Replace, Some(imported)) => imported,
+    (ImportMode::Merge, Some(imported)) => match fs::read_to_string(path) {
+      Ok(raw) => match (serde_json::from_str::<Value>(&raw)?, imported) {
+        (Value::Object(mut current), Value::Object(incoming)) => {
+          current.extend(incoming);
+          Value::Object(current)
+        }
+        (_, incoming) => incoming,
+      },
+      Err(_) => imported,
+    },
+  };
+  Ok(Some(UserConfigChange::Write(document)))
+}
+
+// Names become directories under the user extensions dir, so anything that is not a single
+// plain path component is refused.
+fn validate_extension_name(name: &str) -> Result<()> {
+  if name.is_empty() || name.contains(['/', '\\', ':']) || name == "." || name == ".." {
+    return Err(anyhow!("invalid user extension name: {name:?}"));
+  }
+  Ok(())
+}
+
+/// Extracts the listed extensions into `staging`, one directory per name.
+fn stage_user_extensions<R: Read + Seek>(
+  archive: &mut ZipArchive<R>,
+  names: &[String],
+  staging: &Path,
+) -> Result<()> {
+  ensure_clean_dir(staging)?;
+  for name in names {
+    fs::create_dir_all(staging.join(name))?;
+  }
+
+  for index in 0..archive.len() {
+    let mut file = archive.by_index(index)?;
+    let Some(relative) = file.enclosed_name().and_then(|path| {
+      path
+        .strip_prefix(USER_EXTENSIONS_PREFIX)
+        .ok()
+        .map(PathBuf::from)
+    }) else {
+      continue;
+    };
+    let listed = relative
+      .components()
+      .next()
+      .and_then(|component| component.as_os_str().to_str())
+      .is_some_and(|name| names.iter().any(|listed| listed == name));
+    if !listed {
+      continue;
+    }
+    let out_path = staging.join(&relative);
+    if file.is_dir() {
+      fs::create_dir_all(&out_path)?;
+      continue;
+    }
+    if let Some(parent) = out_path.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    let mut out_file = fs::File::create(&out_path)?;
+    std::io::copy(&mut file, &mut out_file)?;
+  }
+  Ok(())
+}
+
+/// Moves the staged extensions into `user_dir`. Replace swaps the whole directory; Merge
+/// only replaces the extensions the archive carries.
+fn swap_user_extensions(
+  staging: &Path,
+  user_dir: &Path,
+  names: &[String],
+  mode: ImportMode,
+) -> Result<()> {
+  match mode {
+    ImportMode::Replace => {
+      if user_dir.exists() {
+        fs::remove_dir_all(user_dir)?;
+      }
+      fs::rename(staging, user_dir)?;
+    }
+    ImportMode::Merge => {
+      fs::create_dir_all(user_dir)?;
+      for name in names {
+        let target = user_dir.join(name);
+        if target.exists() {
+          fs::remove_dir_all(&target)?;
+        }
+        fs::rename(staging.join(name), &target)?;
+      }
+      fs::remove_dir_all(staging)?;
+    }
+  }
+  Ok(())
+}
+
+fn read_entry(archive: &mut ZipArchive<fs::File>, name: &str) -> Result<String> {
+  let mut file = archive
+    .by_name(name)
+    .map_err(|error| anyhow!("{name} missing from archive: {error}"))?;
+  let mut raw = String::new();
+  file.read_to_string(&mut raw)?;
+  Ok(raw)
+}
+
+fn add_dir_to_zip(
+  zip: &mut ZipWriter<fs::File>,
+  dir: &Path,
+  prefix: &str,
+  options: SimpleFileOptions,
+) -> Result<()> {
+  zip.add_directory(format!("{prefix}/"), options)?;
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+      continue;
+    };
+    let entry_name = format!("{prefix}/{name}");
+    if path.is_dir() {
+      add_dir_to_zip(zip, &path, &entry_name, options)?;
+    } else {
+      zip.start_file(entry_name, options)?;
+      zip.write_all(&fs::read(&path)?)?;
+    }
+  }
+  Ok(())
+}
+
+#[tauri::command]
+pub(crate) async fn export_app_data(
+  app_handle: tauri::AppHandle,
+) -> Result<Option<String>, String> {
+  let (tx, mut rx) = tauri::async_runtime::channel(1);
+  app_handle
+    .dialog()
+    .file()
+    .add_filter("refined-line バックアップ", &["zip"])
+    .set_file_name("refined-line-settings.zip")
+    .save_file(move |path| {
+      let _ = tx.try_send(path);
+    });
+  let Some(path) = rx.recv().await.flatten() else {
+    return Ok(None);
+  };
+  let path = path.into_path().map_err(|error| error.to_string())?;
+  if let Err(error) = export_archive(&app_handle, &path) {
+    warn!("[archive] export failed: {error:#}");
+    show_failure(&app_handle, "エクスポートに失敗しました。", &error);
+    return Err(error.to_string());
+  }
+  Ok(Some(path.display().to_string()))
+}
+
+#[tauri::command]
+pub(crate) async fn import_app_data(
+  app_handle: tauri::AppHandle,
+  mode: Option<ImportMode>,
+) -> Result<Option<ImportReport>, String> {
+  let (tx, mut rx) = tauri::async_runtime::channel(1);
+  app_handle
+    .dialog()
+    .file()
+    .add_filter("refined-line バックアップ", &["zip"])
+    .pick_file(move |path| {
+      let _ = tx.try_send(path);
+    });
+  let Some(path) = rx.recv().await.flatten() else {
+    return Ok(None);
+  };
+  let path = path.into_path().map_err(|error| error.to_string())?;
+  let mode = match mode {
+    Some(mode) => mode,
+    None => {
+      let (tx, mut rx) = tauri::async_runtime::channel(1);
+      app_handle
+        .dialog()
+        .message("現在の設定と統合しますか？それとも置き換えますか？")
+        .title("設定のインポート")
+        .buttons(MessageDialogButtons::YesNoCancelCustom(
+          "統合する".into(),
+          "置き換える".into(),
+          "キャンセル".into(),
+        ))
+        .show_with_result(move |result| {
+          let _ = tx.try_send(result);
+        });
+      match rx.recv().await {
+        Some(MessageDialogResult::Yes) => ImportMode::Merge,
+        Some(MessageDialogResult::No) => ImportMode::Replace,
+        _ => return Ok(None),
+      }
+    }
+  };
+
+  let report = match import_archive(&app_handle, &path, mode) {
+    Ok(report) => report,
+    Err(error) => {
+      warn!("[archive] import failed: {error:#}");
+      show_failure(&app_handle, "インポートに失敗しました。", &error);
+      return Err(error.to_string());
+    }
+  };
+  if report.restart_required {
+    let restart_handle = app_handle.clone();
+    app_handle
+      .dialog()
+      .message("インポートしました。拡張機能と構成の変更は再起動後に反映されます。再起動しますか？")
+      .title("インポート完了")
+      .buttons(MessageDialogButtons::YesNo)
+      .show(move |confirmed| {
+        if confirmed {
+          restart_handle.restart();
+        }
+      });
+  }
+  Ok(Some(report))
+}
+
+fn show_failure(app_handle: &tauri::AppHandle, summary: &str, error: &anyhow::Error) {
+  app_handle
+    .dialog()
+    .message(format!("{summary}\n{error:#}"))
+    .title("設定の移行")
+    .kind(MessageDialogKind::Error)
+    .show(|_| {});
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+  use std::io::Cursor;
+
+  fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+      "refined-line-archive-{name}-{}",
+      std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+  }
+
+  fn archive(entries: &[(&str, &str)]) -> ZipArchive<Cursor<Vec<u8>>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, contents) in entries {
+      zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+      zip.write_all(contents.as_bytes()).unwrap();
+    }
+    ZipArchive::new(zip.finish().unwrap()).unwrap()
+  }
+
+  fn imported(value: Value) -> Map<String, Value> {
+    match value {
+      Value::Object(values) => values,
+      _ => unreachable!(),
+    }
+  }
+
+  #[test]
+  fn merge_keeps_local_values_and_replace_does_not() {
+    let current = AppSettings {
+      auto_start: true,
+      ..AppSettings::default()
+    };
+    let incoming = imported(json!({ "startMinimized": true }));
+
+    let merged = merge_settings(&current, incoming.clone(), ImportMode::Merge).unwrap();
+    assert!(merged.auto_start && merged.start_minimized);
+
+    let replaced = merge_settings(&current, incoming, ImportMode::Replace).unwrap();
+    assert!(!replaced.auto_start && replaced.start_minimized);
+
+    let invalid = imported(json!({ "autoStart": "yes" }));
+    assert!(merge_settings(&current, invalid, ImportMode::Merge).is_err());
+  }
+
+  #[test]
+  fn extension_names_must_be_a_single_component() {
+    assert!(validate_extension_name("my-extension").is_ok());
+    for name in ["", ".", "..", "../evil", "a/b", "a\\b", "C:evil"] {
+      assert!(validate_extension_name(name).is_err(), "{name:?}");
+    }
+  }
+
+  #[test]
+  fn staged_extensions_replace_or_merge_into_the_user_dir() {
+    let root = temp_dir("swap");
+    let user_dir = root.join("user");
+    for name in ["kept", "updated"] {
+      fs::create_dir_all(user_dir.join(name)).unwrap();
+      fs::write(user_dir.join(name).join("old"), name).unwrap();
+    }
+    let names = vec!["updated".to_string(), "added".to_string()];
+    let entries = [
+      ("extensions/user/updated/manifest.json", "{}"),
+      ("extensions/user/added/js/main.js", "main"),
+      ("extensions/user/unlisted/manifest.json", "{}"),
+      ("extensions/user/../../escaped", "no"),
+    ];
+
+    let staging = root.join("merge-staging");
+    stage_user_extensions(&mut archive(&entries), &names, &staging).unwrap();
+    swap_user_extensions(&staging, &user_dir, &names, ImportMode::Merge).unwrap();
+    assert!(user_dir.join("kept/old").exists());
+    assert!(!user_dir.join("updated/old").exists());
+    assert!(user_dir.join("updated/manifest.json").exists());
+    assert!(user_dir.join("added/js/main.js").exists());
+    assert!(!user_dir.join("unlisted").exists());
+    assert!(!staging.exists());
+    assert!(!root.join("escaped").exists() && !root.parent().unwrap().join("escaped").exists());
+
+    let staging = root.join("replace-staging");
+    stage_user_extensions(&mut archive(&entries), &names, &staging).unwrap();
+    swap_user_extensions(&staging, &user_dir, &names, ImportMode::Replace).unwrap();
+    assert!(!user_dir.join("kept").exists());
+    assert!(user_dir.join("updated/manifest.json").exists());
+    assert!(user_dir.join("added/js/main.js").exists());
+    let _ = fs::remove_dir_all(&root);
+  }
+}
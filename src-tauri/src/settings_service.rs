@@ -0,0 +1,334 @@
+use crate::app_menu::sync_menu_checks;
+use crate::content_protection::{apply_content_protection_state, is_content_protected};
+use crate::logger::apply_log_level;
+use crate::policy::{current_policy, locked_error};
+use crate::settings::{
+  ensure_settings_writable, load_settings, save_settings, settings_from_value, AppSettings,
+  SETTINGS_SCHEMA_VERSION,
+};
+use crate::tray::{is_tray_enabled, set_tray_enabled};
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use serde_json::{Map, Value};
+use std::sync::{mpsc, Mutex};
+use tauri::{Emitter, Manager};
+use tauri_plugin_autostart::ManagerExt;
+
+pub(crate) const SETTINGS_CHANGED_EVENT: &str = "settings-changed";
+
+/// The user's own settings and the overrides the running app applies on top of them.
+pub(crate) struct SettingsServiceState {
+  /// Last settings that were saved, or loaded at startup.
+  saved: Mutex<AppSettings>,
+  /// Values from env and the CLI. Each one holds for this run until the
+  /// user changes that setting, and none of them is persisted.
+  overrides: Mutex<Map<String, Value>>,
+  // Held from reading the running settings until the result is saved, so two changes
+  // never start from the same copy and silently drop one another.
+  update: Mutex<()>,
+  // Held while the autostart registry entry is brought in line with the settings.
+  autostart: Mutex<()>,
+}
+
+pub(crate) fn init_settings_service(
+  app: &tauri::AppHandle,
+  saved: &AppSettings,
+  overrides: Map<String, Value>,
+) {
+  app.manage(SettingsServiceState {
+    saved: Mutex::new(saved.clone()),
+    overrides: Mutex::new(overrides),
+    update: Mutex::new(()),
+    autostart: Mutex::new(()),
+  });
+}
+
+/// Settings the running app uses: the saved ones with this run's overrides applied.
+pub(crate) fn current_settings(app: &tauri::AppHandle) -> AppSettings {
+  with_overrides(&saved_settings(app), &current_overrides(app))
+}
+
+/// Settings as the user chose them, without this run's overrides.
+pub(crate) fn saved_settings(app: &tauri::AppHandle) -> AppSettings {
+  app
+    .try_state::<SettingsServiceState>()
+    .and_then(|state| state.saved.lock().ok().map(|saved| saved.clone()))
+    .unwrap_or_else(|| load_settings(app).unwrap_or_default())
+}
+
+fn current_overrides(app: &tauri::AppHandle) -> Map<String, Value> {
+  app
+    .try_state::<SettingsServiceState>()
+    .and_then(|state| {
+      state
+        .overrides
+        .lock()
+        .ok()
+        .map(|overrides| overrides.clone())
+    })
+    .unwrap_or_default()
+}
+
+/// Copies the current settings, lets `change` edit the copy and applies the result.
+pub(crate) fn modify_settings(
+  app: &tauri::AppHandle,
+  change: impl FnOnce(&mut AppSettings) + Send + 'static,
+) -> Result<AppSettings> {
+  let applied = run_update(app, move |app| {
+    let mut next = current_settings(app);
+    change(&mut next);
+    apply_requested(app, next)
+  })?;
+  sync_autostart(app, applied)
+}
+
+/// Applies every side effect that differs between the running state and `requested`,
+/// persists what actually took effect and notifies the menus and all windows. An override
+/// is only saved once the request changes that setting. Changes to settings locked by
+/// policy are refused.
+pub(crate) fn replace_settings(
+  app: &tauri::AppHandle,
+  requested: AppSettings,
+) -> Result<AppSettings> {
+  let applied = run_update(app, move |app| apply_requested(app, requested))?;
+  sync_autostart(app, applied)
+}
+
+fn apply_requested(app: &tauri::AppHandle, requested: AppSettings) -> Result<AppSettings> {
+  if let Some(key) = current_policy(app).violation(&requested) {
+    return Err(anyhow!(locked_error(key)));
+  }
+  // Checked before any side effect, so a refused change leaves nothing half applied.
+  ensure_settings_writable(app)?;
+  let saved = saved_settings(app);
+  let mut overrides = current_overrides(app);
+  let running = with_overrides(&saved, &overrides);
+  drop_changed_overrides(&running, &requested, &mut overrides);
+  let applied = apply_side_effects(app, &running, requested);
+  let next = restore_overridden(&applied, &saved, &overrides);
+  save_settings(app, &next)?;
+  publish(app, &next, overrides, &applied);
+  Ok(applied)
+}
+
+/// Applies settings that were already written to disk by someone else. Locked values are
+/// enforced, and the file is only rewritten when something could not be applied as written.
+pub(crate) fn apply_external_settings(
+  app: &tauri::AppHandle,
+  mut loaded: AppSettings,
+) -> Result<Option<AppSettings>> {
+  let applied = run_update(app, move |app| {
+    current_policy(app).enforce(&mut loaded);
+    if saved_settings(app) == loaded {
+      return Ok(None);
+    }
+    let overrides = current_overrides(app);
+    let running = current_settings(app);
+    let applied = apply_side_effects(app, &running, with_overrides(&loaded, &overrides));
+    let next = restore_overridden(&applied, &loaded, &overrides);
+    // A file from a newer release is applied as read but never rewritten.
+    if next != loaded && ensure_settings_writable(app).is_ok() {
+      save_settings(app, &next)?;
+    }
+    publish(app, &next, overrides, &applied);
+    Ok(Some(applied))
+  })?;
+  applied
+    .map(|applied| sync_autostart(app, applied))
+    .transpose()
+}
+
+/// Runs `update` on the main thread under the update lock and waits for its result.
+/// The side effects touch menus and windows, which wait for the main thread when used
+/// from anywhere else; holding the lock there instead cannot deadlock against it.
+fn run_update<T: Send + 'static>(
+  app: &tauri::AppHandle,
+  update: impl FnOnce(&tauri::AppHandle) -> Result<T> + Send + 'static,
+) -> Result<T> {
+  let (sender, receiver) = mpsc::channel();
+  let app_handle = app.clone();
+  app.run_on_main_thread(move || {
+    let state = app_handle.try_state::<SettingsServiceState>();
+    let _guard = state.as_ref().map(|state| {
+      state
+        .update
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+    });
+    let _ = sender.send(update(&app_handle));
+  })?;
+  receiver
+    .recv()
+    .map_err(|_| anyhow!("settings update was dropped"))?
+}
+
+/// Brings the autostart registry entry in line with the running settings. This runs on the
+/// caller's thread after the update, since the registry write can stall and the main thread
+/// must not wait for it. When the entry cannot be changed, the settings follow what it holds.
+fn sync_autostart(app: &tauri::AppHandle, applied: AppSettings) -> Result<AppSettings> {
+  let state = app.try_state::<SettingsServiceState>();
+  let guard = state.as_ref().map(|state| {
+    state
+      .autostart
+      .lock()
+      .unwrap_or_else(|error| error.into_inner())
+  });
+  let target = current_settings(app).auto_start;
+  let current = app.autolaunch().is_enabled().unwrap_or(target);
+  if current == target {
+    return Ok(applied);
+  }
+  let actual = apply_autostart(app, target, current);
+  drop(guard);
+  if actual == target {
+    return Ok(applied);
+  }
+  run_update(app, move |app| {
+    let mut next = current_settings(app);
+    next.auto_start = actual;
+    apply_requested(app, next)
+  })
+}
+
+/// Brings the windows, menus and logger from `previous` to `requested` and returns what
+/// took effect. Autostart is left to `sync_autostart`.
+fn apply_side_effects(
+  app: &tauri::AppHandle,
+  previous: &AppSettings,
+  requested: AppSettings,
+) -> AppSettings {
+  let mut next = requested;
+  next.schema_version = SETTINGS_SCHEMA_VERSION;
+
+  if is_tray_enabled(app) != next.start_minimized {
+    next.start_minimized = set_tray_enabled(app, next.start_minimized);
+  }
+
+  if is_content_protected(app) != next.content_protection {
+    apply_content_protection_state(app, next.content_protection);
+  }
+
+  if previous.log_level != next.log_level {
+    apply_log_level(next.log_level);
+  }
+
+  next
+}
+
+fn publish(
+  app: &tauri::AppHandle,
+  saved: &AppSettings,
+  overrides: Map<String, Value>,
+  running: &AppSettings,
+) {
+  if let Some(state) = app.try_state::<SettingsServiceState>() {
+    if let Ok(mut current) = state.saved.lock() {
+      *current = saved.clone();
+    }
+    if let Ok(mut current) = state.overrides.lock() {
+      *current = overrides;
+    }
+  }
+  sync_menu_checks(app, running);
+  let _ = app.emit(SETTINGS_CHANGED_EVENT, running);
+}
+
+fn with_overrides(settings: &AppSettings, overrides: &Map<String, Value>) -> AppSettings {
+  if overrides.is_empty() {
+    return settings.clone();
+  }
+  let Ok(Value::Object(mut document)) = serde_json::to_value(settings) else {
+    return settings.clone();
+  };
+  document.extend(overrides.clone());
+  settings_from_value(Value::Object(document)).unwrap_or_else(|_| settings.clone())
+}
+
+// A setting the request changes from what the app runs with is the user's choice from now on.
+fn drop_changed_overrides(
+  running: &AppSettings,
+  requested: &AppSettings,
+  overrides: &mut Map<String, Value>,
+) {
+  let (Ok(Value::Object(running)), Ok(Value::Object(requested))) = (
+    serde_json::to_value(running),
+    serde_json::to_value(requested),
+  ) else {
+    return;
+  };
+  overrides.retain(|key, _| running.get(key) == requested.get(key));
+}
+
+// `settings` with every overridden key put back to what `saved` holds, so only the user's own
+// values reach the file.
+fn restore_overridden(
+  settings: &AppSettings,
+  saved: &AppSettings,
+  overrides: &Map<String, Value>,
+) -> AppSettings {
+  let (Ok(Value::Object(mut document)), Ok(Value::Object(saved))) =
+    (serde_json::to_value(settings), serde_json::to_value(saved))
+  else {
+    return settings.clone();
+  };
+  for key in overrides.keys() {
+    match saved.get(key) {
+      Some(value) => document.insert(key.clone(), value.clone()),
+      None => document.remove(key),
+    };
+  }
+  settings_from_value(Value::Object(document)).unwrap_or_else(|_| settings.clone())
+}
+
+fn apply_autostart(app: &tauri::AppHandle, target: bool, current: bool) -> bool {
+  info!("[autostart] toggle current={current} target={target}");
+  let result = if target {
+    app.autolaunch().enable()
+  } else {
+    app.autolaunch().disable()
+  };
+  if let Err(error) = result {
+    warn!("[autostart] update failed: {error:#}");
+  }
+  app.autolaunch().is_enabled().unwrap_or(current)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn overrides(value: Value) -> Map<String, Value> {
+    match value {
+      Value::Object(values) => values,
+      _ => unreachable!(),
+    }
+  }
+
+  #[test]
+  fn overrides_shape_the_run_but_not_the_file() {
+    let saved = AppSettings::default();
+    let mut overrides = overrides(json!({ "startMinimized": true, "contentProtection": false }));
+    let running = with_overrides(&saved, &overrides);
+    assert!(running.start_minimized && !running.content_protection);
+
+    // An unrelated change keeps both overrides and saves neither of them.
+    let mut requested = running.clone();
+    requested.auto_start = true;
+    drop_changed_overrides(&running, &requested, &mut overrides);
+    assert_eq!(overrides.len(), 2);
+    let next = restore_overridden(&requested, &saved, &overrides);
+    assert!(next.auto_start);
+    assert!(!next.start_minimized && next.content_protection);
+
+    // Changing an overridden setting makes it the user's own value.
+    let running = with_overrides(&next, &overrides);
+    let mut requested = running.clone();
+    requested.start_minimized = false;
+    drop_changed_overrides(&running, &requested, &mut overrides);
+    assert!(!overrides.contains_key("startMinimized"));
+    let next = restore_overridden(&requested, &next, &overrides);
+    assert!(!next.start_minimized && next.content_protection);
+    assert!(!with_overrides(&next, &overrides).content_protection);
+  }
+}
@@ -0,0 +1,80 @@
+use crate::settings::{read_settings_file, settings_path};
+use crate::settings_service::apply_external_settings;
+use anyhow::Result;
+use log::{debug, info, warn};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+use tauri::Manager;
+
+// Editors and our own atomic writes emit several events per save; wait for them to settle.
+const SETTLE_DELAY: Duration = Duration::from_millis(300);
+
+struct SettingsWatcherState {
+  _watcher: RecommendedWatcher,
+}
+
+pub(crate) fn start_settings_watcher(app: &tauri::AppHandle) -> Result<()> {
+  let path = settings_path(app)?;
+  let Some(dir) = path.parent().map(Path::to_path_buf) else {
+    return Ok(());
+  };
+  fs::create_dir_all(&dir)?;
+
+  let (tx, rx) = mpsc::channel();
+  let mut watcher = notify::recommended_watcher(tx)?;
+  watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+  info!("[settings] watching {}", path.display());
+
+  let app_handle = app.clone();
+  std::thread::spawn(move || loop {
+    match rx.recv() {
+      Ok(Ok(event)) if touches_settings(&event, &path) => {}
+      Ok(Ok(_)) => continue,
+      Ok(Err(error)) => {
+        warn!("[settings] watch error: {error:#}");
+        continue;
+      }
+      Err(_) => return,
+    }
+    loop {
+      match rx.recv_timeout(SETTLE_DELAY) {
+        Ok(_) => continue,
+        Err(RecvTimeoutError::Timeout) => break,
+        Err(RecvTimeoutError::Disconnected) => return,
+      }
+    }
+    reload_settings(&app_handle, &path);
+  });
+
+  app.manage(SettingsWatcherState { _watcher: watcher });
+  Ok(())
+}
+
+fn touches_settings(event: &notify::Event, path: &Path) -> bool {
+  event.paths.iter().any(|changed| changed == path)
+}
+
+fn reload_settings(app: &tauri::AppHandle, path: &Path) {
+  if !path.is_file() {
+    debug!(
+      "[settings] {} removed, keeping current settings",
+      path.display()
+    );
+    return;
+  }
+  let loaded = match read_settings_file(path) {
+    Ok(settings) => settings,
+    Err(error) => {
+      warn!("[settings] external edit rejected: {error:#}");
+      return;
+    }
+  };
+  match apply_external_settings(app, loaded) {
+    Ok(Some(_)) => info!("[settings] reloaded after external edit"),
+    Ok(None) => {}
+    Err(error) => warn!("[settings] reload failed: {error:#}"),
+  }
+}
@@ -0,0 +1,465 @@
+#[cfg(target_os = "windows")]
+use crate::cli::cli_args;
+#[cfg(target_os = "windows")]
+use crate::config::load_config;
+#[cfg(target_os = "windows")]
+use crate::extensions::{
+  import_crx, install_extensions_in_webview, navigate_to_extension, prepare_extensions,
+  schedule_cookie_snapshots, ExtensionSetup, PreparedExtension,
+};
+#[cfg(target_os = "windows")]
+use crate::recovery::{begin_extension_startup, finish_extension_startup};
+#[cfg(target_os = "windows")]
+use anyhow::anyhow;
+use anyhow::Result;
+use log::{info, warn};
+use serde::Serialize;
+#[cfg(target_os = "windows")]
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+#[cfg(target_os = "windows")]
+use tauri::webview::PlatformWebview;
+use tauri::{Emitter, Manager};
+#[cfg(target_os = "windows")]
+use tauri_plugin_dialog::DialogExt;
+
+/// Sent to the main window whenever a stage changes; the payload is its `StageReport`.
+pub(crate) const STARTUP_PROGRESS_EVENT: &str = "startup-progress";
+
+/// The steps of a launch, in the order they run. The extension stages run again when the
+/// recovery screen retries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum StartupStage {
+  /// Settings file, policy and overrides.
+  Settings,
+  /// The maintenance queue, such as a profile reset, then scheduled backups.
+  Maintenance,
+  Window,
+  Tray,
+  /// The app update check. Runs in the background and may finish after everything else.
+  Updater,
+  /// Downloading, updating and unpacking the managed extensions, after any requested repair.
+  Prepare,
+  /// Installing them into the main window's webview.
+  Install,
+  /// Opening the primary extension's page.
+  Navigate,
+  CookieSchedule,
+}
+
+impl StartupStage {
+  const ALL: [StartupStage; 9] = [
+    StartupStage::Settings,
+    StartupStage::Maintenance,
+    StartupStage::Window,
+    StartupStage::Tray,
+    StartupStage::Updater,
+    StartupStage::Prepare,
+    StartupStage::Install,
+    StartupStage::Navigate,
+    StartupStage::CookieSchedule,
+  ];
+
+  /// Everything that needs the extension files on disk.
+  pub(crate) const EXTENSION: [StartupStage; 4] = [
+    StartupStage::Prepare,
+    StartupStage::Install,
+    StartupStage::Navigate,
+    StartupStage::CookieSchedule,
+  ];
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum StageStatus {
+  Pending,
+  Running,
+  Succeeded,
+  Failed,
+  Skipped,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StageReport {
+  stage: StartupStage,
+  status: StageStatus,
+  /// Milliseconds after launch at which the stage last started.
+  started_ms: Option<u64>,
+  duration_ms: Option<u64>,
+  /// Failure message, or why the stage was skipped.
+  error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StartupReport {
+  /// Seconds since the Unix epoch.
+  started_at: u64,
+  elapsed_ms: u64,
+  stages: Vec<StageReport>,
+}
+
+/// One report per stage, in pipeline order; a rerun overwrites the earlier result.
+struct StageLog(Vec<StageReport>);
+
+impl StageLog {
+  fn new() -> Self {
+    Self(
+      StartupStage::ALL
+        .iter()
+        .map(|&stage| StageReport {
+          stage,
+          status: StageStatus::Pending,
+          started_ms: None,
+          duration_ms: None,
+          error: None,
+        })
+        .collect(),
+    )
+  }
+
+  fn update(
+    &mut self,
+    stage: StartupStage,
+    update: impl FnOnce(&mut StageReport),
+  ) -> Option<StageReport> {
+    let report = self.0.iter_mut().find(|report| report.stage == stage)?;
+    update(report);
+    Some(report.clone())
+  }
+
+  fn start(&mut self, stage: StartupStage, at_ms: u64) -> Option<StageReport> {
+    self.update(stage, |report| {
+      report.status = StageStatus::Running;
+      report.started_ms = Some(at_ms);
+      report.duration_ms = None;
+      report.error = None;
+    })
+  }
+
+  fn finish(
+    &mut self,
+    stage: StartupStage,
+    duration_ms: u64,
+    error: Option<String>,
+  ) -> Option<StageReport> {
+    self.update(stage, |report| {
+      report.status = if error.is_some() {
+        StageStatus::Failed
+      } else {
+        StageStatus::Succeeded
+      };
+      report.duration_ms = Some(duration_ms);
+      report.error = error;
+    })
+  }
+
+  fn skip(&mut self, stage: StartupStage, reason: &str) -> Option<StageReport> {
+    self.update(stage, |report| {
+      report.status = StageStatus::Skipped;
+      report.started_ms = None;
+      report.duration_ms = None;
+      report.error = Some(reason.to_string());
+    })
+  }
+}
+
+pub(crate) struct StartupState {
+  launched: Instant,
+  launched_at: u64,
+  stages: Mutex<StageLog>,
+}
+
+/// Must run first in `setup`, so every stage is recorded.
+pub(crate) fn init_startup_state(app: &tauri::AppHandle) {
+  app.manage(StartupState {
+    launched: Instant::now(),
+    launched_at: SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_secs(),
+    stages: Mutex::new(StageLog::new()),
+  });
+}
+
+fn record(app: &tauri::AppHandle, change: impl FnOnce(&mut StageLog, u64) -> Option<StageReport>) {
+  let Some(state) = app.try_state::<StartupState>() else {
+    return;
+  };
+  let at_ms = state.launched.elapsed().as_millis() as u64;
+  let report = change(
+    &mut state
+      .stages
+      .lock()
+      .unwrap_or_else(|error| error.into_inner()),
+    at_ms,
+  );
+  if let Some(report) = report {
+    let _ = app.emit_to("main", STARTUP_PROGRESS_EVENT, report);
+  }
+}
+
+/// A running stage; `finish` records how it ended.
+pub(crate) struct StageTimer {
+  app: tauri::AppHandle,
+  stage: StartupStage,
+  started: Instant,
+}
+
+impl StageTimer {
+  /// Records `result` and passes it through, so the caller decides what a failure means for the
+  /// stages after it.
+  pub(crate) fn finish<T>(self, result: Result<T>) -> Result<T> {
+    let duration_ms = self.started.elapsed().as_millis() as u64;
+    let stage = self.stage;
+    let error = match &result {
+      Ok(_) => {
+        info!("[startup] {stage:?} succeeded in {duration_ms}ms");
+        None
+      }
+      Err(error) => {
+        warn!("[startup] {stage:?} failed after {duration_ms}ms: {error:#}");
+        Some(format!("{error:#}"))
+      }
+    };
+    record(&self.app, |log, _| log.finish(stage, duration_ms, error));
+    result
+  }
+}
+
+pub(crate) fn begin_stage(app: &tauri::AppHandle, stage: StartupStage) -> StageTimer {
+  record(app, |log, at_ms| log.start(stage, at_ms));
+  StageTimer {
+    app: app.clone(),
+    stage,
+    started: Instant::now(),
+  }
+}
+
+pub(crate) fn run_stage<T>(
+  app: &tauri::AppHandle,
+  stage: StartupStage,
+  run: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+  begin_stage(app, stage).finish(run())
+}
+
+pub(crate) fn skip_stages(app: &tauri::AppHandle, stages: &[StartupStage], reason: &str) {
+  for &stage in stages {
+    info!("[startup] {stage:?} skipped: {reason}");
+    record(app, |log, _| log.skip(stage, reason));
+  }
+}
+
+/// Extension files to fix before the extensions are prepared again; see `recovery`.
+pub(crate) type Repair = Box<dyn FnOnce(&tauri::AppHandle) -> Result<()> + Send>;
+
+/// Runs the extension stages for the main window: prepare on a worker thread, then install,
+/// navigate and the cookie schedule on the main thread. A failure skips the stages after it and
+/// shows the recovery screen. `--import-crx` is installed as part of the first prepare only.
+#[cfg(target_os = "windows")]
+pub(crate) fn start_main_extensions(app_handle: &tauri::AppHandle) {
+  if !begin_extension_startup(app_handle) {
+    warn!("[startup] extension stages already running");
+    return;
+  }
+  let import = cli_args()
+    .import_crx
+    .clone()
+    .map(|path| Box::new(move |app: &tauri::AppHandle| import_crx(app, &path)) as Repair);
+  spawn_main_extensions(app_handle, import);
+}
+
+/// `start_main_extensions` for callers that already began the extension startup.
+#[cfg(target_os = "windows")]
+pub(crate) fn spawn_main_extensions(app_handle: &tauri::AppHandle, repair: Option<Repair>) {
+  let app_handle = app_handle.clone();
+  std::thread::spawn(move || {
+    let prepared = run_stage(&app_handle, StartupStage::Prepare, || {
+      if let Some(repair) = repair {
+        repair(&app_handle)?;
+      }
+      prepare_extensions(&app_handle)
+    });
+    let ExtensionSetup {
+      extensions,
+      user_dir,
+      updated,
+      update_failed,
+    } = match prepared {
+      Ok(setup) => setup,
+      Err(error) => {
+        fail_extension_stage(&app_handle, StartupStage::Prepare, error);
+        return;
+      }
+    };
+    let cookie_domains = load_config(&app_handle)
+      .map(|config| config.cookie_persistence)
+      .unwrap_or_default()
+      .domains;
+
+    let handle_for_task = app_handle.clone();
+    let _ = app_handle.run_on_main_thread(move || {
+      let Some(window) = handle_for_task.get_webview_window("main") else {
+        fail_before_install(&handle_for_task, anyhow!("main window not found"));
+        return;
+      };
+      let handle_for_webview = handle_for_task.clone();
+      if let Err(error) = window.with_webview(move |webview| {
+        open_in_webview(
+          &handle_for_webview,
+          &webview,
+          extensions,
+          user_dir,
+          cookie_domains,
+        );
+      }) {
+        fail_before_install(&handle_for_task, anyhow!("with_webview failed: {error}"));
+        return;
+      }
+
+      if update_failed {
+        handle_for_task
+          .dialog()
+          .message("アップデートに失敗しました。")
+          .title("更新失敗")
+          .show(|_| {});
+      } else if updated {
+        let app_handle = handle_for_task.clone();
+        handle_for_task
+          .dialog()
+          .message("拡張機能を更新しました。再起動しますか？")
+          .title("更新完了")
+          .show(move |confirmed| {
+            if confirmed {
+              app_handle.restart();
+            }
+          });
+      }
+    });
+  });
+}
+
+#[cfg(target_os = "windows")]
+fn open_in_webview(
+  app: &tauri::AppHandle,
+  webview: &PlatformWebview,
+  extensions: Vec<PreparedExtension>,
+  user_dir: PathBuf,
+  cookie_domains: Vec<String>,
+) {
+  let page_url = match run_stage(app, StartupStage::Install, || {
+    install_extensions_in_webview(webview, extensions, user_dir)
+  }) {
+    Ok(page_url) => page_url,
+    Err(error) => return fail_extension_stage(app, StartupStage::Install, error),
+  };
+  if let Err(error) = run_stage(app, StartupStage::Navigate, || {
+    navigate_to_extension(webview, &page_url, &cookie_domains)
+  }) {
+    return fail_extension_stage(app, StartupStage::Navigate, error);
+  }
+  finish_extension_startup(app, Ok(()));
+  // Only after a successful open, so a retry never starts a second schedule.
+  let _ = run_stage(app, StartupStage::CookieSchedule, || {
+    schedule_cookie_snapshots(app, "main");
+    Ok(())
+  });
+}
+
+/// Records an install that failed before reaching the webview.
+#[cfg(target_os = "windows")]
+fn fail_before_install(app: &tauri::AppHandle, error: anyhow::Error) {
+  let error = run_stage::<()>(app, StartupStage::Install, || Err(error)).unwrap_err();
+  fail_extension_stage(app, StartupStage::Install, error);
+}
+
+/// Skips the extension stages after `stage` and hands the error to the recovery screen.
+#[cfg(target_os = "windows")]
+fn fail_extension_stage(app: &tauri::AppHandle, stage: StartupStage, error: anyhow::Error) {
+  let after: Vec<StartupStage> = StartupStage::EXTENSION
+    .into_iter()
+    .skip_while(|&other| other != stage)
+    .skip(1)
+    .collect();
+  skip_stages(app, &after, &format!("{stage:?} failed"));
+  finish_extension_startup(app, Err((stage, error)));
+}
+
+#[tauri::command]
+pub(crate) fn get_startup_report(app_handle: tauri::AppHandle) -> Result<StartupReport, String> {
+  let state = app_handle
+    .try_state::<StartupState>()
+    .ok_or_else(|| "startup state unavailable".to_string())?;
+  let stages = state
+    .stages
+    .lock()
+    .unwrap_or_else(|error| error.into_inner())
+    .0
+    .clone();
+  Ok(StartupReport {
+    started_at: state.launched_at,
+    elapsed_ms: state.launched.elapsed().as_millis() as u64,
+    stages,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn status(log: &StageLog, stage: StartupStage) -> StageStatus {
+    log
+      .0
+      .iter()
+      .find(|report| report.stage == stage)
+      .unwrap()
+      .status
+  }
+
+  #[test]
+  fn lists_every_stage_as_pending_in_order() {
+    let log = StageLog::new();
+    let stages: Vec<_> = log.0.iter().map(|report| report.stage).collect();
+
+    assert_eq!(stages, StartupStage::ALL);
+    assert!(log
+      .0
+      .iter()
+      .all(|report| report.status == StageStatus::Pending));
+  }
+
+  #[test]
+  fn records_timing_and_failure() {
+    let mut log = StageLog::new();
+    log.start(StartupStage::Tray, 120);
+    let report = log
+      .finish(StartupStage::Tray, 15, Some("no icon".to_string()))
+      .unwrap();
+
+    assert_eq!(report.status, StageStatus::Failed);
+    assert_eq!(report.started_ms, Some(120));
+    assert_eq!(report.duration_ms, Some(15));
+    assert_eq!(report.error.as_deref(), Some("no icon"));
+    assert_eq!(status(&log, StartupStage::Updater), StageStatus::Pending);
+  }
+
+  #[test]
+  fn rerun_replaces_an_earlier_result() {
+    let mut log = StageLog::new();
+    log.start(StartupStage::Prepare, 10);
+    log.finish(StartupStage::Prepare, 5, Some("offline".to_string()));
+    log.skip(StartupStage::Install, "Prepare failed");
+
+    log.start(StartupStage::Prepare, 900);
+    let report = log.finish(StartupStage::Prepare, 40, None).unwrap();
+
+    assert_eq!(report.status, StageStatus::Succeeded);
+    assert_eq!(report.started_ms, Some(900));
+    assert_eq!(report.error, None);
+    assert_eq!(status(&log, StartupStage::Install), StageStatus::Skipped);
+  }
+}
@@ -0,0 +1,288 @@
+use crate::cache_cleanup::{
+  category_size, clear_cache_categories, dir_size, format_bytes, CacheCategory,
+};
+use crate::config::StorageMonitorConfig;
+use crate::extensions::read_manifest_version;
+use crate::maintenance::{enqueue_maintenance, MaintenanceOp};
+use crate::paths::{extensions_root, log_dir, profile_backups_dir, profile_dir};
+use anyhow::Result;
+use log::{debug, info, warn};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
+
+// The first check waits until startup work (extension updates, scheduled backup) has settled.
+const FIRST_CHECK_DELAY: Duration = Duration::from_secs(5 * 60);
+
+// WebView2 nests almost everything under these; listing them as one entry says nothing useful.
+const EXPANDED_PROFILE_DIRS: [&str; 2] = ["EBWebView", "EBWebView/Default"];
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StorageEntry {
+  name: String,
+  bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CacheUsage {
+  category: CacheCategory,
+  bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExtensionUsage {
+  slot: String,
+  version: Option<String>,
+  bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StorageReport {
+  total_bytes: u64,
+  profile_bytes: u64,
+  profile: Vec<StorageEntry>,
+  /// Space the safe cleanup can reclaim; already counted in `profile`.
+  caches: Vec<CacheUsage>,
+  extensions: Vec<ExtensionUsage>,
+  logs: Vec<StorageEntry>,
+  backups_bytes: u64,
+}
+
+impl StorageReport {
+  fn log_bytes(&self) -> u64 {
+    self.logs.iter().map(|entry| entry.bytes).sum()
+  }
+}
+
+pub(crate) fn build_storage_report(app: &tauri::AppHandle) -> Result<StorageReport> {
+  let profile = profile_dir(app)?;
+  let profile_entries = profile_breakdown(&profile)?;
+  let profile_bytes = profile_entries.iter().map(|entry| entry.bytes).sum();
+  let caches = CacheCategory::ALL
+    .iter()
+    .map(|category| CacheUsage {
+      category: *category,
+      bytes: category_size(&profile, *category),
+    })
+    .collect();
+
+  let mut extensions = Vec::new();
+  let extensions_dir = extensions_root(app)?;
+  if extensions_dir.is_dir() {
+    for entry in fs::read_dir(&extensions_dir)? {
+      let path = entry?.path();
+      if !path.is_dir() {
+        continue;
+      }
+      extensions.push(ExtensionUsage {
+        slot: file_name(&path),
+        version: read_manifest_version(&path),
+        bytes: dir_size(&path),
+      });
+    }
+  }
+  extensions.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+
+  let logs = log_files(app)
+    .into_iter()
+    .map(|(path, bytes, _)| StorageEntry {
+      name: file_name(&path),
+      bytes,
+    })
+    .collect();
+  let backups_bytes = dir_size(&profile_backups_dir(app)?);
+
+  let mut report = StorageReport {
+    total_bytes: 0,
+    profile_bytes,
+    profile: profile_entries,
+    caches,
+    extensions,
+    logs,
+    backups_bytes,
+  };
+  report.total_bytes = report.profile_bytes
+    + report
+      .extensions
+      .iter()
+      .map(|entry| entry.bytes)
+      .sum::<u64>()
+    + report.log_bytes()
+    + report.backups_bytes;
+  Ok(report)
+}
+
+/// Clears every cache category and the rotated log files. Cache directories the webview keeps
+/// open are queued for the next startup.
+pub(crate) fn cleanup_safe_storage(app: &tauri::AppHandle) -> Result<u64> {
+  let cache = clear_cache_categories(&profile_dir(app)?, &CacheCategory::ALL)?;
+  if !cache.incomplete.is_empty() {
+    let op = MaintenanceOp::ClearCache {
+      categories: cache.incomplete.clone(),
+    };
+    if let Err(error) = enqueue_maintenance(app, op) {
+      warn!("[storage] defer cache cleanup failed: {error:#}");
+    }
+  }
+
+  // KeepAll rotation never deletes; everything but the newest file is a finished log.
+  let mut reclaimed = cache.reclaimed_bytes;
+  for (path, bytes, _) in log_files(app).into_iter().skip(1) {
+    match fs::remove_file(&path) {
+      Ok(()) => reclaimed += bytes,
+      Err(error) => debug!("[storage] log {} kept: {error}", path.display()),
+    }
+  }
+  info!("[storage] cleanup reclaimed={reclaimed}");
+  Ok(reclaimed)
+}
+
+pub(crate) fn spawn_storage_monitor(app: &tauri::AppHandle, config: &StorageMonitorConfig) {
+  if config.interval_hours == 0 {
+    return;
+  }
+  let app = app.clone();
+  let interval = Duration::from_secs(config.interval_hours.saturating_mul(60 * 60));
+  let threshold = config.threshold_mb.saturating_mul(1024 * 1024);
+  std::thread::spawn(move || {
+    std::thread::sleep(FIRST_CHECK_DELAY);
+    loop {
+      match build_storage_report(&app) {
+        Ok(report) => {
+          debug!("[storage] total={}", report.total_bytes);
+          if report.total_bytes > threshold {
+            warn!(
+              "[storage] usage {} exceeds threshold {}",
+              report.total_bytes, threshold
+            );
+            // Ask at most once per run; the user already knows after the first prompt.
+            prompt_cleanup(&app, &report);
+            return;
+          }
+        }
+        Err(error) => warn!("[storage] report failed: {error:#}"),
+      }
+      std::thread::sleep(interval);
+    }
+  });
+}
+
+fn prompt_cleanup(app: &tauri::AppHandle, report: &StorageReport) {
+  let reclaimable: u64 =
+    report.caches.iter().map(|entry| entry.bytes).sum::<u64>() + report.log_bytes();
+  let app_handle = app.clone();
+  app
+    .dialog()
+    .message(format!(
+      "データの使用量が {} になりました。\nキャッシュと古いログを削除すると最大 {} を解放できます。ログイン情報は削除されません。",
+      format_bytes(report.total_bytes),
+      format_bytes(reclaimable)
+    ))
+    .title("ストレージの使用量")
+    .buttons(MessageDialogButtons::OkCancelCustom(
+      "クリーンアップ".to_string(),
+      "後で".to_string(),
+    ))
+    .show(move |confirmed| {
+      if !confirmed {
+        return;
+      }
+      std::thread::spawn(move || {
+        if let Err(error) = cleanup_safe_storage(&app_handle) {
+          warn!("[storage] cleanup failed: {error:#}");
+        }
+      });
+    });
+}
+
+fn profile_breakdown(profile: &Path) -> Result<Vec<StorageEntry>> {
+  let mut entries = Vec::new();
+  if profile.is_dir() {
+    collect_profile_entries(profile, Path::new(""), &mut entries)?;
+  }
+  entries.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+  Ok(entries)
+}
+
+fn collect_profile_entries(
+  profile: &Path,
+  relative: &Path,
+  entries: &mut Vec<StorageEntry>,
+) -> Result<()> {
+  for entry in fs::read_dir(profile.join(relative))? {
+    let entry = entry?;
+    let child = relative.join(entry.file_name());
+    let name = child.to_string_lossy().replace('\\', "/");
+    if entry.file_type()?.is_dir() {
+      if EXPANDED_PROFILE_DIRS.contains(&name.as_str()) {
+        collect_profile_entries(profile, &child, entries)?;
+        continue;
+      }
+      entries.push(StorageEntry {
+        bytes: dir_size(&entry.path()),
+        name,
+      });
+    } else {
+      entries.push(StorageEntry {
+        bytes: entry.metadata().map(|meta| meta.len()).unwrap_or(0),
+        name,
+      });
+    }
+  }
+  Ok(())
+}
+
+/// Log files newest first, with their size and modification time.
+fn log_files(app: &tauri::AppHandle) -> Vec<(PathBuf, u64, SystemTime)> {
+  let Ok(dir) = log_dir(app) else {
+    return Vec::new();
+  };
+  let Ok(entries) = fs::read_dir(&dir) else {
+    return Vec::new();
+  };
+  let mut files: Vec<_> = entries
+    .flatten()
+    .filter_map(|entry| {
+      let path = entry.path();
+      if path.extension().and_then(|ext| ext.to_str()) != Some("log") {
+        return None;
+      }
+      let meta = entry.metadata().ok()?;
+      let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+      Some((path, meta.len(), modified))
+    })
+    .collect();
+  files.sort_by(|a, b| b.2.cmp(&a.2));
+  files
+}
+
+fn file_name(path: &Path) -> String {
+  path
+    .file_name()
+    .map(|name| name.to_string_lossy().to_string())
+    .unwrap_or_default()
+}
+
+#[tauri::command]
+pub(crate) async fn get_storage_report(
+  app_handle: tauri::AppHandle,
+) -> Result<StorageReport, String> {
+  tauri::async_runtime::spawn_blocking(move || build_storage_report(&app_handle))
+    .await
+    .map_err(|error| error.to_string())?
+    .map_err(|error| error.to_string())
+}
+
+#[tauri::command]
+pub(crate) async fn cleanup_storage(app_handle: tauri::AppHandle) -> Result<u64, String> {
+  tauri::async_runtime::spawn_blocking(move || cleanup_safe_storage(&app_handle))
+    .await
+    .map_err(|error| error.to_string())?
+    .map_err(|error| error.to_string())
+}
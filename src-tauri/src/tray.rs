@@ -1,7 +1,12 @@
+use crate::profiles::{
+  current_profile_id, handle_profile_menu_action, list_profile_entries, PROFILE_MENU_OPEN_PREFIX,
+  PROFILE_MENU_SWITCH_PREFIX,
+};
+use crate::windowing::show_main_window;
 use anyhow::Result;
 use log::warn;
 use std::sync::Mutex;
-use tauri::menu::{Menu, MenuId, MenuItem};
+use tauri::menu::{CheckMenuItem, Menu, MenuId, MenuItem, PredefinedMenuItem, Submenu};
 use tauri::tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
 use tauri::Manager;
 
@@ -57,9 +62,77 @@ pub(crate) fn is_tray_enabled(app: &tauri::AppHandle) -> bool {
   state.lock().map(|state| state.enabled).unwrap_or(false)
 }
 
-fn build_tray(app: &tauri::AppHandle) -> Result<TrayIcon> {
+/// Rebuilds the tray menu so the profile switcher reflects the current profile list.
+pub(crate) fn refresh_tray_menu(app: &tauri::AppHandle) {
+  let Some(state) = app.try_state::<Mutex<TrayState>>() else {
+    return;
+  };
+  let Ok(state) = state.lock() else {
+    return;
+  };
+  let Some(icon) = state.icon.as_ref() else {
+    return;
+  };
+  match build_tray_menu(app) {
+    Ok(menu) => {
+      if let Err(error) = icon.set_menu(Some(menu)) {
+        warn!("[tray] menu refresh failed: {error:#}");
+      }
+    }
+    Err(error) => warn!("[tray] menu build failed: {error:#}"),
+  }
+}
+
+fn build_tray_menu(app: &tauri::AppHandle) -> Result<Menu<tauri::Wry>> {
+  let current = current_profile_id(app);
+  let profiles = list_profile_entries(app);
+  let switch_items = profiles
+    .iter()
+    .map(|profile| {
+      CheckMenuItem::with_id(
+        app,
+        MenuId::new(format!("{PROFILE_MENU_SWITCH_PREFIX}{}", profile.id)),
+        &profile.name,
+        true,
+        profile.id == current,
+        None::<&str>,
+      )
+    })
+    .collect::<tauri::Result<Vec<_>>>()?;
+  let open_items = profiles
+    .iter()
+    .filter(|profile| profile.id != current)
+    .map(|profile| {
+      MenuItem::with_id(
+        app,
+        MenuId::new(format!("{PROFILE_MENU_OPEN_PREFIX}{}", profile.id)),
+        &profile.name,
+        true,
+        None::<&str>,
+      )
+    })
+    .collect::<tauri::Result<Vec<_>>>()?;
+  let switch_refs: Vec<&dyn tauri::menu::IsMenuItem<tauri::Wry>> = switch_items
+    .iter()
+    .map(|item| item as &dyn tauri::menu::IsMenuItem<tauri::Wry>)
+    .collect();
+  let open_refs: Vec<&dyn tauri::menu::IsMenuItem<tauri::Wry>> = open_items
+    .iter()
+    .map(|item| item as &dyn tauri::menu::IsMenuItem<tauri::Wry>)
+    .collect();
+  let switch_menu = Submenu::with_items(app, "プロファイルを切り替え", true, &switch_refs)?;
+  let open_menu =
+    Submenu::with_items(app, "別ウィンドウで開く", !open_refs.is_empty(), &open_refs)?;
+  let separator = PredefinedMenuItem::separator(app)?;
   let quit = MenuItem::with_id(app, MenuId::new(TRAY_QUIT_ID), "閉じる", true, None::<&str>)?;
-  let menu = Menu::with_items(app, &[&quit])?;
+  Ok(Menu::with_items(
+    app,
+    &[&switch_menu, &open_menu, &separator, &quit],
+  )?)
+}
+
+fn build_tray(app: &tauri::AppHandle) -> Result<TrayIcon> {
+  let menu = build_tray_menu(app)?;
 
   let mut builder = TrayIconBuilder::new()
     .menu(&menu)
@@ -67,6 +140,10 @@ fn build_tray(app: &tauri::AppHandle) -> Result<TrayIcon> {
     .on_menu_event(|app, event| {
       if event.id() == TRAY_QUIT_ID {
         app.exit(0);
+        return;
+      }
+      if let Some(Err(error)) = handle_profile_menu_action(app, event.id().as_ref()) {
+        warn!("[tray] {error}");
       }
     })
     .on_tray_icon_event(|tray, event| {
@@ -76,12 +153,7 @@ fn build_tray(app: &tauri::AppHandle) -> Result<TrayIcon> {
         ..
       } = event
       {
-        let app_handle = tray.app_handle();
-        if let Some(window) = app_handle.get_webview_window("main") {
-          let _ = window.unminimize();
-          let _ = window.show();
-          let _ = window.set_focus();
-        }
+        show_main_window(tray.app_handle());
       }
     });
 
@@ -1,30 +1,41 @@
+use crate::cli::cli_args;
+use crate::startup::{begin_stage, skip_stages, StartupStage};
+use anyhow::anyhow;
 use log::{debug, info, warn};
 use tauri::AppHandle;
 use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
 use tauri_plugin_updater::{Update, UpdaterExt};
 
 pub fn spawn_update_check(app: &AppHandle) {
+  if cli_args().skips_updates() {
+    skip_stages(app, &[StartupStage::Updater], "--no-update");
+    return;
+  }
   let app = app.clone();
+  let stage = begin_stage(&app, StartupStage::Updater);
   tauri::async_runtime::spawn(async move {
-    let updater = match app.updater() {
-      Ok(updater) => updater,
-      Err(error) => {
-        warn!("[updater] init failed: {error:#}");
-        return;
-      }
-    };
-
-    match updater.check().await {
+    // Failures are logged by the stage.
+    match stage.finish(check_for_update(&app).await) {
       Ok(Some(update)) => {
         info!("[updater] update available: {}", update.version);
         prompt_update(app.clone(), update);
       }
       Ok(None) => debug!("[updater] no update"),
-      Err(error) => warn!("[updater] check failed: {error:#}"),
+      Err(_) => {}
     }
   });
 }
 
+async fn check_for_update(app: &AppHandle) -> anyhow::Result<Option<Update>> {
+  let updater = app
+    .updater()
+    .map_err(|error| anyhow!("init failed: {error}"))?;
+  updater
+    .check()
+    .await
+    .map_err(|error| anyhow!("check failed: {error}"))
+}
+
 fn prompt_update(app: AppHandle, update: Update) {
   let message = build_update_message(&update);
   app
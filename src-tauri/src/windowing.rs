@@ -1,4 +1,11 @@
+use crate::content_protection::{is_content_protected, set_content_protected, store_base_title};
+use crate::deep_link::open_external;
+use log::{debug, error, warn};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(target_os = "windows")]
+use tauri::webview::ScrollBarStyle;
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindow, WebviewWindowBuilder, Wry};
 use url::Url;
 
 static NEXT_WINDOW_ID: AtomicUsize = AtomicUsize::new(1);
@@ -8,6 +15,146 @@ pub(crate) fn next_popup_label() -> String {
   format!("popup-{id}")
 }
 
+/// Brings the main window forward from the tray, the taskbar or behind other windows.
+pub(crate) fn show_main_window(app: &tauri::AppHandle) {
+  if let Some(window) = app.get_webview_window("main") {
+    let _ = window.unminimize();
+    let _ = window.show();
+    let _ = window.set_focus();
+  }
+}
+
+/// Sends external links of a LINE window to `open_external` and opens every other
+/// `window.open` in a popup on the same WebView2 data directory.
+pub(crate) fn with_link_handlers<'a>(
+  builder: WebviewWindowBuilder<'a, Wry, AppHandle>,
+  app_handle: &AppHandle,
+  data_dir: PathBuf,
+) -> WebviewWindowBuilder<'a, Wry, AppHandle> {
+  builder
+    .on_navigation({
+      let app_handle = app_handle.clone();
+      move |url| {
+        debug!("[open] on_navigation url={}", url);
+        if should_open_external(url) {
+          debug!("[open] on_navigation external url={}", url);
+          open_external(&app_handle, url);
+          return false;
+        }
+        true
+      }
+    })
+    .on_new_window({
+      let app_handle = app_handle.clone();
+      move |url, features| {
+        debug!("[open] on_new_window url={} features={:?}", url, features);
+        if should_open_external(&url) {
+          debug!("[open] on_new_window external url={}", url);
+          open_external(&app_handle, &url);
+          return tauri::webview::NewWindowResponse::Deny;
+        }
+
+        let window = match open_popup_window(&app_handle, data_dir.clone(), url, |mut builder| {
+          if let Some(size) = features.size() {
+            builder = builder.inner_size(size.width, size.height);
+          }
+          #[cfg(windows)]
+          {
+            builder = builder.with_environment(features.opener().environment.clone());
+          }
+          builder
+        }) {
+          Ok(window) => window,
+          Err(error) => {
+            error!("[new-window] failed: {error:#}");
+            return tauri::webview::NewWindowResponse::Deny;
+          }
+        };
+
+        tauri::webview::NewWindowResponse::Create { window }
+      }
+    })
+}
+
+/// Opens `url` in a popup window that shares the WebView2 data of `profile_dir`. `configure`
+/// adds what the caller knows, such as the size a page asked for.
+pub(crate) fn open_popup_window<'a>(
+  app_handle: &'a AppHandle,
+  profile_dir: PathBuf,
+  url: Url,
+  configure: impl FnOnce(
+    WebviewWindowBuilder<'a, Wry, AppHandle>,
+  ) -> WebviewWindowBuilder<'a, Wry, AppHandle>,
+) -> tauri::Result<WebviewWindow> {
+  let label = next_popup_label();
+  let popup_label = label.clone();
+  let popup_base_title = url.as_str().to_string();
+  store_base_title(app_handle, popup_label.as_str(), &popup_base_title);
+  // Pages of the host the popup opened stay in it, so an in-app LINE link can load and redirect.
+  let home_host = url.host_str().map(str::to_string);
+
+  let mut builder = WebviewWindowBuilder::new(app_handle, label, WebviewUrl::External(url))
+    .data_directory(profile_dir)
+    .disable_drag_drop_handler()
+    .title(popup_base_title.as_str())
+    .decorations(false)
+    .browser_extensions_enabled(true)
+    .on_navigation({
+      let app_handle = app_handle.clone();
+      move |url| {
+        debug!("[open] popup on_navigation url={}", url);
+        if should_open_external(url) && url.host_str() != home_host.as_deref() {
+          debug!("[open] popup on_navigation external url={}", url);
+          open_external(&app_handle, url);
+          return false;
+        }
+        true
+      }
+    });
+
+  #[cfg(target_os = "windows")]
+  {
+    builder = builder.scroll_bar_style(ScrollBarStyle::FluentOverlay);
+  }
+
+  let window = configure(builder).build()?;
+
+  let protected = is_content_protected(app_handle);
+  let window_for_tasks = window.clone();
+  let app_handle_for_tasks = app_handle.clone();
+  let _ = window.run_on_main_thread(move || {
+    set_content_protected(
+      &window_for_tasks,
+      &popup_label,
+      protected,
+      Some(popup_base_title.as_str()),
+    );
+
+    #[cfg(target_os = "windows")]
+    if let Err(error) = window_for_tasks.with_webview({
+      let app_handle = app_handle_for_tasks.clone();
+      let popup_label = popup_label.clone();
+      move |webview| {
+        if let Err(error) = attach_new_window_handler(&webview) {
+          warn!("[new-window] handler failed: {error:#}");
+        }
+        if let Err(error) = attach_permission_handler(&webview) {
+          warn!("[new-window] permission handler failed: {error:#}");
+        }
+        if let Err(error) =
+          attach_close_requested_handler(app_handle.clone(), &webview, popup_label.clone())
+        {
+          warn!("[new-window] close handler failed: {error:#}");
+        }
+      }
+    }) {
+      error!("[new-window] with_webview failed: {error:#}");
+    }
+  });
+
+  Ok(window)
+}
+
 pub(crate) fn should_open_external(url: &Url) -> bool {
   match url.scheme() {
     "http" | "https" => !is_localhost_url(url),
@@ -27,12 +174,8 @@ fn is_localhost_url(url: &Url) -> bool {
 #[cfg(target_os = "windows")]
 use anyhow::Result;
 #[cfg(target_os = "windows")]
-use log::debug;
-#[cfg(target_os = "windows")]
 use tauri::webview::PlatformWebview;
 #[cfg(target_os = "windows")]
-use tauri::Manager;
-#[cfg(target_os = "windows")]
 use webview2_com::Microsoft::Web::WebView2::Win32::{
   COREWEBVIEW2_PERMISSION_KIND, COREWEBVIEW2_PERMISSION_KIND_NOTIFICATIONS,
   COREWEBVIEW2_PERMISSION_STATE_ALLOW,
@@ -0,0 +1,115 @@
+(() => {
+  // Recovery screen for when the LINE extension cannot be opened. The main window stays on this
+  // page until the extension opens, so a failure is shown here instead of a blank window.
+  const STAGE_LABELS = {
+    prepare: "拡張機能の準備（ダウンロード・更新・修復）に失敗しました。",
+    install: "拡張機能の読み込みに失敗しました。",
+    navigate: "LINE の画面を開けませんでした。"
+  };
+
+  const root = document.getElementById("recovery");
+  const stage = document.getElementById("recovery-stage");
+  const errorBox = document.getElementById("recovery-error");
+  const versions = document.getElementById("recovery-versions");
+  const status = document.getElementById("recovery-status");
+  const buttons = Array.from(root.querySelectorAll("button[data-action]"));
+
+  const getTauriInvoke = () => window.__TAURI__?.core?.invoke;
+
+  let hasPrevious = false;
+
+  const setBusy = (busy, message = "") => {
+    for (const button of buttons) {
+      button.disabled =
+        busy || (button.dataset.action === "rollback" && !hasPrevious);
+    }
+    status.textContent = message;
+  };
+
+  const describeVersions = (extensions) =>
+    extensions
+      .map((extension) => {
+        const current = extension.current ? `v${extension.current}` : "未インストール";
+        const previous = extension.previous
+          ? ` / 前のバージョン: v${extension.previous}`
+          : "";
+        const held = extension.held ? "（固定中）" : "";
+        return `${extension.id}: ${current}${held}${previous}`;
+      })
+      .join("\n");
+
+  const render = (info) => {
+    if (!info?.failure) {
+      root.hidden = true;
+      return;
+    }
+    hasPrevious = info.extensions.some((extension) => !!extension.previous);
+    stage.textContent = STAGE_LABELS[info.failure.stage] ?? "起動に失敗しました。";
+    errorBox.textContent = info.failure.message;
+    versions.textContent = describeVersions(info.extensions);
+    root.hidden = false;
+    setBusy(info.running, info.running ? "処理中…" : "");
+  };
+
+  const refresh = async () => {
+    const invoke = getTauriInvoke();
+    if (!invoke) return;
+    try {
+      render(await invoke("get_recovery_info"));
+    } catch (error) {
+      console.warn("[recovery] get_recovery_info failed", error);
+    }
+  };
+
+  const runStartupAction = async (command, message) => {
+    const invoke = getTauriInvoke();
+    if (!invoke) return;
+    setBusy(true, message);
+    try {
+      // On success the window navigates to LINE; a new failure arrives as an event.
+      await invoke(command);
+    } catch (error) {
+      console.warn(`[recovery] ${command} failed`, error);
+      await refresh();
+    }
+  };
+
+  const actions = {
+    retry: () => runStartupAction("retry_startup", "再試行しています…"),
+    reinstall: () =>
+      runStartupAction("reinstall_extensions", "拡張機能を再インストールしています…"),
+    rollback: () =>
+      runStartupAction("rollback_extensions", "前のバージョンに戻しています…"),
+    resetProfile: async () => {
+      const invoke = getTauriInvoke();
+      if (!invoke) return;
+      try {
+        const confirmed = await invoke("confirm_reset_profile");
+        if (!confirmed) return;
+        await invoke("reset_profile");
+      } catch (error) {
+        console.warn("[recovery] reset profile failed", error);
+      }
+    },
+    openLogs: async () => {
+      const invoke = getTauriInvoke();
+      if (!invoke) return;
+      try {
+        await invoke("open_data_folder", { folder: "logs" });
+      } catch (error) {
+        console.warn("[recovery] open logs failed", error);
+      }
+    }
+  };
+
+  for (const button of buttons) {
+    button.addEventListener("click", () => {
+      actions[button.dataset.action]?.();
+    });
+  }
+
+  window.__TAURI__?.event?.listen("startup-failed", () => {
+    refresh();
+  });
+  refresh();
+})();
@@ -0,0 +1,71 @@
+(() => {
+  // Startup progress while the main window waits for the LINE extension to open.
+  const STAGE_LABELS = {
+    settings: "設定の読み込み",
+    maintenance: "メンテナンス",
+    window: "ウィンドウの作成",
+    tray: "トレイ",
+    updater: "アプリの更新確認",
+    prepare: "拡張機能の準備",
+    install: "拡張機能のインストール",
+    navigate: "LINE を開く",
+    cookieSchedule: "ログイン情報の保存"
+  };
+  const STATUS_MARKS = {
+    pending: "・",
+    running: "…",
+    succeeded: "✓",
+    failed: "✕",
+    skipped: "－"
+  };
+
+  const list = document.getElementById("splash-stages");
+  const items = new Map();
+
+  const getTauriInvoke = () => window.__TAURI__?.core?.invoke;
+
+  const renderStage = (report) => {
+    let item = items.get(report.stage);
+    if (!item) {
+      item = document.createElement("li");
+      const mark = document.createElement("span");
+      mark.className = "mark";
+      const label = document.createElement("span");
+      label.className = "label";
+      label.textContent = STAGE_LABELS[report.stage] ?? report.stage;
+      const detail = document.createElement("span");
+      detail.className = "detail";
+      item.append(mark, label, detail);
+      list.append(item);
+      items.set(report.stage, item);
+    }
+    item.dataset.status = report.status;
+    item.querySelector(".mark").textContent = STATUS_MARKS[report.status] ?? "";
+    const detail = item.querySelector(".detail");
+    detail.textContent =
+      report.durationMs != null ? `${report.durationMs} ms` : "";
+    item.title = report.error ?? "";
+  };
+
+  const refresh = async () => {
+    const invoke = getTauriInvoke();
+    if (!invoke) return;
+    try {
+      const report = await invoke("get_startup_report");
+      for (const stage of report.stages) {
+        renderStage(stage);
+      }
+    } catch (error) {
+      console.warn("[splash] get_startup_report failed", error);
+    }
+  };
+
+  const start = async () => {
+    // Subscribe before fetching, so no change falls between the report and the events.
+    await window.__TAURI__?.event?.listen("startup-progress", (event) => {
+      if (event.payload) renderStage(event.payload);
+    });
+    await refresh();
+  };
+  start();
+})();